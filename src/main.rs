@@ -1,4 +1,4 @@
-use clap::{Parser, Subcommand};
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand, parser::ValueSource};
 use crossterm::{
     event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
     execute,
@@ -7,18 +7,22 @@ use crossterm::{
 use ratatui::{
     Frame, Terminal,
     backend::CrosstermBackend,
+    buffer::Buffer,
     layout::{Alignment, Constraint, Direction, Layout},
     style::{Color, Modifier, Style},
     text::{Line, Span},
-    widgets::{BarChart, Block, Borders, Padding, Paragraph, Wrap},
+    widgets::{
+        BarChart, Block, Borders, List, ListItem, ListState, Padding, Paragraph, Sparkline, Wrap,
+    },
 };
 use serde::{Deserialize, Serialize};
 use std::{
-    collections::HashMap,
+    collections::{HashMap, HashSet},
     fs, io,
     path::{Path, PathBuf},
     time::{Duration, Instant},
 };
+use unicode_segmentation::UnicodeSegmentation;
 
 #[derive(Parser)]
 #[command(name = "typetester")]
@@ -36,22 +40,748 @@ struct Cli {
     #[arg(long)]
     inception: bool,
 
+    /// Practice a run of short quotations from the built-in collection
+    #[arg(long, conflicts_with_all = ["file", "inception"])]
+    quotes: bool,
+
+    /// Practice a snippet of a system manual page, e.g. --man grep
+    #[arg(long, value_name = "PAGE", conflicts_with_all = ["file", "inception", "quotes"])]
+    man: Option<String>,
+
+    /// Drill programming symbols and balanced bracket pairs (reproducible with --seed)
+    #[arg(long, conflicts_with_all = ["file", "inception", "quotes", "man"])]
+    symbols: bool,
+
+    /// Practice code examples from a Rust crate's doc comments: a crate directory,
+    /// or the name of a crate in the local cargo registry
+    #[arg(long, value_name = "CRATE", conflicts_with_all = ["file", "inception", "quotes", "man", "symbols"])]
+    docs: Option<String>,
+
     /// Size of the text chunk to practice with
     #[arg(short, long, value_enum, default_value = "medium")]
     size: ChunkSize,
+
+    /// Exact passage length in characters instead of --size, e.g. 500:700
+    #[arg(long, value_name = "MIN:MAX", conflicts_with = "size")]
+    chars: Option<CharRange>,
+
+    /// Practice an explicit line range of the file (1-based, inclusive), e.g. 40:80
+    #[arg(long, value_name = "START:END", requires = "file")]
+    lines: Option<LineRange>,
+
+    /// Keep leading license headers and shebangs in code snippets
+    #[arg(long)]
+    keep_headers: bool,
+
+    /// Keep the snippet verbatim, including leading/trailing whitespace
+    #[arg(long)]
+    preserve_whitespace: bool,
+
+    /// Rearrange the selected snippet to break memorization
+    #[arg(long, value_enum)]
+    transform: Option<TextTransform>,
+
+    /// Seed for randomized transforms, for reproducible drills
+    #[arg(long, value_name = "N")]
+    seed: Option<u64>,
+
+    /// Type the selected passage N times in a row, one repetition per line block
+    #[arg(long, value_name = "N", default_value_t = 1, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    repeat: usize,
+
+    /// Map curly quotes, dashes, ellipses etc. in loaded files to plain ASCII
+    #[arg(long)]
+    ascii_fold: bool,
+
+    /// Lowercase the passage so no Shift is needed, for raw letter speed
+    #[arg(long)]
+    lowercase: bool,
+
+    /// What to do with control characters (form feed, NUL, ...) in loaded files
+    #[arg(long, value_enum, default_value = "strip")]
+    control_chars: ControlChars,
+
+    /// Zen mode: no analytics, only WPM, accuracy and time are reported
+    #[arg(long)]
+    zen: bool,
+
+    /// Keep only the last N samples of rhythm, errors and per-key latencies;
+    /// error totals and the keystroke log (needed for replay) still cover the
+    /// whole session
+    #[arg(long, value_name = "N", value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    max_samples: Option<usize>,
+
+    /// Run without the alternate screen so the session stays in scrollback
+    #[arg(long)]
+    no_alt_screen: bool,
+
+    /// Show live WPM and accuracy above the text while typing
+    #[arg(long)]
+    live_header: bool,
+
+    /// Don't finish until every skipped character has been typed correctly
+    #[arg(long)]
+    require_clean: bool,
+
+    /// Keyboard layout, used to tell which symbols need Shift/AltGr
+    #[arg(long, value_enum, default_value = "us")]
+    layout: KeyboardLayout,
+
+    /// Ignore the preferences file for this run
+    #[arg(long)]
+    no_preferences: bool,
+
+    /// Print the available keyboard layouts, one per line, and exit
+    #[arg(long)]
+    list_layouts: bool,
+
+    /// Don't read or record session history
+    #[arg(long)]
+    no_history: bool,
+
+    /// Warn instead of freezing when the error buffer is full
+    #[arg(long)]
+    soft_cap: bool,
+
+    /// Check for bursts of near-instant keystrokes that suggest pasted input
+    #[arg(long, value_enum, default_value = "flag")]
+    paste_guard: PasteGuard,
+
+    /// Flash each correctly typed character briefly
+    #[arg(long)]
+    feedback: bool,
+
+    /// End the session on the first error and report how far you got error-free
+    #[arg(long)]
+    sudden_death: bool,
+
+    /// Keep ligature fonts from fusing symbols like -> or != across the cursor
+    /// and error highlighting, by separating them with zero-width non-joiners
+    #[arg(long)]
+    break_ligatures: bool,
+
+    /// Draw this glyph at the cursor position (the finished-text cursor defaults to '|')
+    #[arg(long, value_name = "CHAR")]
+    cursor_char: Option<char>,
+
+    /// Show correctly typed text as this character (e.g. █) instead of the text itself
+    #[arg(long, value_name = "CHAR")]
+    mask_char: Option<char>,
+
+    /// Report WPM separately for code lines and comment lines
+    #[arg(long)]
+    split_wpm: bool,
+
+    /// How to unfreeze after too many errors: backspace them away, or retype the right key
+    #[arg(long, value_enum, default_value = "backspace")]
+    freeze_recovery: FreezeRecovery,
+
+    /// Wrong keys never enter the buffer: they are recorded and the cursor waits for the right one
+    #[arg(long, conflicts_with = "soft_cap")]
+    instant: bool,
+
+    /// Show a pace marker moving through the text at this speed
+    #[arg(long, value_name = "WPM", value_parser = clap::builder::RangedU64ValueParser::<u32>::new().range(1..))]
+    target_wpm: Option<u32>,
+
+    /// Keystrokes slower than this count as hesitations (default 500)
+    #[arg(long, value_name = "MS", value_parser = clap::builder::RangedU64ValueParser::<u64>::new().range(MIN_HESITATION_MS..))]
+    hesitation_ms: Option<u64>,
+
+    /// Type a short passage to measure your pace, then save a matching
+    /// --hesitation-ms and --target-wpm to the preferences file
+    #[arg(long, conflicts_with_all = ["file", "inception", "quotes", "man", "symbols", "docs"])]
+    calibrate: bool,
+
+    /// Pace marker that speeds up from START to END WPM over the passage, e.g. 30:60
+    #[arg(long, value_name = "START:END", conflicts_with = "target_wpm")]
+    ramp: Option<WpmRamp>,
+
+    /// Keep adding snippets to the passage until your rolling WPM holds at or
+    /// above this for 30 seconds, then report how long it took to get there
+    #[arg(long, value_name = "WPM", value_parser = clap::builder::RangedU64ValueParser::<u32>::new().range(1..), conflicts_with_all = ["sudden_death", "calibrate", "zen"])]
+    until_wpm: Option<u32>,
+
+    /// How many keys/errors/digraphs to show in the report charts and lists
+    #[arg(long, value_name = "N", default_value_t = 8, value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..))]
+    top_n: usize,
+
+    /// Show a sparkline of recent keystroke latencies while typing
+    #[arg(long)]
+    sparkline: bool,
+
+    /// Show a column ruler above the text, for indentation and alignment
+    #[arg(long)]
+    ruler: bool,
+
+    /// Leave the guide sidebars and improvement tips out of the report
+    #[arg(long)]
+    quiet: bool,
+
+    /// Practice in focus intervals of this many minutes, each followed by a short break
+    #[arg(long, value_name = "MINUTES", value_parser = clap::builder::RangedU64ValueParser::<u64>::new().range(1..))]
+    pomodoro: Option<u64>,
+
+    /// Replay a synthetic keystroke log this many times and print timings (for development)
+    #[arg(long, hide = true, value_name = "ITERATIONS", value_parser = clap::builder::RangedU64ValueParser::<u32>::new().range(1..))]
+    bench: Option<u32>,
+
+    /// Recompute an exported report from its keystroke log with the current
+    /// analysis options (layout, finger map, ...) and print it as JSON
+    #[arg(long, value_name = "REPORT")]
+    reanalyze: Option<PathBuf>,
+
+    /// Race a ghost replaying an exported report (practices its text unless another source is given)
+    #[arg(long, value_name = "REPORT")]
+    ghost: Option<PathBuf>,
+
+    /// Hide the upcoming text except for a few characters ahead of the cursor
+    #[arg(long)]
+    blind: bool,
+
+    /// Ignore the slowest 5% of keystrokes when averaging latencies
+    #[arg(long)]
+    trim_outliers: bool,
+
+    /// Sample WPM for the report's WPM-over-time chart every N ms instead of every 10 characters
+    #[arg(long, value_name = "MS", value_parser = clap::builder::RangedU64ValueParser::<u64>::new().range(MIN_SAMPLE_INTERVAL_MS..))]
+    sample_interval_ms: Option<u64>,
+
+    /// Append one JSON line per keystroke to this file while typing
+    #[arg(long, value_name = "FILE")]
+    telemetry: Option<PathBuf>,
+
+    /// After finishing, wait for a key before showing the report
+    #[arg(long)]
+    hold_on_complete: bool,
+
+    /// Type a short unrecorded warm-up passage before the real session
+    #[arg(long)]
+    warmup: bool,
+
+    /// Pause the clock after this many seconds without a keystroke (0 disables)
+    #[arg(long, value_name = "SECS", default_value_t = 0)]
+    idle_timeout: u64,
+
+    /// Export a partial report when quitting mid-session with Ctrl+Q
+    #[arg(long)]
+    save_on_quit: bool,
+
+    /// Print the selected snippet and its selection score, then exit
+    #[arg(long)]
+    print: bool,
+
+    /// Label for this run (e.g. "warmup"), shown in the report and export filename
+    #[arg(long, value_name = "TEXT")]
+    label: Option<String>,
+
+    /// JSON file mapping keys to fingers (e.g. {"a": "L-Pinky"}); keys it leaves out keep their QWERTY finger
+    #[arg(long, value_name = "FILE")]
+    finger_map: Option<PathBuf>,
 }
 
-#[derive(clap::ValueEnum, Clone, Debug)]
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
 enum ChunkSize {
     Small,  // ~20-40 lines or 800-1600 characters
     Medium, // ~40-80 lines or 1600-3200 characters
     Large,  // ~80-120 lines or 3200-4800 characters
 }
 
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KeyboardLayout {
+    #[default]
+    Us, // US QWERTY
+    Uk, // UK QWERTY
+    De, // German QWERTZ
+    Fr, // French AZERTY
+}
+
+/// Write the --layout values, one per line, for --list-layouts
+fn list_layouts(out: &mut impl io::Write) -> io::Result<()> {
+    use clap::ValueEnum;
+    for layout in KeyboardLayout::value_variants() {
+        if let Some(value) = layout.to_possible_value() {
+            writeln!(out, "{}", value.get_name())?;
+        }
+    }
+    Ok(())
+}
+
+/// Characters that need Shift or AltGr on the given layout
+fn symbol_chars(layout: KeyboardLayout) -> &'static str {
+    match layout {
+        KeyboardLayout::Us => "!@#$%^&*()_+{}|:\"<>?~",
+        KeyboardLayout::Uk => "!\"£$%^&*()_+{}:@~<>?|¬",
+        KeyboardLayout::De => "!\"§$%&/()=?`*'>;:_°{[]}\\@€~|",
+        // AZERTY needs Shift for the digits themselves
+        KeyboardLayout::Fr => "1234567890°+¨£µ%?./§>~#{[|`\\^@]}",
+    }
+}
+
+/// Operators strung between brackets in --symbols drills. '<' and '>' only
+/// ever appear as a bracket pair so every drill stays balanced.
+const SYMBOL_OPERATORS: [&str; 18] = [
+    "=", "==", "+=", "-=", "*=", "/=", ":=", "&&", "||", "::", ";", ":", "+", "-", "*", "/", "&",
+    "|",
+];
+const SYMBOL_BRACKETS: [(char, char); 4] = [('(', ')'), ('[', ']'), ('{', '}'), ('<', '>')];
+
+/// Space-separated groups of operators and (possibly nested) bracket pairs,
+/// wrapped onto lines of about 60 characters, at least `min_chars` long
+fn generate_symbols(min_chars: usize, seed: Option<u64>) -> String {
+    use rand::{Rng, SeedableRng};
+
+    fn group(rng: &mut impl Rng, depth: usize, out: &mut String) {
+        for _ in 0..rng.gen_range(1..=3) {
+            if depth < 2 && rng.gen_bool(0.5) {
+                let (open, close) = SYMBOL_BRACKETS[rng.gen_range(0..SYMBOL_BRACKETS.len())];
+                out.push(open);
+                if rng.gen_bool(0.6) {
+                    group(rng, depth + 1, out);
+                }
+                out.push(close);
+            } else {
+                out.push_str(SYMBOL_OPERATORS[rng.gen_range(0..SYMBOL_OPERATORS.len())]);
+            }
+        }
+    }
+
+    let mut rng = match seed {
+        Some(seed) => rand::rngs::StdRng::seed_from_u64(seed),
+        None => rand::rngs::StdRng::from_entropy(),
+    };
+    let mut text = String::new();
+    let mut line_len = 0;
+    while text.len() < min_chars {
+        let mut word = String::new();
+        group(&mut rng, 0, &mut word);
+        if line_len > 0 && line_len + word.len() >= 60 {
+            text.push('\n');
+            line_len = 0;
+        } else if line_len > 0 {
+            text.push(' ');
+            line_len += 1;
+        }
+        line_len += word.len();
+        text.push_str(&word);
+    }
+    text
+}
+
+/// Fence attributes rustdoc still treats as Rust code
+const RUST_FENCE_ATTRS: [&str; 7] = [
+    "rust",
+    "no_run",
+    "ignore",
+    "should_panic",
+    "compile_fail",
+    "test_harness",
+    "allow_fail",
+];
+
+/// Where a crate's sources live: `krate` itself when it is a directory (its
+/// `src/` if it has one), otherwise the newest version of that crate in the
+/// cargo registry
+fn crate_source_dir(krate: &str) -> io::Result<PathBuf> {
+    let path = Path::new(krate);
+    if path.is_dir() {
+        let src = path.join("src");
+        return Ok(if src.is_dir() {
+            src
+        } else {
+            path.to_path_buf()
+        });
+    }
+
+    let cargo_home = std::env::var_os("CARGO_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cargo")))
+        .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "cannot locate CARGO_HOME"))?;
+    let prefix = format!("{}-", krate);
+    let mut versions = Vec::new();
+    for registry in fs::read_dir(cargo_home.join("registry/src"))? {
+        for entry in fs::read_dir(registry?.path())? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            // "serde-1.0.200" but not "serde_json-1.0.1" or "serde-derive-1.0.0"
+            if name
+                .strip_prefix(&prefix)
+                .is_some_and(|version| version.starts_with(|c: char| c.is_ascii_digit()))
+            {
+                versions.push((version_key(&name[prefix.len()..]), entry.path()));
+            }
+        }
+    }
+    versions
+        .into_iter()
+        .max()
+        .map(|(_, dir)| dir.join("src"))
+        .ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("not a directory and not in the cargo registry: {}", krate),
+            )
+        })
+}
+
+/// Sort key for a registry version like "1.0.10" or "2.0.0-rc.1": numeric
+/// parts compare as numbers, and a release sorts after its pre-releases
+fn version_key(version: &str) -> (Vec<u64>, bool, String) {
+    let version = version.split('+').next().unwrap_or(version);
+    let (core, pre) = match version.split_once('-') {
+        Some((core, pre)) => (core, Some(pre)),
+        None => (version, None),
+    };
+    let numbers = core
+        .split('.')
+        .map(|part| part.parse().unwrap_or(0))
+        .collect();
+    (numbers, pre.is_none(), pre.unwrap_or_default().to_string())
+}
+
+/// Every .rs file under `dir`, skipping hidden directories and build output
+fn collect_rust_files(dir: &Path, files: &mut Vec<PathBuf>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path.file_name().unwrap_or_default().to_string_lossy();
+        if name.starts_with('.') || name == "target" {
+            continue;
+        }
+        if path.is_dir() {
+            collect_rust_files(&path, files)?;
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+    Ok(())
+}
+
+/// Rust code fences inside `///` and `//!` doc comments, without the comment
+/// markers. Lines rustdoc hides (`# use ...;`) are dropped, as are fences in
+/// other languages (```text, ```toml, ...).
+fn doc_code_blocks(source: &str) -> Vec<String> {
+    let mut blocks = Vec::new();
+    // Inside a fence: Some(lines) for Rust, None for another language
+    let mut fence: Option<Option<Vec<&str>>> = None;
+
+    for line in source.lines() {
+        let trimmed = line.trim_start();
+        let doc = trimmed.strip_prefix("//!").or_else(|| {
+            trimmed
+                .strip_prefix("///")
+                .filter(|rest| !rest.starts_with('/'))
+        });
+        let Some(doc) = doc else {
+            // The comment ended without closing its fence
+            fence = None;
+            continue;
+        };
+        let doc = doc.strip_prefix(' ').unwrap_or(doc);
+
+        if let Some(info) = doc.trim_start().strip_prefix("```") {
+            match fence.take() {
+                Some(Some(lines)) => blocks.push(lines.join("\n")),
+                Some(None) => {}
+                None => {
+                    let is_rust = info.split(',').map(str::trim).all(|attr| {
+                        attr.is_empty()
+                            || attr.starts_with("edition")
+                            || RUST_FENCE_ATTRS.contains(&attr)
+                    });
+                    fence = Some(is_rust.then(Vec::new));
+                }
+            }
+            continue;
+        }
+
+        if let Some(Some(lines)) = &mut fence {
+            let code = doc.trim_start();
+            if code == "#" || code.starts_with("# ") {
+                continue;
+            }
+            // "##" escapes a line that really starts with '#'
+            lines.push(if code.starts_with("##") {
+                &doc[doc.len() - code.len() + 1..]
+            } else {
+                doc
+            });
+        }
+    }
+    blocks
+}
+
+/// Approximate key centres in key widths (x, y), with the usual row stagger.
+/// Only the unshifted character of each key is listed.
+fn key_coordinates(layout: KeyboardLayout) -> HashMap<char, (f64, f64)> {
+    let rows: [&str; 4] = match layout {
+        KeyboardLayout::Us => [
+            "`1234567890-=",
+            "qwertyuiop[]\\",
+            "asdfghjkl;'",
+            "zxcvbnm,./",
+        ],
+        KeyboardLayout::Uk => [
+            "`1234567890-=",
+            "qwertyuiop[]",
+            "asdfghjkl;'#",
+            "\\zxcvbnm,./",
+        ],
+        KeyboardLayout::De => [
+            "^1234567890ß´",
+            "qwertzuiopü+",
+            "asdfghjklöä#",
+            "<yxcvbnm,.-",
+        ],
+        KeyboardLayout::Fr => [
+            "²&é\"'(-è_çà)=",
+            "azertyuiop^$",
+            "qsdfghjklmù*",
+            "<wxcvbn,;:!",
+        ],
+    };
+    // ISO boards have an extra key left of the bottom row
+    let bottom_offset = if layout == KeyboardLayout::Us {
+        1.25
+    } else {
+        0.25
+    };
+    let offsets = [0.0, 0.5, 0.75, bottom_offset];
+
+    let mut map = HashMap::new();
+    for (y, (row, offset)) in rows.iter().zip(offsets).enumerate() {
+        for (x, key) in row.chars().enumerate() {
+            map.insert(key, (x as f64 + offset, y as f64));
+        }
+    }
+    map.insert(' ', (5.5, 4.0)); // Space bar, under the middle of the board
+    map
+}
+
+/// Home-row resting position of each finger, in key coordinates
+fn finger_home(finger: &str) -> Option<(f64, f64)> {
+    let column = match finger {
+        "L-Pinky" => 0.0,
+        "L-Ring" => 1.0,
+        "L-Middle" => 2.0,
+        "L-Index" => 3.0,
+        "R-Index" => 6.0,
+        "R-Middle" => 7.0,
+        "R-Ring" => 8.0,
+        "R-Pinky" => 9.0,
+        "Thumb" => return Some((5.5, 4.0)),
+        _ => return None,
+    };
+    Some((column + 0.75, 2.0))
+}
+
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum TextTransform {
+    ShuffleWords, // Shuffle words, keeping the whitespace layout
+    ReverseLines, // Reverse line order
+}
+
+/// How to get out of a frozen error buffer
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum FreezeRecovery {
+    #[default]
+    Backspace, // Delete the errors
+    Retype, // Type the expected character, which also clears the buffer
+}
+
+/// What to do with a session that looks pasted rather than typed
+#[derive(clap::ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub enum PasteGuard {
+    Off, // Don't check
+    #[default]
+    Flag, // Warn in the report
+    Reject, // Warn, and keep the run out of history and personal bests
+}
+
+/// Control characters can't be typed, so they never reach the target text
+#[derive(clap::ValueEnum, Clone, Copy, Debug, PartialEq)]
+enum ControlChars {
+    Strip,   // Drop them
+    Replace, // Swap each for a typable '?' so the text keeps its shape
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct LineRange {
+    start: usize,
+    end: usize,
+}
+
+impl std::str::FromStr for LineRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected START:END, got '{}'", s))?;
+        let start: usize = start
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid start line '{}'", start))?;
+        let end: usize = end
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid end line '{}'", end))?;
+
+        if start == 0 || start > end {
+            return Err(format!(
+                "invalid line range {}:{} (lines start at 1 and START must not exceed END)",
+                start, end
+            ));
+        }
+
+        Ok(LineRange { start, end })
+    }
+}
+
+/// Explicit passage length band, overriding the --size presets
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct CharRange {
+    min: usize,
+    max: usize,
+}
+
+impl std::str::FromStr for CharRange {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (min, max) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected MIN:MAX, got '{}'", s))?;
+        let min: usize = min
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid minimum '{}'", min))?;
+        let max: usize = max
+            .trim()
+            .parse()
+            .map_err(|_| format!("invalid maximum '{}'", max))?;
+
+        if min == 0 || min > max {
+            return Err(format!(
+                "invalid character range {}:{} (MIN must be at least 1 and not exceed MAX)",
+                min, max
+            ));
+        }
+
+        Ok(CharRange { min, max })
+    }
+}
+
+/// Characters per line assumed when turning an explicit --chars band into lines,
+/// matching the --size presets (e.g. medium: 1600-3200 chars, 40-80 lines)
+const CHARS_PER_LINE: usize = 40;
+
+/// A pace target rising linearly in time from `start` to `end` WPM
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WpmRamp {
+    pub start: u32,
+    pub end: u32,
+}
+
+impl std::str::FromStr for WpmRamp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (start, end) = s
+            .split_once(':')
+            .ok_or_else(|| format!("expected START:END, got '{}'", s))?;
+        let parse = |wpm: &str| match wpm.trim().parse::<u32>() {
+            Ok(wpm) if wpm > 0 => Ok(wpm),
+            _ => Err(format!(
+                "invalid WPM '{}' (expected a positive number)",
+                wpm
+            )),
+        };
+
+        Ok(WpmRamp {
+            start: parse(start)?,
+            end: parse(end)?,
+        })
+    }
+}
+
+impl WpmRamp {
+    /// Characters the pace marker has covered after `elapsed` on a passage of
+    /// `len` characters. The ramp lasts as long as the passage takes at the
+    /// mean of both speeds, so the marker reaches the end exactly at `end` WPM.
+    fn chars_at(&self, elapsed: Duration, len: usize) -> f64 {
+        let (start, end) = (self.start as f64, self.end as f64);
+        let ramp_minutes = len as f64 / 5.0 / ((start + end) / 2.0);
+        if ramp_minutes <= 0.0 {
+            return 0.0;
+        }
+        // Integral of the linearly rising WPM target, in characters
+        let t = (elapsed.as_secs_f64() / 60.0).min(ramp_minutes);
+        5.0 * (start * t + (end - start) * t * t / (2.0 * ramp_minutes))
+    }
+}
+
+/// Number of equal passage segments checked against the ramp in the report
+const RAMP_SEGMENTS: usize = 4;
+
 #[derive(Subcommand)]
 enum Commands {
     /// Start typing test with file browser (default mode)
     Browse,
+    /// Practice the words and keys an exported report shows you struggled with
+    Drill {
+        /// Report exported with 'e'
+        report: PathBuf,
+    },
+}
+
+/// Where the practice text comes from, decided from the command line
+#[derive(Debug, PartialEq)]
+enum SourceChoice<'a> {
+    Browse,
+    Inception,
+    Quotes,
+    Man(&'a str),
+    Symbols,
+    Docs(&'a str),
+    Calibrate,
+    Drill(&'a Path),
+    File(&'a Path),
+    Ghost,
+    Stdin, // Piped input: `cmd | keysentry`
+}
+
+impl<'a> SourceChoice<'a> {
+    /// The browser is the default whenever no source flag is given, unless
+    /// text is piped in on stdin
+    fn from_cli(cli: &'a Cli, stdin_is_terminal: bool) -> Self {
+        if let Some(Commands::Browse) = cli.command {
+            SourceChoice::Browse
+        } else if let Some(Commands::Drill { report }) = &cli.command {
+            SourceChoice::Drill(report)
+        } else if cli.calibrate {
+            SourceChoice::Calibrate
+        } else if cli.inception {
+            SourceChoice::Inception
+        } else if cli.quotes {
+            SourceChoice::Quotes
+        } else if let Some(page) = &cli.man {
+            SourceChoice::Man(page)
+        } else if cli.symbols {
+            SourceChoice::Symbols
+        } else if let Some(krate) = &cli.docs {
+            SourceChoice::Docs(krate)
+        } else if let Some(file_path) = &cli.file {
+            SourceChoice::File(file_path)
+        } else if cli.ghost.is_some() {
+            SourceChoice::Ghost
+        } else if !stdin_is_terminal {
+            SourceChoice::Stdin
+        } else {
+            SourceChoice::Browse
+        }
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
@@ -60,6 +790,7 @@ pub enum ErrorType {
     Insertion,
     Omission,
     Repeat,
+    Doubling, // Extra or missing letter on a double letter ("committ", "comit")
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -71,16 +802,90 @@ pub struct ErrorEvent {
     pub timestamp: Duration,
     pub correction_timestamp: Option<Duration>,
     pub correction_latency: Option<Duration>,
+    #[serde(default)]
+    pub context_before: String, // Target text just before the error position
+    #[serde(default)]
+    pub context_after: String, // Target text just after it
 }
 
+/// Characters of target text kept on each side of an error
+const ERROR_CONTEXT_CHARS: usize = 5;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(from = "KeyStatRecord")]
 pub struct KeyStat {
     pub key: char,
     pub count: u32,
     pub total_latency: Duration,
     pub error_count: u32,
-    pub latencies: Vec<u64>,   // Individual keystroke latencies in ms
-    pub positions: Vec<usize>, // Where this key appeared in text
+    pub latencies_us: Vec<u64>, // Individual keystroke latencies in µs
+    pub positions: Vec<usize>,  // Where this key appeared in text
+    #[serde(default)]
+    pub error_flags: Vec<bool>, // Whether each press was made in an error state
+}
+
+/// KeyStat as stored in reports of any version: before latencies were kept
+/// in µs they were stored in ms under `latencies`
+#[derive(Deserialize)]
+struct KeyStatRecord {
+    key: char,
+    count: u32,
+    total_latency: Duration,
+    error_count: u32,
+    #[serde(default)]
+    latencies_us: Option<Vec<u64>>,
+    #[serde(default)]
+    latencies: Vec<u64>, // ms
+    positions: Vec<usize>,
+    #[serde(default)]
+    error_flags: Vec<bool>,
+}
+
+impl From<KeyStatRecord> for KeyStat {
+    fn from(record: KeyStatRecord) -> Self {
+        let latencies_us = record
+            .latencies_us
+            .unwrap_or_else(|| record.latencies.iter().map(|ms| ms * 1000).collect());
+        KeyStat {
+            key: record.key,
+            count: record.count,
+            total_latency: record.total_latency,
+            error_count: record.error_count,
+            latencies_us,
+            positions: record.positions,
+            error_flags: record.error_flags,
+        }
+    }
+}
+
+/// Keys typed fewer times than this are left out of per-key accuracy rankings
+const KEY_ACCURACY_MIN_COUNT: u32 = 5;
+
+impl KeyStat {
+    /// Drop the oldest presses so at most `max` remain, keeping the count,
+    /// total latency and error count in step with the samples left
+    fn keep_last(&mut self, max: usize) {
+        let dropped = self.latencies_us.len().saturating_sub(max);
+        if dropped == 0 {
+            return;
+        }
+        let dropped_us: u64 = self.latencies_us[..dropped].iter().sum();
+        let dropped_errors = self.error_flags[..dropped].iter().filter(|&&e| e).count();
+        self.count -= dropped as u32;
+        self.total_latency = self
+            .total_latency
+            .saturating_sub(Duration::from_micros(dropped_us));
+        self.error_count -= dropped_errors as u32;
+        keep_last(&mut self.latencies_us, max);
+        keep_last(&mut self.positions, max);
+        keep_last(&mut self.error_flags, max);
+    }
+
+    /// Share of presses of this key that weren't errors, in percent
+    pub fn accuracy(&self) -> Option<f64> {
+        (self.count > 0)
+            .then(|| self.count.saturating_sub(self.error_count) as f64 / self.count as f64 * 100.0)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -117,6 +922,54 @@ pub struct WeaknessAnalysis {
     pub finger_errors: HashMap<String, u32>,  // Finger assignment errors
     pub rhythm_breaks: Vec<usize>,            // Positions where rhythm broke
     pub problematic_transitions: Vec<(char, char, f64)>, // char1->char2, avg latency
+    pub hand_balance: Option<HandBalance>,    // None until both hands typed
+    #[serde(default)]
+    pub finger_travel: FingerTravel,
+}
+
+/// Approximate finger travel in key widths (about 19mm each)
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FingerTravel {
+    pub left: f64,
+    pub right: f64,
+}
+
+/// Centre-to-centre distance of standard keys
+const KEY_PITCH_MM: f64 = 19.05;
+
+impl FingerTravel {
+    pub fn total(&self) -> f64 {
+        self.left + self.right
+    }
+
+    pub fn to_meters(keys: f64) -> f64 {
+        keys * KEY_PITCH_MM / 1000.0
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HandBalance {
+    pub left_avg_latency_ms: f64,
+    pub right_avg_latency_ms: f64,
+    pub left_share: f64, // Fraction of hand keystrokes typed with the left hand
+    pub right_share: f64,
+}
+
+impl HandBalance {
+    /// e.g. "right hand 30% slower", or None when the hands are within 5%
+    pub fn imbalance(&self) -> Option<String> {
+        let (slow_hand, slow, fast) = if self.left_avg_latency_ms > self.right_avg_latency_ms {
+            ("left", self.left_avg_latency_ms, self.right_avg_latency_ms)
+        } else {
+            ("right", self.right_avg_latency_ms, self.left_avg_latency_ms)
+        };
+        if fast <= 0.0 {
+            return None;
+        }
+
+        let percent_slower = (slow / fast - 1.0) * 100.0;
+        (percent_slower >= 5.0).then(|| format!("{} hand {:.0}% slower", slow_hand, percent_slower))
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -125,9 +978,13 @@ pub struct SessionReport {
     pub total_characters: usize,
     pub correct_characters: usize,
     pub wpm: f64,
+    #[serde(default)]
+    pub raw_wpm: f64, // Every key press, wrong keys and Backspace included
     pub accuracy: f64,
     pub average_latency: Duration,
     pub errors: Vec<ErrorEvent>,
+    #[serde(default)]
+    pub error_counts: HashMap<ErrorType, usize>, // All errors by type, `errors` may be windowed
     pub key_stats: HashMap<char, KeyStat>,
     pub total_corrections: usize,
     pub average_correction_latency: Option<Duration>,
@@ -135,90 +992,955 @@ pub struct SessionReport {
     pub hesitation_patterns: Vec<HesitationPattern>,
     pub weakness_analysis: WeaknessAnalysis,
     pub wpm_over_time: Vec<(Duration, f64)>, // WPM at different time points
+    #[serde(default)]
+    pub accuracy_over_time: Vec<(Duration, f64)>, // Accuracy at the same time points
+    pub label: Option<String>,
+    pub snippet_score: Option<f32>, // Selection score of the practiced snippet
+    pub attribution: Option<String>, // Authors of the practiced quotations
+    #[serde(default)]
+    pub source_meta: Option<SourceMeta>, // File (and git commit) the snippet came from
+    #[serde(default)]
+    pub target_text: String, // Empty in reports exported before it was recorded
+    #[serde(default)]
+    pub coverage: Coverage,
+    #[serde(default)]
+    pub doubling_errors: usize, // Extra or missing letters on double letters
+    #[serde(default)]
+    pub ramp: Option<(u32, u32)>, // Start and end WPM of a --ramp run
+    #[serde(default)]
+    pub ramp_segments: Vec<bool>, // Kept ahead of the ramp, per passage quarter
+    #[serde(default)]
+    pub content_wpm: Option<ContentWpm>, // --split-wpm, when both kinds were typed
+    #[serde(default)]
+    pub keystroke_log: Vec<(char, Duration)>, // Every key press incl. backspace, for replays
+    #[serde(default)]
+    pub paste_suspected: bool, // Near-instant keystroke bursts: results unreliable
+    #[serde(default)]
+    pub error_free_chars: Option<usize>, // --sudden-death: characters typed before the first error
+    #[serde(default)]
+    pub until_wpm: Option<(u32, Option<Duration>)>, // --until-wpm target and the time it took to reach it
+    #[serde(default)]
+    pub line_times: Vec<(usize, Duration)>, // 1-based line number, first key to its newline
+    #[serde(default)]
+    pub sentence_stats: Vec<SentenceStat>, // Typed sentences, in order
+    #[serde(default)]
+    pub started_at: Option<chrono::DateTime<chrono::Utc>>, // First key, wall clock; None in older reports
 }
 
-#[derive(PartialEq)]
-enum AppState {
-    Typing,
-    ShowingReport,
+/// Shareable summary of a session: aggregate metrics and weakness patterns
+/// only, without the target text, keystroke log, label or error positions
+#[derive(Debug, Clone, Serialize)]
+pub struct AnonymizedReport {
+    pub session_duration: Duration,
+    pub total_characters: usize,
+    pub correct_characters: usize,
+    pub wpm: f64,
+    pub accuracy: f64,
+    pub average_latency: Duration,
+    pub total_corrections: usize,
+    pub error_counts: HashMap<ErrorType, usize>,
+    pub slowest_digraphs: Vec<(String, f64)>,
+    pub finger_errors: HashMap<String, u32>,
+    pub hand_balance: Option<HandBalance>,
+    pub latency_distribution: Vec<(String, usize)>, // Bucket label, keystrokes
 }
 
-#[derive(PartialEq)]
-enum ReportView {
-    Charts,
-    Analysis,
+/// The file a snippet was taken from, and the commit checked out when it was
+/// loaded if the file lives in a git repository
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SourceMeta {
+    pub path: String,
+    pub commit: Option<String>,
 }
 
-pub struct TypingSession {
-    target_text: String,
-    user_input: String,
-    current_position: usize,
-    errors: Vec<ErrorEvent>,
-    key_stats: HashMap<char, KeyStat>,
-    session_start: Instant,
-    session_end: Option<Instant>,
-    last_keystroke: Option<Instant>,
-    has_error: bool,
+impl SourceMeta {
+    fn for_path(path: &Path) -> Self {
+        let path = fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf());
+        let dir = path.parent().unwrap_or(Path::new("."));
+        let commit = std::process::Command::new("git")
+            .arg("-C")
+            .arg(dir)
+            .args(["rev-parse", "HEAD"])
+            .stderr(std::process::Stdio::null())
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+            .filter(|hash| !hash.is_empty());
+
+        Self {
+            path: path.display().to_string(),
+            commit,
+        }
+    }
+}
+
+/// Speed and errors over one sentence of the target text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SentenceStat {
+    pub start: usize, // Char positions in the target text, end exclusive
+    pub end: usize,
+    pub wpm: f64,
+    pub errors: usize,
+}
+
+impl SessionReport {
+    /// The sentence with the most errors, the slower one on a tie
+    pub fn hardest_sentence(&self) -> Option<&SentenceStat> {
+        self.sentence_stats.iter().max_by(|a, b| {
+            a.errors.cmp(&b.errors).then(
+                b.wpm
+                    .partial_cmp(&a.wpm)
+                    .unwrap_or(std::cmp::Ordering::Equal),
+            )
+        })
+    }
+}
+
+/// Words ending in a period that don't end a sentence
+const ABBREVIATIONS: [&str; 12] = [
+    "e.g.", "i.e.", "etc.", "vs.", "cf.", "Mr.", "Mrs.", "Ms.", "Dr.", "St.", "Jr.", "No.",
+];
+
+/// Char ranges of the sentences in `text`, ending after '.', '!' or '?' (and
+/// any closing quotes or brackets) followed by whitespace. Known abbreviations
+/// and initials ("J.", "U.S.") don't end a sentence, but a lone "I." or
+/// lowercase letter does; whitespace between sentences belongs to neither.
+fn sentence_spans(text: &str) -> Vec<(usize, usize)> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut spans = Vec::new();
+    let mut start = None;
+    let mut i = 0;
+    while i < chars.len() {
+        if start.is_none() && !chars[i].is_whitespace() {
+            start = Some(i);
+        }
+        if let Some(begin) = start
+            && matches!(chars[i], '.' | '!' | '?')
+        {
+            let mut end = i + 1;
+            while end < chars.len()
+                && matches!(chars[end], '.' | '!' | '?' | '"' | '\'' | ')' | ']')
+            {
+                end += 1;
+            }
+            let word_start = chars[..i]
+                .iter()
+                .rposition(|c| c.is_whitespace())
+                .map_or(0, |p| p + 1);
+            let word: String = chars[word_start..end].iter().collect();
+            let word = word.trim_start_matches(['"', '\'', '(', '[']);
+            let parts: Vec<&str> = word.split_terminator('.').collect();
+            let initials = word.ends_with('.')
+                && parts.iter().all(|part| part.chars().count() == 1)
+                && match parts[..] {
+                    [letter] => letter != "I" && letter.chars().all(char::is_uppercase),
+                    _ => true,
+                };
+            let abbreviation = ABBREVIATIONS.contains(&word) || initials;
+            if (end == chars.len() || chars[end].is_whitespace()) && !abbreviation {
+                spans.push((begin, end));
+                start = None;
+            }
+            i = end;
+            continue;
+        }
+        i += 1;
+    }
+    if let Some(begin) = start {
+        let end = chars.len() - chars.iter().rev().take_while(|c| c.is_whitespace()).count();
+        spans.push((begin, end));
+    }
+    spans
+}
+
+/// WPM over code lines and over comment lines of a mixed snippet
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ContentWpm {
+    pub code: f64,
+    pub comment: f64,
+}
+
+/// Result in the shape typing sites export (camelCase keys), for importing
+/// into external trackers. See the README for how each field is derived.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ExternalResult {
+    pub wpm: f64,
+    pub raw_wpm: f64,
+    pub acc: f64,
+    pub consistency: f64,
+    pub char_stats: [usize; 4], // correct, incorrect, extra, missed
+    pub test_duration: f64,     // Seconds
+    pub timestamp: i64,         // Session start, Unix milliseconds (0 when unknown)
+}
+
+/// Upper bounds of the latency distribution buckets in anonymized exports
+const LATENCY_BUCKETS_MS: [u64; 6] = [100, 200, 300, 500, 800, 1200];
+
+impl SessionReport {
+    /// Errors of the given kinds over the whole session. Reports from before
+    /// the totals were kept count their error log instead.
+    pub fn error_count(&self, kinds: &[ErrorType]) -> usize {
+        if self.error_counts.is_empty() {
+            return self
+                .errors
+                .iter()
+                .filter(|e| kinds.contains(&e.error_type))
+                .count();
+        }
+        kinds
+            .iter()
+            .filter_map(|kind| self.error_counts.get(kind))
+            .sum()
+    }
+
+    pub fn total_errors(&self) -> usize {
+        self.error_count(&[
+            ErrorType::Substitution,
+            ErrorType::Insertion,
+            ErrorType::Omission,
+            ErrorType::Repeat,
+            ErrorType::Doubling,
+        ])
+    }
+
+    pub fn to_external_schema(&self) -> ExternalResult {
+        let count = |kinds: &[ErrorType]| self.error_count(kinds);
+
+        // 100 minus the coefficient of variation of keystroke latencies, in percent
+        let latencies: Vec<f64> = self
+            .typing_rhythm
+            .iter()
+            .skip(1)
+            .map(|r| r.latency.as_secs_f64())
+            .collect();
+        let consistency = match latencies.len() {
+            0 | 1 => 100.0,
+            n => {
+                let mean = latencies.iter().sum::<f64>() / n as f64;
+                let variance = latencies.iter().map(|l| (l - mean).powi(2)).sum::<f64>() / n as f64;
+                if mean > 0.0 {
+                    (100.0 * (1.0 - variance.sqrt() / mean)).clamp(0.0, 100.0)
+                } else {
+                    100.0
+                }
+            }
+        };
+
+        ExternalResult {
+            wpm: self.wpm,
+            raw_wpm: self.raw_wpm,
+            acc: self.accuracy,
+            consistency,
+            char_stats: [
+                self.correct_characters,
+                count(&[
+                    ErrorType::Substitution,
+                    ErrorType::Repeat,
+                    ErrorType::Doubling,
+                ]),
+                count(&[ErrorType::Insertion]),
+                count(&[ErrorType::Omission]),
+            ],
+            test_duration: self.session_duration.as_secs_f64(),
+            timestamp: self.started_at.map_or(0, |t| t.timestamp_millis()),
+        }
+    }
+
+    pub fn anonymized(&self) -> AnonymizedReport {
+        let mut error_counts = self.error_counts.clone();
+        if error_counts.is_empty() {
+            for error in &self.errors {
+                *error_counts.entry(error.error_type.clone()).or_insert(0) += 1;
+            }
+        }
+
+        let mut latency_distribution: Vec<(String, usize)> = LATENCY_BUCKETS_MS
+            .iter()
+            .map(|bound| (format!("<{}ms", bound), 0))
+            .collect();
+        latency_distribution.push((format!(">={}ms", LATENCY_BUCKETS_MS[5]), 0));
+        for rhythm in &self.typing_rhythm {
+            let ms = rhythm.latency.as_millis() as u64;
+            let bucket = LATENCY_BUCKETS_MS
+                .iter()
+                .position(|&bound| ms < bound)
+                .unwrap_or(LATENCY_BUCKETS_MS.len());
+            latency_distribution[bucket].1 += 1;
+        }
+
+        AnonymizedReport {
+            session_duration: self.session_duration,
+            total_characters: self.total_characters,
+            correct_characters: self.correct_characters,
+            wpm: self.wpm,
+            accuracy: self.accuracy,
+            average_latency: self.average_latency,
+            total_corrections: self.total_corrections,
+            error_counts,
+            slowest_digraphs: self.weakness_analysis.slowest_digraphs.clone(),
+            finger_errors: self.weakness_analysis.finger_errors.clone(),
+            hand_balance: self.weakness_analysis.hand_balance.clone(),
+            latency_distribution,
+        }
+    }
+}
+
+/// Which keys a drill touched. All sets hold visible characters, sorted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Coverage {
+    pub present: Vec<char>,         // In the target text
+    pub typed_correctly: Vec<char>, // Typed right at least once
+    pub exercised: Vec<char>,       // Pressed at all, right or wrong
+}
+
+impl Coverage {
+    /// Target characters never typed correctly, e.g. after quitting early
+    pub fn missing(&self) -> Vec<char> {
+        self.present
+            .iter()
+            .filter(|c| !self.typed_correctly.contains(c))
+            .copied()
+            .collect()
+    }
+}
+
+/// Aggregated key stats for a group of keys (e.g. the number row)
+#[derive(Debug, Clone, Default)]
+pub struct KeyGroupStats {
+    pub keystrokes: u32,
+    pub errors: u32,
+    pub total_latency: Duration,
+}
+
+impl KeyGroupStats {
+    pub fn accuracy(&self) -> Option<f64> {
+        (self.keystrokes > 0).then(|| {
+            (self.keystrokes.saturating_sub(self.errors)) as f64 / self.keystrokes as f64 * 100.0
+        })
+    }
+
+    pub fn average_latency(&self) -> Option<Duration> {
+        (self.keystrokes > 0).then(|| self.total_latency / self.keystrokes)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SymbolStats {
+    pub digits: KeyGroupStats,
+    pub symbols: KeyGroupStats,
+}
+
+impl SessionReport {
+    /// Standard error of the sampled WPM, None with fewer than two samples
+    pub fn wpm_standard_error(&self) -> Option<f64> {
+        let samples: Vec<f64> = self.wpm_over_time.iter().map(|(_, wpm)| *wpm).collect();
+        standard_error(&samples)
+    }
+
+    /// Characters left in the input, right or wrong, per minute
+    pub fn gross_wpm(&self) -> f64 {
+        words_per_minute(self.total_characters, self.session_duration)
+    }
+
+    /// WPM with its sampling interval when available, e.g. "42.0 ± 4.1"
+    pub fn wpm_display(&self) -> String {
+        match self.wpm_standard_error() {
+            Some(se) => format!("{:.1} ± {:.1}", self.wpm, se),
+            None => format!("{:.1}", self.wpm),
+        }
+    }
+
+    /// Accuracy and latency over the number row and its shifted symbols,
+    /// the keys that matter most for code
+    pub fn symbol_stats(&self) -> SymbolStats {
+        let mut stats = SymbolStats::default();
+
+        for (key, stat) in &self.key_stats {
+            let group = if key.is_ascii_digit() {
+                &mut stats.digits
+            } else if "!@#$%^&*()".contains(*key) {
+                &mut stats.symbols
+            } else {
+                continue;
+            };
+            group.keystrokes += stat.count;
+            group.errors += stat.error_count;
+            group.total_latency += stat.total_latency;
+        }
+
+        stats
+    }
+}
+
+/// Session state right after a given keystroke, rebuilt from the rhythm log
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeystrokeState {
+    pub index: usize,
+    pub correct_prefix: String,
+    pub typed_char: char,
+    pub expected_char: Option<char>,
+    pub elapsed: Duration,
+    pub wpm: f64,
+}
+
+#[derive(PartialEq)]
+enum AppState {
+    Browsing,
+    Typing,
+    Completed, // Finished text still on screen, waiting for a key (--hold-on-complete)
+    ShowingReport,
+    Break, // --pomodoro rest between focus intervals
+}
+
+#[derive(PartialEq)]
+enum ReportView {
+    Charts,
+    Analysis,
+    HesitationMap,
+}
+
+impl ReportView {
+    fn next(&self) -> Self {
+        match self {
+            ReportView::Charts => ReportView::Analysis,
+            ReportView::Analysis => ReportView::HesitationMap,
+            ReportView::HesitationMap => ReportView::Charts,
+        }
+    }
+
+    fn previous(&self) -> Self {
+        match self {
+            ReportView::Charts => ReportView::HesitationMap,
+            ReportView::Analysis => ReportView::Charts,
+            ReportView::HesitationMap => ReportView::Analysis,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct SessionConfig {
+    /// Skip key stats, rhythm recording and hesitation detection entirely
+    pub zen: bool,
+    /// Rolling-window cap on recorded samples (None = unbounded). The
+    /// keystroke log is exempt: `--reanalyze` and `raw_wpm` need every press.
+    pub max_samples: Option<usize>,
+    /// Custom key to finger mapping, overriding the built-in QWERTY one key by key
+    pub finger_map: Option<HashMap<char, String>>,
+    /// Keep accepting keystrokes past the error threshold instead of freezing
+    pub soft_cap: bool,
+    /// What unfreezes a full error buffer
+    pub freeze_recovery: FreezeRecovery,
+    /// Reject wrong keys outright instead of buffering them as red errors
+    pub instant: bool,
+    /// User-supplied label identifying the run
+    pub label: Option<String>,
+    /// Block completion while any accepted error is left uncorrected
+    pub require_clean: bool,
+    /// Physical keyboard layout the user types on
+    pub layout: KeyboardLayout,
+    /// Compute separate WPM figures for code and comment lines
+    pub split_wpm: bool,
+    /// How to treat a session that looks pasted
+    pub paste_guard: PasteGuard,
+    /// Separate differently styled symbols so fonts can't join them
+    pub break_ligatures: bool,
+    /// Glyph drawn at the cursor, in addition to the underline
+    pub cursor_char: Option<char>,
+    /// Stand-in drawn over correctly typed characters
+    pub mask_char: Option<char>,
+    /// Briefly brighten each character as it is typed correctly
+    pub feedback: bool,
+    /// The first error (or skipped word) ends the session
+    pub sudden_death: bool,
+    /// Constant pace to race against, shown as a marker in the text
+    pub target_wpm: Option<u32>,
+    /// Latency above which a keystroke is a hesitation (None = DEFAULT_HESITATION_MS)
+    pub hesitation_ms: Option<u64>,
+    /// Accelerating pace to race against instead of a constant one
+    pub ramp: Option<WpmRamp>,
+    /// Extend the passage until the rolling WPM stabilizes at or above this
+    pub until_wpm: Option<u32>,
+    /// JSON-lines file receiving every keystroke as it happens
+    pub telemetry: Option<PathBuf>,
+    /// Time between WPM samples (None = one sample every 10 characters)
+    pub sample_interval: Option<Duration>,
+    /// Leave latencies above OUTLIER_PERCENTILE out of reported averages
+    pub trim_outliers: bool,
+    /// Only show BLIND_LOOKAHEAD characters of the text ahead of the cursor
+    pub blind: bool,
+}
+
+/// --until-wpm: rolling WPM is measured over the last UNTIL_WPM_SMOOTHING and
+/// must stay at or above the target for UNTIL_WPM_HOLD to count as reached
+const UNTIL_WPM_SMOOTHING: Duration = Duration::from_secs(10);
+const UNTIL_WPM_HOLD: Duration = Duration::from_secs(30);
+
+/// When the rolling WPM first got to `target` and then stayed there for `hold`,
+/// or None if it never did. `series` holds cumulative WPM samples as in
+/// wpm_over_time; the pace over the trailing `smoothing` window is recovered
+/// from the words typed between two of them, so one slow key doesn't count
+fn sustained_wpm_since(
+    series: &[(Duration, f64)],
+    target: f64,
+    smoothing: Duration,
+    hold: Duration,
+) -> Option<Duration> {
+    let words: Vec<(Duration, f64)> = std::iter::once((Duration::ZERO, 0.0))
+        .chain(
+            series
+                .iter()
+                .map(|&(time, wpm)| (time, wpm * time.as_secs_f64() / 60.0)),
+        )
+        .collect();
+
+    let mut held_since = None;
+    let mut back = 0;
+    for &(time, typed) in &words {
+        // Latest sample at least a smoothing window before this one
+        while words
+            .get(back + 1)
+            .is_some_and(|&(earlier, _)| earlier + smoothing <= time)
+        {
+            back += 1;
+        }
+        let (from, typed_then) = words[back];
+        let rolling = (from + smoothing <= time)
+            .then(|| (typed - typed_then) / (time - from).as_secs_f64() * 60.0);
+
+        if rolling.is_some_and(|wpm| wpm >= target) {
+            let since = *held_since.get_or_insert(time);
+            if time - since >= hold {
+                return Some(since);
+            }
+        } else {
+            held_since = None;
+        }
+    }
+    None
+}
+
+/// How far ahead of the cursor the text is visible in blind mode
+const BLIND_LOOKAHEAD: usize = 8;
+
+/// A run of this many consecutive keystrokes, each faster than
+/// PASTE_MAX_LATENCY, is taken as pasted input
+const PASTE_BURST_KEYS: usize = 8;
+const PASTE_MAX_LATENCY: Duration = Duration::from_millis(10);
+
+/// How long the --feedback highlight takes to fade out
+const FEEDBACK_DECAY: Duration = Duration::from_millis(250);
+
+/// Keystrokes slower than this percentile are outliers under --trim-outliers
+const OUTLIER_PERCENTILE: f64 = 95.0;
+
+/// Finer WPM sampling than this is noise, and only costs memory
+const MIN_SAMPLE_INTERVAL_MS: u64 = 100;
+
+/// A past session replayed as a second cursor to race against
+struct Ghost {
+    target_text: String,
+    progress: Vec<(Duration, usize)>, // (time since start, characters completed)
+}
+
+impl Ghost {
+    fn from_report(report: &SessionReport) -> Result<Self, String> {
+        if report.target_text.is_empty() {
+            return Err("report has no target text (exported by an older version)".to_string());
+        }
+        if report.typing_rhythm.is_empty() {
+            return Err("report has no keystroke log (zen mode?)".to_string());
+        }
+
+        let target_chars: Vec<char> = report.target_text.chars().collect();
+        let progress = report
+            .typing_rhythm
+            .iter()
+            .map(|rhythm| {
+                let completed = if target_chars.get(rhythm.position) == Some(&rhythm.char_typed) {
+                    rhythm.position + 1
+                } else {
+                    rhythm.position
+                };
+                (rhythm.timestamp, completed)
+            })
+            .collect();
+
+        Ok(Self {
+            target_text: report.target_text.clone(),
+            progress,
+        })
+    }
+
+    /// Characters the ghost had completed `elapsed` into its session
+    fn position_at(&self, elapsed: Duration) -> usize {
+        let reached = self.progress.partition_point(|(time, _)| *time <= elapsed);
+        reached
+            .checked_sub(1)
+            .map_or(0, |last| self.progress[last].1)
+    }
+}
+
+/// One keystroke as written to the telemetry stream
+#[derive(Debug, Serialize)]
+struct TelemetryEvent {
+    position: usize,
+    char: char,
+    latency_us: u64,
+    correct: Option<bool>, // None for backspace
+}
+
+/// Buffered so per-keystroke writes don't stall rendering; flushed on finish
+/// and when dropped
+struct Telemetry {
+    writer: io::BufWriter<fs::File>,
+}
+
+impl Telemetry {
+    fn open(path: &Path) -> io::Result<Self> {
+        let file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)?;
+        Ok(Self {
+            writer: io::BufWriter::new(file),
+        })
+    }
+
+    fn record(&mut self, event: &TelemetryEvent) -> io::Result<()> {
+        use std::io::Write;
+
+        writeln!(self.writer, "{}", serde_json::to_string(event)?)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        use std::io::Write;
+
+        self.writer.flush()
+    }
+}
+
+/// Consecutive errors allowed before input freezes (or warns in soft-cap mode)
+const MAX_CONSECUTIVE_ERRORS: usize = 10;
+
+pub struct TypingSession {
+    config: SessionConfig,
+    target_text: String,
+    target_len: usize, // In chars, like every position
+    user_input: String,
+    current_position: usize,
+    errors: Vec<ErrorEvent>,
+    error_counts: HashMap<ErrorType, usize>, // Every error so far, even once --max-samples drops it
+    errors_at: HashMap<usize, usize>,        // The same, per position
+    open_errors: HashSet<usize>,             // Positions with an error not corrected yet
+    key_stats: HashMap<char, KeyStat>,
+    session_start: Instant,
+    started_at: Option<chrono::DateTime<chrono::Utc>>, // Wall-clock time of the first key
+    session_end: Option<Instant>,
+    last_keystroke: Option<Instant>,
+    last_advance: Option<(usize, Instant)>, // Most recent correctly typed position
+    paused_at: Option<Instant>,
+    resumed: bool, // The next key ends an idle pause, its gap is no typing latency
+    has_error: bool,
     consecutive_errors: usize,
     is_frozen: bool,
+    skipped_positions: std::collections::HashSet<usize>,
+    died_at: Option<usize>, // --sudden-death: position of the fatal error
+    target_reached: Option<Duration>, // --until-wpm: when the rolling WPM got there to stay
     total_corrections: usize,
     typing_rhythm: Vec<TypingRhythm>,
+    keystroke_log: Vec<(char, Duration)>, // Raw presses (Tab, Backspace, skips) since the first key
     hesitation_patterns: Vec<HesitationPattern>,
     wpm_samples: Vec<(Instant, f64)>,
+    accuracy_samples: Vec<(Instant, f64)>,
+    snippet_score: Option<f32>,
+    attribution: Option<String>,
+    source_meta: Option<SourceMeta>,
+    recent_latencies: std::collections::VecDeque<u64>, // µs, for the sparkline
+    telemetry: Option<Telemetry>,
+    clock: Box<dyn Clock>,
+    difficulty: f32,
+}
+
+/// Where a session reads the time from. Sessions only ever ask their clock,
+/// so a scripted clock can drive latencies, WPM and hesitations deterministically.
+pub trait Clock {
+    fn now(&self) -> Instant;
+}
+
+/// The wall clock used for real sessions
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
 }
 
+/// A clock set by hand, for replaying keystroke logs
+struct ReplayClock(std::rc::Rc<std::cell::Cell<Instant>>);
+
+impl Clock for ReplayClock {
+    fn now(&self) -> Instant {
+        self.0.get()
+    }
+}
+
+/// Stands for a Ctrl+S word skip in keystroke logs
+const SKIP_WORD_KEY: char = '\x13';
+
+/// Keystrokes slower than this are hesitations, twice as slow a long pause
+const DEFAULT_HESITATION_MS: u64 = 500;
+const MIN_HESITATION_MS: u64 = 100;
+
+/// Length band of the --calibrate passage
+const CALIBRATION_MIN_CHARS: usize = 400;
+const CALIBRATION_MAX_CHARS: usize = 600;
+/// Fewer keystrokes than this say too little about someone's pace
+const CALIBRATION_MIN_KEYS: usize = 50;
+
+/// Length band of the throwaway --warmup passage
+const WARMUP_MIN_CHARS: usize = 120;
+const WARMUP_MAX_CHARS: usize = 240;
+
+/// Saved weak words appended to each weakest-spot drill
+const WEAK_WORDS_PER_DRILL: usize = 5;
+
+/// How many recent keystroke latencies are kept for the live sparkline
+const RECENT_LATENCY_CAPACITY: usize = 40;
+
 impl TypingSession {
-    pub fn new(target_text: String) -> Self {
+    pub fn new(target_text: String, config: SessionConfig) -> Self {
+        Self::with_clock(target_text, config, Box::new(SystemClock))
+    }
+
+    /// Rebuild a session by feeding it a recorded keystroke log, as stored in
+    /// `SessionReport::keystroke_log`. Typing the same keys at the same times
+    /// live gives the same report.
+    pub fn from_keystroke_log(
+        target_text: String,
+        log: &[(char, Duration)],
+        config: SessionConfig,
+    ) -> Self {
+        let start = Instant::now();
+        let time = std::rc::Rc::new(std::cell::Cell::new(start));
+        let mut session =
+            Self::with_clock(target_text, config, Box::new(ReplayClock(time.clone())));
+
+        for &(key, at) in log {
+            time.set(start + at);
+            match key {
+                '\t' => session.handle_tab(),
+                SKIP_WORD_KEY => session.skip_word(),
+                _ => session.handle_key(key),
+            }
+        }
+        session
+    }
+
+    pub fn with_clock(target_text: String, config: SessionConfig, clock: Box<dyn Clock>) -> Self {
+        // main checks the file can be opened, a later failure just drops telemetry
+        let telemetry = config
+            .telemetry
+            .as_deref()
+            .and_then(|path| Telemetry::open(path).ok());
+
         Self {
-            target_text,
+            config,
             user_input: String::new(),
             current_position: 0,
             errors: Vec::new(),
+            error_counts: HashMap::new(),
+            errors_at: HashMap::new(),
+            open_errors: HashSet::new(),
             key_stats: HashMap::new(),
-            session_start: Instant::now(),
+            session_start: clock.now(),
+            started_at: None,
             session_end: None,
             last_keystroke: None,
+            last_advance: None,
+            paused_at: None,
+            resumed: false,
             has_error: false,
             consecutive_errors: 0,
             is_frozen: false,
+            skipped_positions: std::collections::HashSet::new(),
+            died_at: None,
+            target_reached: None,
             total_corrections: 0,
             typing_rhythm: Vec::new(),
+            keystroke_log: Vec::new(),
             hesitation_patterns: Vec::new(),
             wpm_samples: Vec::new(),
+            accuracy_samples: Vec::new(),
+            snippet_score: None,
+            attribution: None,
+            source_meta: None,
+            recent_latencies: std::collections::VecDeque::with_capacity(RECENT_LATENCY_CAPACITY),
+            telemetry,
+            clock,
+            difficulty: estimate_difficulty(&target_text),
+            target_len: target_text.chars().count(),
+            target_text,
+        }
+    }
+
+    /// Time since the last keystroke, or since the start before the first one
+    pub fn idle_time(&self) -> Duration {
+        self.clock
+            .now()
+            .duration_since(self.last_keystroke.unwrap_or(self.session_start))
+    }
+
+    pub fn is_paused(&self) -> bool {
+        self.paused_at.is_some()
+    }
+
+    /// Stop the clock, backdated to the last keystroke so the idle stretch
+    /// itself doesn't count against WPM
+    pub fn pause_idle(&mut self) {
+        if self.is_paused() || self.session_end.is_some() {
+            return;
+        }
+        self.paused_at = Some(self.last_keystroke.unwrap_or(self.session_start));
+    }
+
+    /// Restart the clock. Every stored instant moves forward by the pause length,
+    /// so durations measured from the session start only cover active typing.
+    pub fn resume(&mut self) {
+        let Some(paused_at) = self.paused_at.take() else {
+            return;
+        };
+        let pause = self.clock.now().duration_since(paused_at);
+        self.resumed = true;
+
+        self.session_start += pause;
+        self.last_keystroke = self.last_keystroke.map(|last| last + pause);
+        for (instant, _) in self
+            .wpm_samples
+            .iter_mut()
+            .chain(self.accuracy_samples.iter_mut())
+        {
+            *instant += pause;
+        }
+    }
+
+    /// The clock starts on the first keystroke rather than when the text appears,
+    /// so every recorded timestamp is relative to it
+    fn start_timing(&mut self, now: Instant) {
+        if self.last_keystroke.is_none() {
+            self.session_start = now;
+            self.started_at = Some(chrono::Utc::now());
+            self.paused_at = None;
         }
     }
 
+    /// Active typing time so far, frozen while paused
+    fn elapsed(&self) -> Duration {
+        if self.last_keystroke.is_none() {
+            return Duration::ZERO;
+        }
+        self.paused_at
+            .unwrap_or_else(|| self.clock.now())
+            .duration_since(self.session_start)
+    }
+
     pub fn handle_key(&mut self, key: char) {
+        self.log_keystroke(key);
+        self.process_key(key, true);
+    }
+
+    /// Note a key press in the replayable keystroke log
+    fn log_keystroke(&mut self, key: char) {
+        let now = self.clock.now();
+        self.start_timing(now);
+        self.keystroke_log
+            .push((key, now.duration_since(self.session_start)));
+    }
+
+    /// A Tab press types a literal tab when the target has one, otherwise four
+    /// spaces. Either way it is recorded as a single logical keystroke in stats.
+    pub fn handle_tab(&mut self) {
+        self.log_keystroke('\t');
+        if self.target_text.chars().nth(self.current_position) == Some('\t') {
+            self.process_key('\t', true);
+            return;
+        }
+
         if self.is_frozen {
             return;
         }
 
-        let now = Instant::now();
+        self.start_timing(self.clock.now());
+        let after_pause = std::mem::take(&mut self.resumed);
+        if !self.config.zen && !after_pause {
+            let latency = self
+                .last_keystroke
+                .map_or(Duration::from_millis(0), |last| {
+                    self.clock.now().duration_since(last)
+                });
+            self.update_key_stats('\t', latency);
+        }
+
+        for _ in 0..4 {
+            self.process_key(' ', false);
+            if self.is_complete() {
+                break;
+            }
+        }
+    }
+
+    fn process_key(&mut self, key: char, record_stats: bool) {
+        if self.died_at.is_some() || self.target_reached.is_some() {
+            return;
+        }
+        let now = self.clock.now();
+        self.start_timing(now);
         let latency = if let Some(last) = self.last_keystroke {
             now.duration_since(last)
         } else {
             Duration::from_millis(0)
         };
+        let after_pause = std::mem::take(&mut self.resumed);
 
+        // Backspace is the way out of a frozen buffer, so it is always accepted
         if key == '\x08' {
+            self.record_telemetry(key, latency, None);
             self.handle_backspace();
             self.last_keystroke = Some(now);
             return;
         }
 
         let expected_char = self.target_text.chars().nth(self.current_position);
+        if self.is_frozen {
+            // Retype recovery: the right key thaws the buffer and corrects it
+            if self.config.freeze_recovery == FreezeRecovery::Retype && expected_char == Some(key) {
+                self.is_frozen = false;
+            } else {
+                return;
+            }
+        }
+
+        self.record_telemetry(key, latency, Some(expected_char == Some(key)));
 
+        // Stats first: samples taken there describe the input before this key
+        if record_stats && !self.config.zen && !after_pause {
+            self.update_key_stats(key, latency);
+        }
         self.user_input.push(key);
-        self.update_key_stats(key, latency);
 
         if let Some(expected) = expected_char {
             if key == expected {
                 // Correct character typed
+                self.resolve_errors_at(self.current_position, now);
+                self.last_advance = Some((self.current_position, now));
                 if !self.has_error {
                     // No errors, advance normally
                     self.current_position += 1;
                     // Check if we completed the text
-                    if self.current_position >= self.target_text.len() {
+                    if self.current_position >= self.target_len {
                         self.session_end = Some(now);
                     }
                 } else {
@@ -239,10 +1961,14 @@ impl TypingSession {
                     }
                     self.user_input = corrected_input;
 
-                    if self.current_position >= self.target_text.len() {
+                    if self.current_position >= self.target_len {
                         self.session_end = Some(now);
                     }
                 }
+            } else if self.config.instant {
+                // Nothing enters the buffer, the cursor waits for the right key
+                self.user_input.pop();
+                self.record_error(key, expected, now);
             } else {
                 // Incorrect character typed
                 self.handle_error(key, expected, now);
@@ -252,7 +1978,20 @@ impl TypingSession {
         self.last_keystroke = Some(now);
     }
 
+    /// Remove the last grapheme cluster, so an accented letter typed as a base
+    /// character plus a combining mark (or a multi-codepoint emoji) goes in one press
     fn handle_backspace(&mut self) {
+        let chars = self
+            .user_input
+            .graphemes(true)
+            .next_back()
+            .map_or(0, |grapheme| grapheme.chars().count());
+        for _ in 0..chars {
+            self.backspace_char();
+        }
+    }
+
+    fn backspace_char(&mut self) {
         if self.user_input.pop().is_some() {
             if self.has_error {
                 // Reduce consecutive errors when backspacing in error state
@@ -268,18 +2007,176 @@ impl TypingSession {
                 self.is_frozen = false;
                 self.total_corrections += 1;
             } else if self.current_position > 0 {
+                // Backspace freely across words and lines; stepping back over a
+                // skipped character reopens it for correction
                 self.current_position -= 1;
+                if self.skipped_positions.remove(&self.current_position) {
+                    self.total_corrections += 1;
+                }
             }
         }
     }
 
+    fn record_telemetry(&mut self, key: char, latency: Duration, correct: Option<bool>) {
+        let Some(telemetry) = &mut self.telemetry else {
+            return;
+        };
+        let event = TelemetryEvent {
+            position: self.current_position,
+            char: key,
+            latency_us: latency.as_micros() as u64,
+            correct,
+        };
+        // Stop streaming on a write error rather than failing the session
+        if telemetry.record(&event).is_err() {
+            self.telemetry = None;
+        }
+    }
+
+    pub fn flush_telemetry(&mut self) {
+        if let Some(telemetry) = &mut self.telemetry {
+            let _ = telemetry.flush();
+        }
+    }
+
+    /// Mark every open error at `position` as corrected, now that it has been typed right
+    fn resolve_errors_at(&mut self, position: usize, now: Instant) {
+        // Most correct keys land where no error was ever made
+        if !self.open_errors.remove(&position) {
+            return;
+        }
+        let timestamp = now.duration_since(self.session_start);
+        for error in self
+            .errors
+            .iter_mut()
+            .filter(|e| e.position == position && e.correction_timestamp.is_none())
+        {
+            error.correction_timestamp = Some(timestamp);
+            error.correction_latency = Some(timestamp.saturating_sub(error.timestamp));
+        }
+    }
+
+    fn average_correction_latency(&self) -> Option<Duration> {
+        let latencies: Vec<Duration> = self
+            .errors
+            .iter()
+            .filter_map(|e| e.correction_latency)
+            .collect();
+        if latencies.is_empty() {
+            None
+        } else {
+            Some(latencies.iter().sum::<Duration>() / latencies.len() as u32)
+        }
+    }
+
+    /// Give up on the current word: advance past the next whitespace, recording
+    /// every skipped character as an omission
+    pub fn skip_word(&mut self) {
+        if self.current_position >= self.target_len || self.died_at.is_some() {
+            return;
+        }
+        self.log_keystroke(SKIP_WORD_KEY);
+
+        let now = self.clock.now();
+        self.start_timing(now);
+
+        // Drop whatever is in the error buffer, the word is abandoned anyway
+        self.has_error = false;
+        self.consecutive_errors = 0;
+        self.is_frozen = false;
+        self.user_input = self
+            .target_text
+            .chars()
+            .take(self.current_position)
+            .collect();
+
+        let skipped_from = self.current_position;
+        let remaining: Vec<char> = self
+            .target_text
+            .chars()
+            .skip(self.current_position)
+            .collect();
+        let skip_len = remaining
+            .iter()
+            .position(|c| c.is_whitespace())
+            .map_or(remaining.len(), |i| i + 1);
+
+        for &ch in &remaining[..skip_len] {
+            let (context_before, context_after) = self.context_around(self.current_position);
+            self.push_error(ErrorEvent {
+                error_type: ErrorType::Omission,
+                position: self.current_position,
+                expected_char: Some(ch),
+                actual_char: None,
+                timestamp: now.duration_since(self.session_start),
+                correction_timestamp: None,
+                correction_latency: None,
+                context_before,
+                context_after,
+            });
+            self.skipped_positions.insert(self.current_position);
+            self.user_input.push(ch);
+            self.current_position += 1;
+        }
+
+        if self.current_position >= self.target_len {
+            self.session_end = Some(now);
+        }
+        if self.config.sudden_death {
+            self.die(skipped_from, now);
+        }
+        self.last_keystroke = Some(now);
+    }
+
+    /// End a --sudden-death session at its first error
+    fn die(&mut self, position: usize, now: Instant) {
+        if self.died_at.is_none() {
+            self.died_at = Some(position);
+            self.session_end = Some(now);
+        }
+    }
+
+    /// Characters typed correctly, excluding ones given up on via skip
+    fn correct_characters(&self) -> usize {
+        self.current_position - self.skipped_positions.len()
+    }
+
     fn handle_error(&mut self, actual: char, expected: char, timestamp: Instant) {
-        let error_type = if actual == expected {
-            ErrorType::Repeat
+        self.record_error(actual, expected, timestamp);
+        self.has_error = true;
+        self.consecutive_errors += 1;
+
+        // Freeze after 10 consecutive errors, unless only warning about it
+        if self.over_error_cap() && !self.config.soft_cap {
+            self.is_frozen = true;
+        }
+    }
+
+    /// Classify and log a wrong keystroke, without touching the error buffer
+    fn record_error(&mut self, actual: char, expected: char, timestamp: Instant) {
+        let position = self.current_position;
+        if self.config.sudden_death {
+            self.die(position, timestamp);
+        }
+        let target_at = |offset: isize| {
+            position
+                .checked_add_signed(offset)
+                .and_then(|i| self.target_text.chars().nth(i))
+        };
+        let previous = target_at(-1);
+
+        // Doubling: one letter too many right after a double letter, or typing
+        // the next letter while the second half of a double is still due
+        let extra_double = previous == Some(actual) && target_at(-2) == Some(actual);
+        let missing_double = previous == Some(expected) && target_at(1) == Some(actual);
+
+        let error_type = if !self.has_error && (extra_double || missing_double) {
+            ErrorType::Doubling
         } else {
             ErrorType::Substitution
         };
 
+        let (context_before, context_after) = self.context_around(self.current_position);
         let error = ErrorEvent {
             error_type,
             position: self.current_position,
@@ -288,20 +2185,51 @@ impl TypingSession {
             timestamp: timestamp.duration_since(self.session_start),
             correction_timestamp: None,
             correction_latency: None,
+            context_before,
+            context_after,
         };
 
+        self.push_error(error);
+    }
+
+    /// Log an error, counting it towards the session totals
+    fn push_error(&mut self, error: ErrorEvent) {
+        *self
+            .error_counts
+            .entry(error.error_type.clone())
+            .or_default() += 1;
+        *self.errors_at.entry(error.position).or_default() += 1;
+        self.open_errors.insert(error.position);
         self.errors.push(error);
-        self.has_error = true;
-        self.consecutive_errors += 1;
+    }
 
-        // Freeze after 10 consecutive errors
-        if self.consecutive_errors >= 10 {
-            self.is_frozen = true;
-        }
+    /// Up to ERROR_CONTEXT_CHARS of target text on either side of `position`,
+    /// the character at `position` itself excluded
+    fn context_around(&self, position: usize) -> (String, String) {
+        let start = position.saturating_sub(ERROR_CONTEXT_CHARS);
+        let before = self
+            .target_text
+            .chars()
+            .skip(start)
+            .take(position - start)
+            .collect();
+        let after = self
+            .target_text
+            .chars()
+            .skip(position + 1)
+            .take(ERROR_CONTEXT_CHARS)
+            .collect();
+        (before, after)
+    }
+
+    pub fn over_error_cap(&self) -> bool {
+        self.consecutive_errors >= MAX_CONSECUTIVE_ERRORS
     }
 
     fn update_key_stats(&mut self, key: char, latency: Duration) {
-        let now = Instant::now();
+        let now = self.clock.now();
+        // Kept in µs: fast typists produce sub-millisecond gaps that would round to 0
+        let latency_us = latency.as_micros() as u64;
         let latency_ms = latency.as_millis() as u64;
 
         // Update key statistics
@@ -310,14 +2238,21 @@ impl TypingSession {
             count: 0,
             total_latency: Duration::from_millis(0),
             error_count: 0,
-            latencies: Vec::new(),
+            latencies_us: Vec::new(),
             positions: Vec::new(),
+            error_flags: Vec::new(),
         });
 
         stat.count += 1;
-        stat.total_latency += latency;
-        stat.latencies.push(latency_ms);
+        stat.total_latency += Duration::from_micros(latency_us);
+        stat.latencies_us.push(latency_us);
         stat.positions.push(self.current_position);
+        stat.error_flags.push(self.has_error);
+
+        if self.recent_latencies.len() == RECENT_LATENCY_CAPACITY {
+            self.recent_latencies.pop_front();
+        }
+        self.recent_latencies.push_back(latency_us);
 
         if self.has_error {
             stat.error_count += 1;
@@ -332,7 +2267,7 @@ impl TypingSession {
         });
 
         // Detect hesitation patterns
-        if latency_ms > 500 {
+        if latency_ms > self.hesitation_ms() {
             let preceding = if self.current_position >= 3 {
                 self.target_text
                     .chars()
@@ -361,13 +2296,53 @@ impl TypingSession {
             });
         }
 
-        // Sample WPM every 10 characters
-        if self.current_position % 10 == 0 && self.current_position > 0 {
+        // Sample WPM and accuracy every interval, or every 10 characters by default
+        let sample_due = match self.config.sample_interval {
+            Some(interval) => self
+                .wpm_samples
+                .last()
+                .is_none_or(|(last, _)| now.duration_since(*last) >= interval),
+            None => self.current_position.is_multiple_of(10) && self.current_position > 0,
+        };
+        if sample_due {
             let wpm = self.calculate_wpm();
             self.wpm_samples.push((now, wpm));
+            let accuracy = self.calculate_accuracy();
+            self.accuracy_samples.push((now, accuracy));
+
+            if let Some(target) = self.config.until_wpm
+                && self.target_reached.is_none()
+            {
+                self.target_reached = sustained_wpm_since(
+                    &self.wpm_over_time(),
+                    target as f64,
+                    UNTIL_WPM_SMOOTHING,
+                    UNTIL_WPM_HOLD,
+                );
+                if self.target_reached.is_some() {
+                    self.session_end = Some(now);
+                }
+            }
+        }
+
+        // Keep only the most recent samples when a cap is configured; the
+        // keystroke log stays whole so the session can still be replayed
+        if let Some(max) = self.config.max_samples {
+            keep_last(&mut self.typing_rhythm, max);
+            keep_last(&mut self.errors, max);
+            keep_last(&mut self.hesitation_patterns, max);
+            keep_last(&mut self.wpm_samples, max);
+            keep_last(&mut self.accuracy_samples, max);
+            if let Some(stat) = self.key_stats.get_mut(&key) {
+                stat.keep_last(max);
+            }
         }
     }
 
+    fn hesitation_ms(&self) -> u64 {
+        self.config.hesitation_ms.unwrap_or(DEFAULT_HESITATION_MS)
+    }
+
     fn detect_hesitation_type(
         &self,
         key: char,
@@ -375,16 +2350,17 @@ impl TypingSession {
         preceding: &str,
         _following: &str,
     ) -> HesitationType {
-        if latency_ms > 1000 {
+        if latency_ms > 2 * self.hesitation_ms() {
             return HesitationType::LongPause;
         }
 
-        if key.is_ascii_punctuation() {
-            return HesitationType::Punctuation;
+        // Shifted/AltGr symbols first, so plain punctuation is what remains
+        if key.is_ascii_digit() || symbol_chars(self.config.layout).contains(key) {
+            return HesitationType::NumberSymbol;
         }
 
-        if key.is_ascii_digit() || "!@#$%^&*()_+{}|:<>?".contains(key) {
-            return HesitationType::NumberSymbol;
+        if key.is_ascii_punctuation() {
+            return HesitationType::Punctuation;
         }
 
         if key.is_uppercase() != preceding.chars().last().is_some_and(|c| c.is_uppercase()) {
@@ -404,12 +2380,44 @@ impl TypingSession {
         HesitationType::Transition
     }
 
+    /// Character index a typist at the target WPM would have reached by now.
+    /// None without a target, or once the pace has run off the end of the text.
+    pub fn pace_position(&self) -> Option<usize> {
+        let position = self.pace_chars_at(self.elapsed())? as usize;
+        (position < self.target_text.chars().count()).then_some(position)
+    }
+
+    /// Characters a --target-wpm or --ramp pacer has covered after `elapsed`
+    fn pace_chars_at(&self, elapsed: Duration) -> Option<f64> {
+        if let Some(ramp) = self.config.ramp {
+            return Some(ramp.chars_at(elapsed, self.target_text.chars().count()));
+        }
+        let target_wpm = self.config.target_wpm?;
+        Some(target_wpm as f64 * 5.0 * elapsed.as_secs_f64() / 60.0)
+    }
+
+    /// Whether each of the RAMP_SEGMENTS passage segments was finished ahead of
+    /// the ramp's pace marker; segments never reached are left out
+    fn ramp_segments(&self) -> Vec<bool> {
+        if self.config.ramp.is_none() {
+            return Vec::new();
+        }
+        let len = self.target_text.chars().count();
+        (1..=RAMP_SEGMENTS)
+            .map_while(|segment| {
+                let end = len * segment / RAMP_SEGMENTS;
+                let reached = self.typing_rhythm.iter().find(|r| r.position + 1 >= end)?;
+                Some(self.pace_chars_at(reached.timestamp)? <= end as f64)
+            })
+            .collect()
+    }
+
     pub fn calculate_wpm(&self) -> f64 {
-        let elapsed = self.session_start.elapsed().as_secs_f64() / 60.0;
+        let elapsed = self.elapsed().as_secs_f64() / 60.0;
         if elapsed == 0.0 {
             0.0
         } else {
-            (self.current_position as f64 / 5.0) / elapsed
+            (self.correct_characters() as f64 / 5.0) / elapsed
         }
     }
 
@@ -417,37 +2425,130 @@ impl TypingSession {
         if self.user_input.is_empty() {
             100.0
         } else {
-            (self.current_position as f64 / self.user_input.len() as f64) * 100.0
+            (self.correct_characters() as f64 / self.user_input.chars().count() as f64) * 100.0
         }
     }
 
     pub fn is_complete(&self) -> bool {
-        self.current_position >= self.target_text.len() && !self.has_error
+        self.died_at.is_some()
+            || self.target_reached.is_some()
+            || self.current_position >= self.target_len
+                && !self.has_error
+                && (!self.config.require_clean || self.outstanding_errors() == 0)
+    }
+
+    /// Whether a finished passage should be extended rather than ended, because
+    /// the --until-wpm target hasn't been held yet
+    pub fn wants_more_text(&self) -> bool {
+        self.config.until_wpm.is_some() && self.target_reached.is_none() && self.died_at.is_none()
+    }
+
+    /// Append another snippet to the passage and carry on typing
+    fn extend_text(&mut self, more: &str) {
+        self.target_text.push('\n');
+        self.target_text.push_str(more);
+        self.target_len = self.target_text.chars().count();
+        self.session_end = None;
+    }
+
+    /// Positions accepted without ever being typed correctly (e.g. skipped words)
+    pub fn outstanding_errors(&self) -> usize {
+        self.skipped_positions.len()
     }
 
     pub fn get_status(&self) -> String {
-        if self.is_frozen {
-            "FROZEN: 10 consecutive errors! Use backspace to correct.".to_string()
+        if self.is_paused() {
+            "PAUSED: no keystrokes for a while - press any key to resume".to_string()
+        } else if self.is_frozen {
+            match self.config.freeze_recovery {
+                FreezeRecovery::Backspace => {
+                    "FROZEN: 10 consecutive errors! Use backspace to correct.".to_string()
+                }
+                FreezeRecovery::Retype => {
+                    "FROZEN: 10 consecutive errors! Type the correct character to continue."
+                        .to_string()
+                }
+            }
+        } else if self.over_error_cap() {
+            format!(
+                "WARNING: {} consecutive errors! Slow down and use backspace to correct",
+                self.consecutive_errors
+            )
         } else if self.has_error {
             format!(
                 "ERROR BUFFER: {} of 10 errors - use backspace to correct",
                 self.consecutive_errors
             )
+        } else if self.current_position >= self.target_len && !self.is_complete() {
+            format!(
+                "ALMOST: {} skipped characters left uncorrected - backspace to fix them",
+                self.outstanding_errors()
+            )
         } else {
             "Ready".to_string()
         }
     }
 
-    pub fn generate_styled_text(&self) -> Vec<Line<'static>> {
+    /// Under --feedback the character just typed flashes white, then fades
+    /// through light green back to the normal style
+    fn feedback_style(&self, position: usize, style: Style) -> Style {
+        if !self.config.feedback {
+            return style;
+        }
+        match self.last_advance {
+            Some((typed, at)) if typed == position => {
+                let age = self.clock.now().saturating_duration_since(at);
+                if age < FEEDBACK_DECAY / 2 {
+                    style.fg(Color::White).add_modifier(Modifier::BOLD)
+                } else if age < FEEDBACK_DECAY {
+                    style.fg(Color::LightGreen)
+                } else {
+                    style
+                }
+            }
+            _ => style,
+        }
+    }
+
+    pub fn generate_styled_text(&self, ghost_position: Option<usize>) -> Vec<Line<'static>> {
         let target_chars: Vec<char> = self.target_text.chars().collect();
         let user_chars: Vec<char> = self.user_input.chars().collect();
 
         let mut lines = Vec::new();
         let mut current_line_spans = Vec::new();
 
+        // Pace and ghost markers are drawn as coloured carets on whichever
+        // character they sit
+        let pace_position = self.pace_position();
+        let with_pace = |i: usize, style: Style| {
+            if ghost_position == Some(i) {
+                style.bg(Color::Blue)
+            } else if pace_position == Some(i) {
+                style.bg(Color::Magenta)
+            } else {
+                style
+            }
+        };
+
         // Display correctly typed characters in green
-        for i in 0..self.current_position.min(target_chars.len()) {
-            let ch = target_chars[i];
+        for (i, &ch) in target_chars.iter().enumerate().take(self.current_position) {
+            // Skipped characters are struck through instead of green
+            let typed_style = if self.skipped_positions.contains(&i) {
+                Style::default()
+                    .fg(Color::Yellow)
+                    .add_modifier(Modifier::CROSSED_OUT)
+            } else {
+                Style::default().fg(Color::Green)
+            };
+            let typed_style = with_pace(i, self.feedback_style(i, typed_style));
+            let ch = match self.config.mask_char {
+                Some(mask)
+                    if !matches!(ch, '\n' | '\t') && !self.skipped_positions.contains(&i) =>
+                {
+                    mask
+                }
+                _ => ch,
+            };
 
             if ch == '\n' {
                 // End current line and start a new one
@@ -460,34 +2561,26 @@ impl TypingSession {
                     // Last correctly typed character with cursor - green with underline
                     current_line_spans.push(Span::styled(
                         display_text.to_string(),
-                        Style::default()
-                            .fg(Color::Green)
+                        typed_style
                             .add_modifier(Modifier::UNDERLINED)
                             .add_modifier(Modifier::BOLD),
                     ));
                 } else {
                     // Other correctly typed characters - green
-                    current_line_spans.push(Span::styled(
-                        display_text.to_string(),
-                        Style::default().fg(Color::Green),
-                    ));
+                    current_line_spans.push(Span::styled(display_text.to_string(), typed_style));
                 }
             } else {
                 if i == self.current_position - 1 && !self.has_error && !self.is_frozen {
                     // Last correctly typed character with cursor - green with underline
                     current_line_spans.push(Span::styled(
                         ch.to_string(),
-                        Style::default()
-                            .fg(Color::Green)
+                        typed_style
                             .add_modifier(Modifier::UNDERLINED)
                             .add_modifier(Modifier::BOLD),
                     ));
                 } else {
                     // Other correctly typed characters - green
-                    current_line_spans.push(Span::styled(
-                        ch.to_string(),
-                        Style::default().fg(Color::Green),
-                    ));
+                    current_line_spans.push(Span::styled(ch.to_string(), typed_style));
                 }
             }
         }
@@ -501,8 +2594,12 @@ impl TypingSession {
             let mut chars_to_show = Vec::new();
 
             // Collect only the actual error characters
-            for i in error_start..user_chars.len().min(error_start + 10) {
-                let user_char = user_chars[i];
+            for (i, &user_char) in user_chars
+                .iter()
+                .enumerate()
+                .take(error_start + self.consecutive_errors.max(MAX_CONSECUTIVE_ERRORS))
+                .skip(error_start)
+            {
                 let expected_char_at_pos = self.target_text.chars().nth(i);
 
                 // Only include characters that don't match what's expected at their position
@@ -563,6 +2660,22 @@ impl TypingSession {
             }
         }
 
+        // A custom cursor glyph sits between the typed text and what is left
+        if let Some(cursor) = self.config.cursor_char
+            && self.current_position < target_chars.len()
+        {
+            current_line_spans.push(Span::styled(
+                cursor.to_string(),
+                Style::default()
+                    .fg(if self.has_error {
+                        Color::Red
+                    } else {
+                        Color::Green
+                    })
+                    .add_modifier(Modifier::BOLD),
+            ));
+        }
+
         // Display remaining target text in gray
         let start_pos = if self.has_error {
             (self.current_position + self.consecutive_errors).min(target_chars.len())
@@ -570,22 +2683,35 @@ impl TypingSession {
             self.current_position
         };
 
-        for i in start_pos..target_chars.len() {
-            let ch = target_chars[i];
+        for (i, &ch) in target_chars.iter().enumerate().skip(start_pos) {
+            // Blind mode blanks text past the look-ahead window (not every terminal
+            // honours the hidden attribute); the blanks keep their cells so the
+            // layout doesn't shift as characters are revealed
+            let concealed = self.config.blind && i >= self.current_position + BLIND_LOOKAHEAD;
+            let pending_style = |i: usize, style: Style| {
+                with_pace(i, if concealed { Style::default() } else { style })
+            };
 
             if ch == '\n' {
                 lines.push(Line::from(current_line_spans.clone()));
                 current_line_spans.clear();
+            } else if ch == ' ' && matches!(target_chars.get(i + 1), None | Some('\n')) {
+                // Trailing spaces are invisible otherwise, give them a subtle background
+                current_line_spans.push(Span::styled(
+                    " ".to_string(),
+                    pending_style(i, Style::default().bg(Color::DarkGray)),
+                ));
             } else if ch == '\t' {
                 // Convert tab to 4 spaces in remaining text
                 current_line_spans.push(Span::styled(
                     "    ".to_string(), // 4 spaces
-                    Style::default().fg(Color::DarkGray),
+                    pending_style(i, Style::default().fg(Color::DarkGray)),
                 ));
             } else {
+                let ch = if concealed { ' ' } else { ch };
                 current_line_spans.push(Span::styled(
                     ch.to_string(),
-                    Style::default().fg(Color::DarkGray),
+                    pending_style(i, Style::default().fg(Color::DarkGray)),
                 ));
             }
         }
@@ -593,7 +2719,7 @@ impl TypingSession {
         // Add cursor at the end if we've typed everything without errors
         if self.current_position >= target_chars.len() && !self.has_error {
             current_line_spans.push(Span::styled(
-                "|".to_string(),
+                self.config.cursor_char.unwrap_or('|').to_string(),
                 Style::default()
                     .fg(Color::Green)
                     .add_modifier(Modifier::BOLD),
@@ -605,6 +2731,75 @@ impl TypingSession {
             lines.push(Line::from(current_line_spans));
         }
 
+        if self.config.break_ligatures {
+            lines.iter_mut().for_each(separate_ligatures);
+        }
+        lines
+    }
+
+    /// Rebuild the text state and running WPM right after keystroke `index`
+    pub fn reconstruct_at(&self, index: usize) -> Option<KeystrokeState> {
+        let rhythm = self.typing_rhythm.get(index)?;
+        let expected_char = self.target_text.chars().nth(rhythm.position);
+        let progress = if expected_char == Some(rhythm.char_typed) {
+            rhythm.position + 1
+        } else {
+            rhythm.position
+        };
+
+        let minutes = rhythm.timestamp.as_secs_f64() / 60.0;
+        let wpm = if minutes == 0.0 {
+            0.0
+        } else {
+            (progress as f64 / 5.0) / minutes
+        };
+
+        Some(KeystrokeState {
+            index,
+            correct_prefix: self.target_text.chars().take(rhythm.position).collect(),
+            typed_char: rhythm.char_typed,
+            expected_char,
+            elapsed: rhythm.timestamp,
+            wpm,
+        })
+    }
+
+    /// Render the full target text for review, highlighting the characters
+    /// at the given positions (e.g. where the user hesitated)
+    pub fn generate_review_text(&self, marked_positions: &[usize]) -> Vec<Line<'static>> {
+        let marked: std::collections::HashSet<usize> = marked_positions.iter().copied().collect();
+
+        let mut lines = Vec::new();
+        let mut current_line_spans = Vec::new();
+
+        for (i, ch) in self.target_text.chars().enumerate() {
+            let style = if marked.contains(&i) {
+                Style::default()
+                    .bg(Color::Magenta)
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD)
+            } else {
+                Style::default().fg(Color::DarkGray)
+            };
+
+            if ch == '\n' {
+                if marked.contains(&i) {
+                    // Make a hesitation on a newline visible at the end of the line
+                    current_line_spans.push(Span::styled("↵".to_string(), style));
+                }
+                lines.push(Line::from(current_line_spans.clone()));
+                current_line_spans.clear();
+            } else if ch == '\t' {
+                current_line_spans.push(Span::styled("    ".to_string(), style));
+            } else {
+                current_line_spans.push(Span::styled(ch.to_string(), style));
+            }
+        }
+
+        if !current_line_spans.is_empty() {
+            lines.push(Line::from(current_line_spans));
+        }
+
         lines
     }
 
@@ -612,13 +2807,21 @@ impl TypingSession {
         let session_duration = if let Some(end_time) = self.session_end {
             end_time.duration_since(self.session_start)
         } else {
-            self.session_start.elapsed()
+            self.elapsed()
         };
 
         let total_latency: Duration = self.key_stats.values().map(|stat| stat.total_latency).sum();
         let total_keys: u32 = self.key_stats.values().map(|stat| stat.count).sum();
 
-        let average_latency = if total_keys > 0 {
+        let average_latency = if let Some(cutoff) = self.latency_cutoff() {
+            let kept: Vec<Duration> = self
+                .typing_rhythm
+                .iter()
+                .map(|r| r.latency)
+                .filter(|&latency| latency <= cutoff)
+                .collect();
+            kept.iter().sum::<Duration>() / kept.len().max(1) as u32
+        } else if total_keys > 0 {
             total_latency / total_keys
         } else {
             Duration::from_millis(0)
@@ -626,39 +2829,242 @@ impl TypingSession {
 
         SessionReport {
             session_duration,
-            total_characters: self.user_input.len(),
-            correct_characters: self.current_position,
+            total_characters: self.user_input.chars().count(),
+            correct_characters: self.correct_characters(),
             wpm: self.calculate_wpm_with_duration(session_duration),
+            raw_wpm: words_per_minute(self.keystroke_log.len(), session_duration),
             accuracy: self.calculate_accuracy(),
             average_latency,
             errors: self.errors.clone(),
+            error_counts: self.error_counts.clone(),
             key_stats: self.key_stats.clone(),
             total_corrections: self.total_corrections,
-            average_correction_latency: None,
+            average_correction_latency: self.average_correction_latency(),
             typing_rhythm: self.typing_rhythm.clone(),
             hesitation_patterns: self.hesitation_patterns.clone(),
             weakness_analysis: self.analyze_weaknesses(),
-            wpm_over_time: self
-                .wpm_samples
+            wpm_over_time: self.wpm_over_time(),
+            accuracy_over_time: self
+                .accuracy_samples
                 .iter()
-                .map(|(instant, wpm)| (instant.duration_since(self.session_start), *wpm))
+                .map(|(instant, acc)| (instant.duration_since(self.session_start), *acc))
                 .collect(),
+            label: self.config.label.clone(),
+            snippet_score: self.snippet_score,
+            attribution: self.attribution.clone(),
+            source_meta: self.source_meta.clone(),
+            target_text: self.target_text.clone(),
+            coverage: self.coverage(),
+            doubling_errors: self
+                .error_counts
+                .get(&ErrorType::Doubling)
+                .copied()
+                .unwrap_or_default(),
+            ramp: self.config.ramp.map(|ramp| (ramp.start, ramp.end)),
+            ramp_segments: self.ramp_segments(),
+            line_times: self.line_times(),
+            sentence_stats: self.sentence_stats(),
+            paste_suspected: self.paste_suspected(),
+            error_free_chars: self
+                .config
+                .sudden_death
+                .then(|| self.died_at.unwrap_or(self.current_position)),
+            until_wpm: self
+                .config
+                .until_wpm
+                .map(|target| (target, self.target_reached)),
+            keystroke_log: self.keystroke_log.clone(),
+            content_wpm: self.content_wpm(),
+            started_at: self.started_at,
+        }
+    }
+
+    /// WPM samples as time since the start of the session
+    fn wpm_over_time(&self) -> Vec<(Duration, f64)> {
+        self.wpm_samples
+            .iter()
+            .map(|(instant, wpm)| (instant.duration_since(self.session_start), *wpm))
+            .collect()
+    }
+
+    /// Split the rhythm log by whether each keystroke's line is a comment,
+    /// and compute WPM from the correct keys and time spent on each side
+    fn content_wpm(&self) -> Option<ContentWpm> {
+        if !self.config.split_wpm {
+            return None;
+        }
+
+        let mut line_is_comment = Vec::new();
+        for line in self.target_text.split('\n') {
+            let comment = is_comment_line(line);
+            // The newline belongs to the line it ends
+            line_is_comment.extend(std::iter::repeat_n(comment, line.chars().count() + 1));
+        }
+
+        let target_chars: Vec<char> = self.target_text.chars().collect();
+        // (correct keys, time) for code and comment lines
+        let mut code = (0usize, Duration::ZERO);
+        let mut comment = (0usize, Duration::ZERO);
+        for rhythm in &self.typing_rhythm {
+            let Some(&is_comment) = line_is_comment.get(rhythm.position) else {
+                continue;
+            };
+            let side = if is_comment { &mut comment } else { &mut code };
+            side.1 += rhythm.latency;
+            if target_chars.get(rhythm.position) == Some(&rhythm.char_typed) {
+                side.0 += 1;
+            }
+        }
+
+        let wpm = |(correct, time): (usize, Duration)| {
+            let minutes = time.as_secs_f64() / 60.0;
+            (minutes > 0.0).then(|| correct as f64 / 5.0 / minutes)
+        };
+        Some(ContentWpm {
+            code: wpm(code)?,
+            comment: wpm(comment)?,
+        })
+    }
+
+    /// Whether the rhythm log has a burst of keys too fast to be typed by hand
+    fn paste_suspected(&self) -> bool {
+        if self.config.paste_guard == PasteGuard::Off {
+            return false;
+        }
+        let mut burst = 0;
+        // The first keystroke has no predecessor and always shows zero latency
+        for rhythm in self.typing_rhythm.iter().skip(1) {
+            if rhythm.latency < PASTE_MAX_LATENCY {
+                burst += 1;
+                if burst >= PASTE_BURST_KEYS {
+                    return true;
+                }
+            } else {
+                burst = 0;
+            }
+        }
+        false
+    }
+
+    /// Time spent on each line of the target, from its first keystroke to the
+    /// one typing its newline (or its last keystroke, on the final line).
+    /// Lines with fewer than two keystrokes are left out.
+    fn line_times(&self) -> Vec<(usize, Duration)> {
+        let mut line_times = Vec::new();
+        let mut start = 0;
+        for (index, line) in self.target_text.split('\n').enumerate() {
+            let end = start + line.chars().count(); // Position of the newline
+            let mut in_line = self
+                .typing_rhythm
+                .iter()
+                .filter(|r| (start..=end).contains(&r.position));
+            if let (Some(first), Some(last)) = (in_line.next(), in_line.next_back()) {
+                line_times.push((index + 1, last.timestamp.saturating_sub(first.timestamp)));
+            }
+            start = end + 1;
+        }
+        line_times
+    }
+
+    /// WPM over the positions typed in each sentence, from the key before its
+    /// first one to its last key, and the errors made inside it. Sentences
+    /// not typed yet are left out.
+    fn sentence_stats(&self) -> Vec<SentenceStat> {
+        sentence_spans(&self.target_text)
+            .into_iter()
+            .filter_map(|(start, end)| {
+                let in_sentence: Vec<&TypingRhythm> = self
+                    .typing_rhythm
+                    .iter()
+                    .filter(|r| (start..end).contains(&r.position))
+                    .collect();
+                let (first, last) = (in_sentence.first()?, in_sentence.last()?);
+                let typed: HashSet<usize> = in_sentence.iter().map(|r| r.position).collect();
+                let began = first.timestamp.saturating_sub(first.latency);
+                Some(SentenceStat {
+                    start,
+                    end,
+                    wpm: words_per_minute(typed.len(), last.timestamp.saturating_sub(began)),
+                    errors: (start..end)
+                        .filter_map(|position| self.errors_at.get(&position))
+                        .sum(),
+                })
+            })
+            .collect()
+    }
+
+    fn coverage(&self) -> Coverage {
+        let visible = |c: &char| !c.is_whitespace();
+        let sorted = |chars: std::collections::BTreeSet<char>| chars.into_iter().collect();
+
+        Coverage {
+            present: sorted(self.target_text.chars().filter(visible).collect()),
+            typed_correctly: sorted(
+                self.target_text
+                    .chars()
+                    .take(self.current_position)
+                    .enumerate()
+                    .filter(|(i, c)| visible(c) && !self.skipped_positions.contains(i))
+                    .map(|(_, c)| c)
+                    .collect(),
+            ),
+            exercised: sorted(self.key_stats.keys().copied().filter(visible).collect()),
         }
     }
 
     fn calculate_wpm_with_duration(&self, duration: Duration) -> f64 {
-        let elapsed_minutes = duration.as_secs_f64() / 60.0;
-        if elapsed_minutes == 0.0 {
-            0.0
-        } else {
-            (self.current_position as f64 / 5.0) / elapsed_minutes
+        words_per_minute(self.correct_characters(), duration)
+    }
+
+    /// Text of the error cluster with the most errors, widened to whole words,
+    /// for a focused re-practice drill
+    pub fn weakest_cluster_text(&self) -> Option<String> {
+        let clusters = self.analyze_weaknesses().error_clusters;
+        let errors_in = |&(start, end): &(usize, usize)| {
+            self.errors
+                .iter()
+                .filter(|e| e.position >= start && e.position <= end)
+                .count()
+        };
+        let (start, end) = clusters.into_iter().max_by_key(errors_in)?;
+
+        let chars: Vec<char> = self.target_text.chars().collect();
+        let end = end.min(chars.len().checked_sub(1)?);
+        let start = chars[..start]
+            .iter()
+            .rposition(|c| c.is_whitespace())
+            .map_or(0, |i| i + 1);
+        let end = chars[end..]
+            .iter()
+            .position(|c| c.is_whitespace())
+            .map_or(chars.len(), |i| end + i);
+
+        let drill: String = chars[start..end].iter().collect();
+        let drill = drill.trim();
+        (!drill.is_empty()).then(|| drill.to_string())
+    }
+
+    /// Latency above which keystrokes are left out of averages, when trimming
+    fn latency_cutoff(&self) -> Option<Duration> {
+        if !self.config.trim_outliers {
+            return None;
         }
+        percentile_cutoff(
+            self.typing_rhythm.iter().map(|r| r.latency).collect(),
+            OUTLIER_PERCENTILE,
+        )
     }
 
     fn analyze_weaknesses(&self) -> WeaknessAnalysis {
+        let cutoff = self.latency_cutoff();
+        let is_outlier = |latency: Duration| cutoff.is_some_and(|cutoff| latency > cutoff);
+
         // Analyze slowest digraphs
         let mut digraph_latencies: HashMap<String, Vec<u64>> = HashMap::new();
         for rhythm in &self.typing_rhythm {
+            if is_outlier(rhythm.latency) {
+                continue;
+            }
             if rhythm.position > 0
                 && let Some(prev_char) = self.target_text.chars().nth(rhythm.position - 1)
             {
@@ -666,7 +3072,7 @@ impl TypingSession {
                 digraph_latencies
                     .entry(digraph)
                     .or_default()
-                    .push(rhythm.latency.as_millis() as u64);
+                    .push(rhythm.latency.as_micros() as u64);
             }
         }
 
@@ -674,8 +3080,8 @@ impl TypingSession {
             .into_iter()
             .filter(|(_, latencies)| latencies.len() >= 2) // Only consider repeated digraphs
             .map(|(digraph, latencies)| {
-                let avg = latencies.iter().sum::<u64>() as f64 / latencies.len() as f64;
-                (digraph, avg)
+                let avg_us = latencies.iter().sum::<u64>() as f64 / latencies.len() as f64;
+                (digraph, avg_us / 1000.0)
             })
             .collect();
         slowest_digraphs.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap());
@@ -729,13 +3135,13 @@ impl TypingSession {
         let latencies: Vec<u64> = self
             .typing_rhythm
             .iter()
-            .map(|r| r.latency.as_millis() as u64)
+            .map(|r| r.latency.as_micros() as u64)
             .collect();
 
         if latencies.len() > 5 {
             for i in 5..latencies.len() {
                 let moving_avg = latencies[i - 5..i].iter().sum::<u64>() / 5;
-                if latencies[i] > moving_avg * 2 && latencies[i] > 400 {
+                if latencies[i] > moving_avg * 2 && latencies[i] > 400_000 {
                     rhythm_breaks.push(self.typing_rhythm[i].position);
                 }
             }
@@ -744,9 +3150,12 @@ impl TypingSession {
         // Analyze problematic transitions
         let mut transition_latencies: HashMap<(char, char), Vec<u64>> = HashMap::new();
         for i in 1..self.typing_rhythm.len() {
+            if is_outlier(self.typing_rhythm[i].latency) {
+                continue;
+            }
             let prev_char = self.typing_rhythm[i - 1].char_typed;
             let curr_char = self.typing_rhythm[i].char_typed;
-            let latency = self.typing_rhythm[i].latency.as_millis() as u64;
+            let latency = self.typing_rhythm[i].latency.as_micros() as u64;
 
             transition_latencies
                 .entry((prev_char, curr_char))
@@ -758,20 +3167,75 @@ impl TypingSession {
             .into_iter()
             .filter(|(_, latencies)| latencies.len() >= 2)
             .map(|((from, to), latencies)| {
-                let avg = latencies.iter().sum::<u64>() as f64 / latencies.len() as f64;
-                (from, to, avg)
+                let avg_us = latencies.iter().sum::<u64>() as f64 / latencies.len() as f64;
+                (from, to, avg_us / 1000.0)
             })
             .filter(|(_, _, avg)| *avg > 300.0) // Only slow transitions
             .collect();
         problematic_transitions.sort_by(|a, b| b.2.partial_cmp(&a.2).unwrap());
         problematic_transitions.truncate(10);
 
-        WeaknessAnalysis {
-            slowest_digraphs,
-            error_clusters,
+        // Per-hand latency and share, from the finger map's L-/R- prefix
+        let (mut left, mut right) = ((0u32, 0u128), (0u32, 0u128));
+        for rhythm in &self.typing_rhythm {
+            if is_outlier(rhythm.latency) {
+                continue;
+            }
+            let finger = finger_map.get(&rhythm.char_typed.to_ascii_lowercase());
+            let hand = match finger.map(String::as_str) {
+                Some(f) if f.starts_with("L-") => &mut left,
+                Some(f) if f.starts_with("R-") => &mut right,
+                _ => continue,
+            };
+            hand.0 += 1;
+            hand.1 += rhythm.latency.as_micros();
+        }
+        let hand_balance = (left.0 > 0 && right.0 > 0).then(|| {
+            let total = (left.0 + right.0) as f64;
+            HandBalance {
+                left_avg_latency_ms: left.1 as f64 / left.0 as f64 / 1000.0,
+                right_avg_latency_ms: right.1 as f64 / right.0 as f64 / 1000.0,
+                left_share: left.0 as f64 / total,
+                right_share: right.0 as f64 / total,
+            }
+        });
+
+        // Finger travel: each finger moves from wherever it last pressed a key
+        // (starting on the home row) to the next key it is assigned
+        let coordinates = key_coordinates(self.config.layout);
+        let mut finger_positions: HashMap<&str, (f64, f64)> = HashMap::new();
+        let mut finger_travel = FingerTravel::default();
+        for rhythm in &self.typing_rhythm {
+            let key = rhythm
+                .char_typed
+                .to_lowercase()
+                .next()
+                .unwrap_or(rhythm.char_typed);
+            let (Some(finger), Some(&target)) = (finger_map.get(&key), coordinates.get(&key))
+            else {
+                continue;
+            };
+            let Some(home) = finger_home(finger) else {
+                continue;
+            };
+            let from = finger_positions.entry(finger.as_str()).or_insert(home);
+            let distance = ((target.0 - from.0).powi(2) + (target.1 - from.1).powi(2)).sqrt();
+            *from = target;
+            if finger.starts_with("L-") {
+                finger_travel.left += distance;
+            } else if finger.starts_with("R-") {
+                finger_travel.right += distance;
+            }
+        }
+
+        WeaknessAnalysis {
+            slowest_digraphs,
+            error_clusters,
             finger_errors,
             rhythm_breaks,
             problematic_transitions,
+            hand_balance,
+            finger_travel,
         }
     }
 
@@ -811,1153 +3275,6928 @@ impl TypingSession {
         // Thumbs
         map.insert(' ', "Thumb".to_string());
 
+        // A custom map only needs the keys it moves
+        if let Some(custom) = &self.config.finger_map {
+            map.extend(custom.clone());
+        }
+
         map
     }
 }
 
-#[derive(Debug, Clone)]
-enum TextSource {
-    File(String, String), // (filename, content)
-    Inception(String),    // source code content
+const FINGER_LABELS: [&str; 9] = [
+    "L-Pinky", "L-Ring", "L-Middle", "L-Index", "R-Index", "R-Middle", "R-Ring", "R-Pinky", "Thumb",
+];
+
+/// Load a custom key to finger mapping from a JSON object of single-character
+/// keys to finger labels
+fn load_finger_map(path: &Path) -> io::Result<HashMap<char, String>> {
+    let content = fs::read_to_string(path)?;
+    let map: HashMap<char, String> = serde_json::from_str(&content).map_err(|e| {
+        io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid finger map {}: {}", path.display(), e),
+        )
+    })?;
+
+    if let Some((key, finger)) = map
+        .iter()
+        .find(|(_, finger)| !FINGER_LABELS.contains(&finger.as_str()))
+    {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!(
+                "unknown finger '{}' for key '{}' (expected one of: {})",
+                finger,
+                key,
+                FINGER_LABELS.join(", ")
+            ),
+        ));
+    }
+
+    Ok(map)
 }
 
-struct App {
-    session: Option<TypingSession>,
-    text_source: TextSource,
-    should_quit: bool,
-    state: AppState,
-    report_view: ReportView,
+/// Line comment, block comment opener or '#' comment, as seen by snippet scoring
+fn is_comment_line(line: &str) -> bool {
+    let trimmed = line.trim();
+    trimmed.starts_with("//") || trimmed.starts_with("/*") || trimmed.starts_with('#')
 }
 
-impl ChunkSize {
-    fn get_char_range(&self) -> (usize, usize) {
-        match self {
-            ChunkSize::Small => (800, 1600),
-            ChunkSize::Medium => (1600, 3200),
-            ChunkSize::Large => (3200, 4800),
-        }
+/// Whitespace-only text completes trivially and yields meaningless stats
+fn has_visible_chars(text: &str) -> bool {
+    text.chars().any(|c| !c.is_whitespace())
+}
+
+fn is_code_file(filename: &str) -> bool {
+    filename.ends_with(".rs")
+        || filename.ends_with(".py")
+        || filename.ends_with(".js")
+        || filename.ends_with(".ts")
+        || filename.ends_with(".cpp")
+        || filename.ends_with(".c")
+        || filename.ends_with(".java")
+        || filename.ends_with(".go")
+}
+
+/// Replace anything but alphanumerics, '-' and '_' so the label is filename-safe
+fn sanitize_for_filename(label: &str) -> String {
+    label
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '-' || c == '_' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect()
+}
+
+/// Standard error of the mean (sample standard deviation / sqrt(n))
+fn standard_error(values: &[f64]) -> Option<f64> {
+    if values.len() < 2 {
+        return None;
     }
 
-    fn get_line_range(&self) -> (usize, usize) {
-        match self {
-            ChunkSize::Small => (20, 40),
-            ChunkSize::Medium => (40, 80),
-            ChunkSize::Large => (80, 120),
-        }
+    let n = values.len() as f64;
+    let mean = values.iter().sum::<f64>() / n;
+    let variance = values.iter().map(|v| (v - mean).powi(2)).sum::<f64>() / (n - 1.0);
+    Some(variance.sqrt() / n.sqrt())
+}
+
+/// Five-character words per minute
+fn words_per_minute(chars: usize, duration: Duration) -> f64 {
+    let minutes = duration.as_secs_f64() / 60.0;
+    if minutes == 0.0 {
+        0.0
+    } else {
+        (chars as f64 / 5.0) / minutes
     }
 }
 
-#[derive(Debug)]
-struct TextParagraph {
-    content: String,
-    char_count: usize,
-    score: f32,
+/// Drop the oldest entries so that at most `max` remain
+fn keep_last<T>(samples: &mut Vec<T>, max: usize) {
+    if samples.len() > max {
+        samples.drain(..samples.len() - max);
+    }
 }
 
-impl TextSource {
-    fn load_from_file(path: &Path, size: ChunkSize) -> io::Result<Self> {
-        let content = fs::read_to_string(path)?;
-        let filename = path
-            .file_name()
-            .and_then(|n| n.to_str())
-            .unwrap_or("unknown")
-            .to_string();
+#[derive(Debug, Clone)]
+enum TextSource {
+    File(String, String, Option<f32>, Option<SourceMeta>), // (filename, content, snippet score, origin)
+    Inception(String, Option<f32>),                        // (source code content, snippet score)
+    Quote(String, Vec<String>), // (quotations, authors in order of appearance)
+}
 
-        let processed_content = Self::extract_file_snippet(&content, &filename, size);
-        Ok(TextSource::File(filename, processed_content))
-    }
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum EntryKind {
+    Parent,
+    Directory,
+    TextFile,
+    Unsupported, // Binary or unreadable, shown but not selectable
+}
 
-    fn extract_file_snippet(content: &str, filename: &str, size: ChunkSize) -> String {
-        let (target_min_chars, target_max_chars) = size.get_char_range();
+#[derive(Debug, Clone)]
+struct BrowserEntry {
+    name: String,
+    path: PathBuf,
+    kind: EntryKind,
+}
 
-        // Find all meaningful paragraphs/sections
-        let mut paragraphs = Self::find_paragraphs(content, filename);
+struct FileBrowser {
+    dir: PathBuf,
+    entries: Vec<BrowserEntry>,
+    selected: usize,
+    message: Option<String>,
+}
 
-        // Score paragraphs strategically (higher score = better for typing practice)
-        for paragraph in &mut paragraphs {
-            paragraph.score = Self::calculate_paragraph_score(&paragraph.content, filename);
+impl FileBrowser {
+    fn open(dir: PathBuf) -> io::Result<Self> {
+        let entries = Self::list_entries(&dir)?;
+        Ok(Self {
+            dir,
+            entries,
+            selected: 0,
+            message: None,
+        })
+    }
+
+    /// Parent link first, then directories, then files, each sorted by name.
+    /// Hidden entries are left out.
+    fn list_entries(dir: &Path) -> io::Result<Vec<BrowserEntry>> {
+        let mut dirs = Vec::new();
+        let mut files = Vec::new();
+
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if name.starts_with('.') {
+                continue;
+            }
+
+            let path = entry.path();
+            if path.is_dir() {
+                dirs.push(BrowserEntry {
+                    name,
+                    path,
+                    kind: EntryKind::Directory,
+                });
+            } else {
+                let kind = if is_text_file(&path) {
+                    EntryKind::TextFile
+                } else {
+                    EntryKind::Unsupported
+                };
+                files.push(BrowserEntry { name, path, kind });
+            }
         }
 
-        // Sort by score (best first) then randomize within score tiers
-        paragraphs.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        dirs.sort_by(|a, b| a.name.cmp(&b.name));
+        files.sort_by(|a, b| a.name.cmp(&b.name));
 
-        // Filter paragraphs that fit within the size constraints
-        let suitable_paragraphs: Vec<_> = paragraphs
-            .iter()
-            .filter(|p| p.char_count >= target_min_chars && p.char_count <= target_max_chars)
-            .collect();
+        let parent = dir.parent().map(|parent| BrowserEntry {
+            name: "..".to_string(),
+            path: parent.to_path_buf(),
+            kind: EntryKind::Parent,
+        });
 
-        // If we have suitable paragraphs, pick strategically with randomness
-        if !suitable_paragraphs.is_empty() {
-            let selected = Self::select_strategic_paragraph(&suitable_paragraphs);
+        Ok(parent.into_iter().chain(dirs).chain(files).collect())
+    }
 
-            return selected.content.trim().to_string();
+    fn move_selection(&mut self, delta: isize) {
+        if self.entries.is_empty() {
+            return;
         }
+        let last = self.entries.len() - 1;
+        self.selected = self.selected.saturating_add_signed(delta).min(last);
+    }
 
-        // If no perfect fit, find the best-scoring paragraph that's still meaningful
-        let acceptable_paragraphs: Vec<_> = paragraphs
-            .iter()
-            .filter(|p| p.char_count >= target_min_chars / 2) // At least half the target
-            .collect();
+    fn change_dir(&mut self, dir: PathBuf) {
+        match Self::list_entries(&dir) {
+            Ok(entries) => {
+                self.dir = dir;
+                self.entries = entries;
+                self.selected = 0;
+                self.message = None;
+            }
+            Err(e) => self.message = Some(format!("Cannot open {}: {}", dir.display(), e)),
+        }
+    }
 
-        if !acceptable_paragraphs.is_empty() {
-            let selected = Self::select_strategic_paragraph(&acceptable_paragraphs);
+    fn go_up(&mut self) {
+        if let Some(parent) = self.dir.parent() {
+            self.change_dir(parent.to_path_buf());
+        }
+    }
 
-            return selected.content.trim().to_string();
+    /// Descend into the selected directory, or return the selected text file
+    fn activate(&mut self) -> Option<PathBuf> {
+        let entry = self.entries.get(self.selected)?.clone();
+        match entry.kind {
+            EntryKind::Parent | EntryKind::Directory => {
+                self.change_dir(entry.path);
+                None
+            }
+            EntryKind::TextFile => Some(entry.path),
+            EntryKind::Unsupported => {
+                self.message = Some(format!("{} is not a text file", entry.name));
+                None
+            }
         }
+    }
+}
 
-        // Fallback: create a chunk of the target size from the middle of the file
-        let lines: Vec<&str> = content.lines().collect();
-        let (_target_lines_min, target_lines_max) = size.get_line_range();
-        let start_idx = lines.len() / 3; // Start from 1/3 into the file
-        let end_idx = (start_idx + target_lines_max).min(lines.len());
-        let snippet_lines = &lines[start_idx..end_idx];
-        let content_str = snippet_lines.join("\n");
+/// Sniff the first few KB: text files are valid UTF-8 without NUL bytes
+fn is_text_file(path: &Path) -> bool {
+    use std::io::Read;
 
-        content_str.trim().to_string()
-    }
+    let mut buffer = [0u8; 8192];
+    let Ok(read) = fs::File::open(path).and_then(|mut file| file.read(&mut buffer)) else {
+        return false;
+    };
+    let sample = &buffer[..read];
 
-    fn find_paragraphs(content: &str, filename: &str) -> Vec<TextParagraph> {
-        let lines: Vec<&str> = content.lines().collect();
-        let mut paragraphs = Vec::new();
+    if sample.contains(&0) {
+        return false;
+    }
+    match std::str::from_utf8(sample) {
+        Ok(_) => true,
+        // A multi-byte char cut off by the sample boundary is fine
+        Err(e) => e.error_len().is_none(),
+    }
+}
 
-        // For code files, find function/struct/impl blocks
-        if filename.ends_with(".rs")
-            || filename.ends_with(".py")
-            || filename.ends_with(".js")
-            || filename.ends_with(".ts")
-            || filename.ends_with(".cpp")
-            || filename.ends_with(".c")
-            || filename.ends_with(".java")
-            || filename.ends_with(".go")
-        {
-            let mut current_start = 0;
-            let mut brace_depth = 0;
-            let mut in_block = false;
+/// Defaults for command line options, read from preferences.json. Options
+/// given on the command line always win over the file.
+#[derive(Debug, Serialize, Deserialize)]
+struct Preferences {
+    size: Option<ChunkSize>,
+    layout: Option<KeyboardLayout>,
+    top_n: Option<usize>,
+    target_wpm: Option<u32>,
+    hesitation_ms: Option<u64>,
+    idle_timeout: Option<u64>,
+    live_header: Option<bool>,
+    sparkline: Option<bool>,
+    quiet: Option<bool>,
+    daily_goal: Option<DailyGoal>,
+}
 
-            for (i, line) in lines.iter().enumerate() {
-                let trimmed = line.trim();
+impl Default for Preferences {
+    /// The built-in defaults, written out on first run as an editable template
+    fn default() -> Self {
+        Self {
+            size: Some(ChunkSize::Medium),
+            layout: Some(KeyboardLayout::Us),
+            top_n: Some(8),
+            target_wpm: None,
+            hesitation_ms: None,
+            idle_timeout: Some(0),
+            live_header: Some(false),
+            sparkline: Some(false),
+            quiet: Some(false),
+            daily_goal: None,
+        }
+    }
+}
 
-                // Detect start of code blocks
-                if (trimmed.starts_with("fn ")
-                    || trimmed.starts_with("pub fn ")
-                    || trimmed.starts_with("struct ")
-                    || trimmed.starts_with("impl ")
-                    || trimmed.starts_with("enum ")
-                    || trimmed.starts_with("class ")
-                    || trimmed.starts_with("def ")
-                    || trimmed.starts_with("function "))
-                    && brace_depth == 0
-                {
-                    current_start = i;
-                    in_block = true;
-                }
+impl Preferences {
+    /// `$XDG_CONFIG_HOME/keysentry/preferences.json`, falling back to `~/.config`
+    fn default_path() -> Option<PathBuf> {
+        let config_dir = std::env::var_os("XDG_CONFIG_HOME")
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".config")))?;
+        Some(config_dir.join("keysentry").join("preferences.json"))
+    }
 
-                // Track braces
-                brace_depth += line.matches('{').count() as i32;
-                brace_depth -= line.matches('}').count() as i32;
+    /// Startup preferences: a broken file is reported and ignored rather
+    /// than overwritten
+    fn load_or_default() -> Self {
+        Self::load().unwrap_or_else(|e| {
+            eprintln!("Warning: ignoring {}", e);
+            Self::default()
+        })
+    }
 
-                // End of block
-                if in_block && brace_depth == 0 && line.contains('}') {
-                    let block_lines = &lines[current_start..=i];
-                    let content = block_lines.join("\n");
-                    if content.len() > 200 {
-                        // Only meaningful blocks
-                        paragraphs.push(TextParagraph {
-                            content,
-                            char_count: block_lines.join("\n").len(),
-                            score: 0.0, // Will be calculated later
-                        });
-                    }
-                    in_block = false;
-                }
-            }
-        } else {
-            // For text files, split by double newlines (paragraphs)
-            let content_str = content.to_string();
+    fn load() -> io::Result<Self> {
+        match Self::default_path() {
+            Some(path) => Self::load_from(&path),
+            None => Ok(Self::default()),
+        }
+    }
 
-            for paragraph_text in content_str.split("\n\n") {
-                if paragraph_text.trim().len() > 100 {
-                    // Only meaningful paragraphs
-                    paragraphs.push(TextParagraph {
-                        content: paragraph_text.to_string(),
-                        char_count: paragraph_text.len(),
-                        score: 0.0, // Will be calculated later
-                    });
+    /// Read the preferences file, creating it with the defaults when missing.
+    /// Errors name the file so a broken one can be found and fixed
+    fn load_from(path: &Path) -> io::Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(content) => serde_json::from_str(&content).map_err(|e| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), e),
+                )
+            }),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let preferences = Self::default();
+                // Only a convenience: failing to write the template is fine
+                if let Some(dir) = path.parent()
+                    && fs::create_dir_all(dir).is_ok()
+                    && let Ok(json) = serde_json::to_string_pretty(&preferences)
+                {
+                    let _ = fs::write(path, json);
                 }
+                Ok(preferences)
             }
+            Err(e) => Err(io::Error::new(
+                e.kind(),
+                format!("{}: {}", path.display(), e),
+            )),
         }
-
-        paragraphs
     }
 
-    fn calculate_paragraph_score(content: &str, filename: &str) -> f32 {
-        let mut score = 0.0f32;
-
-        // Base score from content length (sweet spot around 100-200 chars per complexity)
-        let len = content.len() as f32;
-        score += if len > 50.0 && len < 500.0 { 10.0 } else { 5.0 };
+    fn save(&self) -> io::Result<PathBuf> {
+        let path = Self::default_path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
 
-        // Bonus for diverse character usage (good for typing practice)
-        let unique_chars = content
-            .chars()
-            .collect::<std::collections::HashSet<_>>()
-            .len() as f32;
-        score += unique_chars * 0.5;
+    /// Fill in every option that wasn't given on the command line
+    fn apply(&self, cli: &mut Cli, matches: &clap::ArgMatches) {
+        let from_cli = |id: &str| matches.value_source(id) == Some(ValueSource::CommandLine);
 
-        // Code-specific scoring
-        if filename.ends_with(".rs")
-            || filename.ends_with(".py")
-            || filename.ends_with(".js")
-            || filename.ends_with(".ts")
-            || filename.ends_with(".cpp")
-            || filename.ends_with(".java")
+        if let Some(size) = self.size
+            && !from_cli("size")
         {
-            // Bonus for function implementations (good typing practice)
-            if content.contains("fn ") || content.contains("function ") || content.contains("def ")
-            {
-                score += 15.0;
-            }
+            cli.size = size;
+        }
+        if let Some(layout) = self.layout
+            && !from_cli("layout")
+        {
+            cli.layout = layout;
+        }
+        if let Some(top_n) = self.top_n
+            && !from_cli("top_n")
+        {
+            cli.top_n = top_n.max(1);
+        }
+        if !from_cli("target_wpm") {
+            cli.target_wpm = cli.target_wpm.or(self.target_wpm.filter(|&wpm| wpm > 0));
+        }
+        if !from_cli("hesitation_ms") {
+            cli.hesitation_ms = cli
+                .hesitation_ms
+                .or(self.hesitation_ms.map(|ms| ms.max(MIN_HESITATION_MS)));
+        }
+        if let Some(idle_timeout) = self.idle_timeout
+            && !from_cli("idle_timeout")
+        {
+            cli.idle_timeout = idle_timeout;
+        }
+        if let Some(live_header) = self.live_header
+            && !from_cli("live_header")
+        {
+            cli.live_header = live_header;
+        }
+        if let Some(sparkline) = self.sparkline
+            && !from_cli("sparkline")
+        {
+            cli.sparkline = sparkline;
+        }
+        if let Some(quiet) = self.quiet
+            && !from_cli("quiet")
+        {
+            cli.quiet = quiet;
+        }
+    }
+}
 
-            // Bonus for control structures (interesting patterns)
-            if content.contains("if ")
-                || content.contains("for ")
-                || content.contains("while ")
-                || content.contains("match ")
-                || content.contains("switch ")
-            {
-                score += 10.0;
-            }
+/// Thresholds derived from a --calibrate run
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Calibration {
+    hesitation_ms: u64,
+    target_wpm: u32,
+}
 
-            // Bonus for data structures
-            if content.contains("struct ")
-                || content.contains("class ")
-                || content.contains("enum ")
-            {
-                score += 12.0;
-            }
+impl Calibration {
+    /// A hesitation is a keystroke 2.5 times slower than the median one, kept
+    /// within 250-1500ms and rounded up to 10ms. The pace marker runs 10%
+    /// faster than the measured WPM, to have something to chase.
+    fn from_report(report: &SessionReport) -> Option<Self> {
+        let mut latencies: Vec<u64> = report
+            .typing_rhythm
+            .iter()
+            .skip(1) // The first key has no meaningful latency
+            .map(|r| r.latency.as_millis() as u64)
+            .collect();
+        if latencies.len() < CALIBRATION_MIN_KEYS {
+            return None;
+        }
+        latencies.sort_unstable();
+        let median = latencies[latencies.len() / 2];
 
-            // Bonus for error handling (challenging typing)
-            if content.contains("Result")
-                || content.contains("Option")
-                || content.contains("Error")
-                || content.contains("try")
-                || content.contains("catch")
-                || content.contains("except")
-            {
-                score += 8.0;
-            }
+        Some(Self {
+            hesitation_ms: (median * 5 / 2).clamp(250, 1500).div_ceil(10) * 10,
+            target_wpm: ((report.wpm * 1.1).round() as u32).max(10),
+        })
+    }
+}
 
-            // Bonus for generics and advanced syntax (very good practice)
-            if content.contains('<') && content.contains('>') || content.contains("impl ") {
-                score += 12.0;
-            }
+/// Summary of one finished session, stored one JSON object per line
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+    pub source: String,
+    pub wpm: f64,
+    pub accuracy: f64,
+    pub duration: Duration,
+}
 
-            // Penalty for mostly comments or too simple
-            let comment_ratio = content
-                .lines()
-                .filter(|line| {
-                    line.trim().starts_with("//")
-                        || line.trim().starts_with("/*")
-                        || line.trim().starts_with("#")
-                })
-                .count() as f32
-                / content.lines().count().max(1) as f32;
-            score -= comment_ratio * 10.0;
+/// Practice target per day; a day counts towards the streak when it meets
+/// every part that is set
+#[derive(Debug, Clone, Copy, Default, Serialize, Deserialize)]
+pub struct DailyGoal {
+    pub minutes: Option<u64>,
+    pub sessions: Option<u32>,
+}
 
-            // Penalty for too many imports/includes (boring)
-            if content.contains("import ")
-                || content.contains("use ")
-                || content.contains("#include")
-            {
-                let import_lines = content
-                    .lines()
-                    .filter(|line| {
-                        line.contains("import ")
-                            || line.contains("use ")
-                            || line.contains("#include")
-                    })
-                    .count();
-                if import_lines > 3 {
-                    score -= 5.0;
-                }
+impl DailyGoal {
+    fn is_met(&self, practiced: Duration, sessions: u32) -> bool {
+        (self.minutes.is_some() || self.sessions.is_some())
+            && self
+                .minutes
+                .is_none_or(|minutes| practiced >= Duration::from_secs(minutes * 60))
+            && self.sessions.is_none_or(|goal| sessions >= goal)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PersonalBest {
+    pub wpm: f64,
+    pub accuracy: f64,
+}
+
+/// `$XDG_DATA_HOME/keysentry`, falling back to `~/.local/share/keysentry`
+fn data_dir() -> Option<PathBuf> {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".local/share")))?;
+    Some(data_dir.join("keysentry"))
+}
+
+/// Personal list of words to drill, one per line, fed by 'k' on the report
+fn weak_words_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("weak_words.txt"))
+}
+
+/// Saved weak words; a missing file has none
+fn load_weak_words(path: &Path) -> io::Result<Vec<String>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content
+            .lines()
+            .map(str::trim)
+            .filter(|word| !word.is_empty())
+            .map(String::from)
+            .collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e),
+    }
+}
+
+/// Hashes of passages flagged as bad with 'b' on the report, one per line in hex
+fn excluded_snippets_path() -> Option<PathBuf> {
+    Some(data_dir()?.join("excluded_snippets.txt"))
+}
+
+/// Blocks shorter than this (a lone brace, a blank-ish line) are never
+/// excluded, so flagging one passage can't knock out unrelated ones
+const EXCLUDE_MIN_BLOCK_CHARS: usize = 20;
+
+/// FNV-1a, stable across builds unlike the std hasher
+fn content_hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Hashes of the blank-line separated blocks of `text`, each trimmed, so a
+/// passage combined from several paragraphs matches each of them
+fn block_hashes(text: &str) -> Vec<u64> {
+    let mut hashes = Vec::new();
+    let mut block = String::new();
+    for line in text.lines().chain(std::iter::once("")) {
+        if line.trim().is_empty() {
+            let trimmed = block.trim();
+            if trimmed.chars().count() >= EXCLUDE_MIN_BLOCK_CHARS {
+                hashes.push(content_hash(trimmed));
             }
+            block.clear();
         } else {
-            // Text file scoring
-            let word_count = content.split_whitespace().count() as f32;
+            block.push_str(line);
+            block.push('\n');
+        }
+    }
+    hashes
+}
 
-            // Bonus for good paragraph length
-            if word_count > 20.0 && word_count < 150.0 {
-                score += 10.0;
-            }
+/// Saved exclusions; a missing file has none and unreadable lines are skipped
+fn load_excluded_snippets(path: &Path) -> io::Result<HashSet<u64>> {
+    match fs::read_to_string(path) {
+        Ok(content) => Ok(content
+            .lines()
+            .filter_map(|line| u64::from_str_radix(line.trim(), 16).ok())
+            .collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(HashSet::new()),
+        Err(e) => Err(e),
+    }
+}
 
-            // Bonus for punctuation variety (good typing practice)
-            let punct_chars = content
-                .chars()
-                .filter(|c| ".,;:!?\"'()-[]{}".contains(*c))
-                .count() as f32;
-            score += punct_chars * 0.3;
+/// Append the block hashes of `text` not already in the list at `path`,
+/// returning the new ones
+fn persist_excluded_snippet(text: &str, path: &Path) -> io::Result<Vec<u64>> {
+    use std::io::Write;
 
-            // Bonus for sentences (complete thoughts)
-            let sentence_count = content.matches(|c| ".!?".contains(c)).count() as f32;
-            score += sentence_count * 2.0;
+    let mut seen = load_excluded_snippets(path)?;
+    let mut new_hashes = block_hashes(text);
+    new_hashes.retain(|hash| seen.insert(*hash));
+    if new_hashes.is_empty() {
+        return Ok(new_hashes);
+    }
+
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    for hash in &new_hashes {
+        writeln!(file, "{:016x}", hash)?;
+    }
+    Ok(new_hashes)
+}
+
+/// Words of the target text that overlap an error cluster, in order and
+/// without duplicates. Surrounding punctuation is trimmed off.
+fn missed_words(report: &SessionReport) -> Vec<String> {
+    let clusters = &report.weakness_analysis.error_clusters;
+    let mut words: Vec<String> = Vec::new();
+    let mut position = 0;
+    for token in report.target_text.split_inclusive(char::is_whitespace) {
+        let (start, end) = (position, position + token.chars().count());
+        position = end;
+
+        let word = token.trim().trim_matches(|c: char| !c.is_alphanumeric());
+        let in_cluster = clusters
+            .iter()
+            .any(|&(cluster_start, cluster_end)| start <= cluster_end && cluster_start < end);
+        if in_cluster && word.chars().count() > 1 && !words.iter().any(|w| w == word) {
+            words.push(word.to_string());
         }
+    }
+    words
+}
 
-        // Avoid very short or very long content
-        if len < 100.0 {
-            score -= 5.0;
+/// How many of a report's weakest keys a report drill targets
+const DRILL_WEAK_KEYS: usize = 5;
+/// Distinct words in a report drill, before repetition
+const DRILL_MAX_WORDS: usize = 30;
+
+/// Visible keys of a report ranked by errors, then by average latency
+fn report_weak_keys(report: &SessionReport, count: usize) -> Vec<char> {
+    let mut keys: Vec<&KeyStat> = report
+        .key_stats
+        .values()
+        .filter(|stat| stat.count > 0 && !stat.key.is_whitespace())
+        .collect();
+    keys.sort_by_key(|stat| std::cmp::Reverse((stat.error_count, stat.total_latency / stat.count)));
+    keys.iter().take(count).map(|stat| stat.key).collect()
+}
+
+/// Words from a report's text to drill: the ones errors clustered on, then
+/// others containing its weakest keys
+fn report_drill_words(report: &SessionReport) -> Vec<String> {
+    let weak_keys = report_weak_keys(report, DRILL_WEAK_KEYS);
+    let mut words = missed_words(report);
+    for token in report.target_text.split_whitespace() {
+        if words.len() >= DRILL_MAX_WORDS {
+            break;
         }
-        if len > 1000.0 {
-            score -= 3.0;
+        if token.chars().any(|c| weak_keys.contains(&c)) && !words.iter().any(|w| w == token) {
+            words.push(token.to_string());
         }
-
-        // Ensure non-negative score
-        score.max(0.0)
     }
+    words.truncate(DRILL_MAX_WORDS);
+    words
+}
 
-    fn select_strategic_paragraph<'a>(paragraphs: &'a [&'a TextParagraph]) -> &'a TextParagraph {
-        use rand::seq::SliceRandom;
-        use rand::thread_rng;
+/// Shuffled rounds of `words`, one round per line, at least `min_chars` long
+fn drill_passage(words: &[String], min_chars: usize, seed: Option<u64>) -> String {
+    use rand::SeedableRng;
+    use rand::seq::SliceRandom;
 
-        paragraphs.choose(&mut thread_rng()).unwrap()
+    let mut rng = match seed {
+        Some(seed) => rand::rngs::StdRng::seed_from_u64(seed),
+        None => rand::rngs::StdRng::from_entropy(),
+    };
+    let mut rounds: Vec<String> = Vec::new();
+    let mut length = 0;
+    while length < min_chars {
+        let mut round: Vec<&str> = words.iter().map(String::as_str).collect();
+        round.shuffle(&mut rng);
+        let round = round.join(" ");
+        length += round.chars().count() + 1;
+        rounds.push(round);
     }
+    rounds.join("\n")
+}
 
-    fn load_inception(size: ChunkSize) -> io::Result<Self> {
-        // Include the source code directly at compile time
-        let full_content = include_str!("main.rs");
+/// Append this session's missed words to the weak word list, skipping any
+/// already on it. Returns how many were added.
+fn persist_weak_words(report: &SessionReport, path: &Path) -> io::Result<usize> {
+    use std::io::Write;
 
-        // Split into meaningful code sections and select one based on size
-        let snippet = Self::extract_code_section(full_content, size);
-        Ok(TextSource::Inception(snippet))
+    let existing = load_weak_words(path)?;
+    let new_words: Vec<String> = missed_words(report)
+        .into_iter()
+        .filter(|word| !existing.contains(word))
+        .collect();
+    if new_words.is_empty() {
+        return Ok(0);
     }
 
-    fn extract_code_section(content: &str, size: ChunkSize) -> String {
-        let (target_min_chars, target_max_chars) = size.get_char_range();
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    for word in &new_words {
+        writeln!(file, "{}", word)?;
+    }
+    Ok(new_words.len())
+}
 
-        // Use the same strategic paragraph logic for the source code
-        let mut paragraphs = Self::find_paragraphs(content, "main.rs");
+pub struct History {
+    path: PathBuf,
+    entries: Vec<HistoryEntry>,
+    read_only: bool, // Set after a failed write; entries are then only kept in memory
+}
 
-        // Score paragraphs strategically (higher score = better for typing practice)
-        for paragraph in &mut paragraphs {
-            paragraph.score = Self::calculate_paragraph_score(&paragraph.content, "main.rs");
+impl History {
+    /// Consecutive local days, up to `today`, on which `goal` was met. Today
+    /// is still in progress, so a streak that ended yesterday is kept alive.
+    fn daily_streak(&self, goal: &DailyGoal, today: chrono::NaiveDate) -> u32 {
+        let mut days: HashMap<chrono::NaiveDate, (Duration, u32)> = HashMap::new();
+        for entry in &self.entries {
+            let day = entry.timestamp.with_timezone(&chrono::Local).date_naive();
+            let practice = days.entry(day).or_default();
+            practice.0 += entry.duration;
+            practice.1 += 1;
         }
+        let met = |day: chrono::NaiveDate| {
+            days.get(&day)
+                .is_some_and(|&(practiced, sessions)| goal.is_met(practiced, sessions))
+        };
 
-        // Sort by score (best first)
-        paragraphs.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+        let mut day = if met(today) {
+            Some(today)
+        } else {
+            today.pred_opt()
+        };
+        let mut streak = 0;
+        while let Some(current) = day.filter(|&d| met(d)) {
+            streak += 1;
+            day = current.pred_opt();
+        }
+        streak
+    }
 
-        // Filter paragraphs that fit the size requirement
-        let suitable_paragraphs: Vec<_> = paragraphs
-            .iter()
-            .filter(|p| p.char_count >= target_min_chars && p.char_count <= target_max_chars)
-            .collect();
+    /// `history.jsonl` in the data directory
+    fn default_path() -> Option<PathBuf> {
+        Some(data_dir()?.join("history.jsonl"))
+    }
 
-        if !suitable_paragraphs.is_empty() {
-            let selected = Self::select_strategic_paragraph(&suitable_paragraphs);
-            return selected.content.trim().to_string();
-        }
+    /// Load the history file; a missing file is an empty history and
+    /// unreadable lines are skipped
+    fn load(path: PathBuf) -> io::Result<Self> {
+        let entries = match fs::read_to_string(&path) {
+            Ok(content) => content
+                .lines()
+                .filter_map(|line| serde_json::from_str(line).ok())
+                .collect(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(e) => return Err(e),
+        };
+        Ok(Self {
+            path,
+            entries,
+            read_only: false,
+        })
+    }
 
-        // If no perfect fit, find the best available paragraph
-        let acceptable_paragraphs: Vec<_> = paragraphs
-            .iter()
-            .filter(|p| p.char_count >= target_min_chars / 2)
-            .collect();
+    /// Record a session. The entry is always kept in memory; only the first
+    /// failed write is returned, after which the file is left alone.
+    fn append(&mut self, entry: HistoryEntry) -> io::Result<()> {
+        let written = if self.read_only {
+            Ok(())
+        } else {
+            self.write_entry(&entry)
+        };
+        self.read_only |= written.is_err();
+        self.entries.push(entry);
+        written
+    }
 
-        if !acceptable_paragraphs.is_empty() {
-            let selected = Self::select_strategic_paragraph(&acceptable_paragraphs);
-            return selected.content.trim().to_string();
+    fn write_entry(&self, entry: &HistoryEntry) -> io::Result<()> {
+        use std::io::Write;
+
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
         }
+        let mut file = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)
+    }
 
-        // Fallback: use a chunk from the beginning
-        let lines: Vec<&str> = content.lines().collect();
-        let (_, target_max_lines) = size.get_line_range();
-        let end = target_max_lines.min(lines.len());
-        let content_str = lines[0..end].join("\n");
+    /// Best WPM and best accuracy for this source, or over all sources when
+    /// the source has never been practiced
+    fn best_for(&self, source: &str) -> Option<PersonalBest> {
+        let same_source: Vec<&HistoryEntry> =
+            self.entries.iter().filter(|e| e.source == source).collect();
+        let candidates = if same_source.is_empty() {
+            self.entries.iter().collect()
+        } else {
+            same_source
+        };
 
-        content_str.trim().to_string()
+        candidates.iter().fold(None, |best, entry| {
+            Some(match best {
+                None => PersonalBest {
+                    wpm: entry.wpm,
+                    accuracy: entry.accuracy,
+                },
+                Some(best) => PersonalBest {
+                    wpm: best.wpm.max(entry.wpm),
+                    accuracy: best.accuracy.max(entry.accuracy),
+                },
+            })
+        })
     }
+}
 
-    fn get_content(&self) -> Option<(String, String)> {
+/// App-level options that don't affect the typing session itself
+#[derive(Debug, Clone, Default)]
+struct UiOptions {
+    live_header: bool,
+    top_n: usize,
+    save_on_quit: bool,
+    sparkline: bool,
+    idle_timeout: Option<Duration>,
+    warmup: bool,
+    hold_on_complete: bool,
+    quiet: bool, // Hide the report's guide sidebars and action plan
+    daily_goal: Option<DailyGoal>,
+    ruler: bool,                // Column numbers above the text
+    pomodoro: Option<Duration>, // Focus interval length
+    calibrate: bool,            // Save thresholds from the finished session to preferences
+    monochrome: bool,           // Draw with text attributes instead of colours
+}
+
+/// Whether the terminal should get no colours: NO_COLOR is set to anything
+/// non-empty (https://no-color.org) or TERM says the terminal can't show them
+fn colors_disabled() -> bool {
+    colors_disabled_by(
+        std::env::var_os("NO_COLOR").as_deref(),
+        std::env::var_os("TERM").as_deref(),
+    )
+}
+
+/// colors_disabled for the given NO_COLOR and TERM values
+fn colors_disabled_by(no_color: Option<&std::ffi::OsStr>, term: Option<&std::ffi::OsStr>) -> bool {
+    no_color.is_some_and(|value| !value.is_empty())
+        || term.is_some_and(|term| term.is_empty() || term == "dumb")
+}
+
+/// Swap every colour in a drawn frame for a text attribute, so the views keep
+/// their meaning without colour: coloured backgrounds (errors, carets) become
+/// reversed video, red text bold and underlined, highlights bold and
+/// greyed-out text dim. Only backgrounds are reversed, so bar and sparkline
+/// glyphs drawn in red keep their shape.
+fn decolorize(buffer: &mut Buffer) {
+    for cell in buffer.content.iter_mut() {
+        let decoration = match (cell.fg, cell.bg) {
+            (_, Color::Reset) => match cell.fg {
+                Color::Red | Color::LightRed => Modifier::BOLD | Modifier::UNDERLINED,
+                Color::Yellow | Color::Cyan | Color::Magenta | Color::LightYellow => Modifier::BOLD,
+                Color::DarkGray | Color::Gray => Modifier::DIM,
+                _ => Modifier::empty(),
+            },
+            (_, Color::DarkGray) => Modifier::UNDERLINED,
+            _ => Modifier::REVERSED,
+        };
+        cell.modifier |= decoration;
+        cell.fg = Color::Reset;
+        cell.bg = Color::Reset;
+    }
+}
+
+/// Where the report is copied to. The app only ever goes through this, so a
+/// fake clipboard can capture what would have been copied.
+trait Clipboard {
+    fn set_text(&mut self, text: String) -> Result<(), arboard::Error>;
+}
+
+impl Clipboard for arboard::Clipboard {
+    fn set_text(&mut self, text: String) -> Result<(), arboard::Error> {
+        arboard::Clipboard::set_text(self, text)
+    }
+}
+
+/// Length of the rest between --pomodoro focus intervals
+const POMODORO_BREAK: Duration = Duration::from_secs(5 * 60);
+
+/// Focus interval bookkeeping for --pomodoro
+#[derive(Default)]
+struct Pomodoro {
+    focus_start: Option<Instant>, // When the first session of the current interval began
+    break_end: Option<Instant>,
+    back_to_report: bool, // The break interrupted the report rather than the browser
+    focused: Duration,    // Completed focus intervals
+    intervals: u32,
+}
+
+struct App {
+    session: Option<TypingSession>,
+    session_config: SessionConfig,
+    ui_options: UiOptions,
+    text_source: Option<TextSource>,
+    snippet_options: SnippetOptions,
+    browser: Option<FileBrowser>,
+    ghost: Option<Ghost>,
+    warmup_pending: bool,
+    warming_up: bool,
+    drilling: bool, // Re-practicing an error cluster, kept out of history
+    clipboard: Option<Box<dyn Clipboard>>, // Kept alive: on X11 the owner serves the contents
+    report_message: Option<String>, // Feedback shown under the report help
+    should_quit: bool,
+    state: AppState,
+    report_view: ReportView,
+    review_index: Option<usize>,
+    error_inspector: bool,   // Report shows each error as expected-vs-typed
+    inspector_scroll: usize, // First error shown in the inspector
+    history: Option<History>,
+    streak: u32, // Days in a row the daily goal was met
+    personal_best: Option<PersonalBest>,
+    pomodoro: Pomodoro,
+    source_metas: HashMap<PathBuf, SourceMeta>, // Origin of each file picked in the browser
+    export_dir: PathBuf, // Where reports are exported; empty for the working directory
+}
+
+impl ChunkSize {
+    fn get_char_range(&self) -> (usize, usize) {
         match self {
-            TextSource::File(name, content) => Some((name.clone(), content.clone())),
-            TextSource::Inception(content) => {
-                Some(("main.rs (INCEPTION MODE)".to_string(), content.clone()))
-            }
+            ChunkSize::Small => (800, 1600),
+            ChunkSize::Medium => (1600, 3200),
+            ChunkSize::Large => (3200, 4800),
+        }
+    }
+
+    fn get_line_range(&self) -> (usize, usize) {
+        match self {
+            ChunkSize::Small => (20, 40),
+            ChunkSize::Medium => (40, 80),
+            ChunkSize::Large => (80, 120),
         }
     }
 }
 
-impl App {
-    fn new(text_source: TextSource) -> io::Result<Self> {
-        let mut app = Self {
-            session: None,
-            text_source,
-            should_quit: false,
-            state: AppState::Typing,
-            report_view: ReportView::Charts,
-        };
+/// Options controlling how a practice snippet is picked from the source text
+#[derive(Debug, Clone)]
+struct SnippetOptions {
+    size: ChunkSize,
+    chars: Option<CharRange>, // Overrides the size preset
+    lines: Option<LineRange>,
+    keep_headers: bool,
+    transform: Option<TextTransform>,
+    seed: Option<u64>,
+    preserve_whitespace: bool,
+    control_chars: ControlChars,
+    ascii_fold: bool,
+    lowercase: bool,
+    repeat: usize,
+    excluded: HashSet<u64>, // Block hashes of passages flagged as bad
+}
 
-        // Immediately start typing session
-        app.start_typing_session();
+impl SnippetOptions {
+    /// Target passage length in characters: --chars if given, else the --size preset
+    fn char_range(&self) -> (usize, usize) {
+        match self.chars {
+            Some(range) => (range.min, range.max),
+            None => self.size.get_char_range(),
+        }
+    }
 
-        Ok(app)
+    /// Target passage length in lines, for picking raw line chunks
+    fn line_range(&self) -> (usize, usize) {
+        match self.chars {
+            Some(range) => (
+                (range.min / CHARS_PER_LINE).max(1),
+                (range.max / CHARS_PER_LINE).max(1),
+            ),
+            None => self.size.get_line_range(),
+        }
     }
 
-    fn start_typing_session(&mut self) {
-        if let Some((_, content)) = self.text_source.get_content() {
-            self.session = Some(TypingSession::new(content));
-            self.state = AppState::Typing;
+    /// Whether any block of `content` was flagged as a bad passage
+    fn is_excluded(&self, content: &str) -> bool {
+        !self.excluded.is_empty()
+            && block_hashes(content)
+                .iter()
+                .any(|hash| self.excluded.contains(hash))
+    }
+
+    /// Trim the chosen snippet unless whitespace must be kept verbatim
+    fn finish_snippet(&self, snippet: &str) -> String {
+        if self.preserve_whitespace {
+            snippet.to_string()
+        } else {
+            snippet.trim().to_string()
         }
     }
 
-    fn handle_event(&mut self, event: Event) -> io::Result<()> {
-        if let Event::Key(key) = event {
-            match self.state {
-                AppState::Typing => {
-                    if let Some(session) = &mut self.session {
-                        match key.code {
-                            KeyCode::Char('q')
-                                if key.modifiers.contains(event::KeyModifiers::CONTROL) =>
-                            {
-                                self.should_quit = true;
-                            }
-                            KeyCode::Char(c) => {
-                                session.handle_key(c);
-                                if session.is_complete() {
-                                    self.state = AppState::ShowingReport;
-                                }
-                            }
-                            KeyCode::Enter => {
-                                session.handle_key('\n');
-                                if session.is_complete() {
-                                    self.state = AppState::ShowingReport;
-                                }
-                            }
-                            KeyCode::Tab => {
-                                // Send 4 individual space characters for tab
-                                for _ in 0..4 {
-                                    session.handle_key(' ');
-                                    if session.is_complete() {
-                                        break;
-                                    }
-                                }
-                                if session.is_complete() {
-                                    self.state = AppState::ShowingReport;
-                                }
-                            }
-                            KeyCode::Backspace => {
-                                session.handle_key('\x08');
-                            }
-                            _ => {}
-                        }
-                    }
-                }
-                AppState::ShowingReport => match key.code {
-                    KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
-                        self.should_quit = true;
-                    }
-                    KeyCode::Char('e') => {
-                        self.export_report()?;
-                    }
-                    KeyCode::Char('r') => {
-                        self.start_typing_session();
-                    }
-                    KeyCode::Left | KeyCode::Char('h') => {
-                        self.report_view = match self.report_view {
-                            ReportView::Charts => ReportView::Analysis,
-                            ReportView::Analysis => ReportView::Charts,
-                        };
-                    }
-                    KeyCode::Right | KeyCode::Char('l') => {
-                        self.report_view = match self.report_view {
-                            ReportView::Charts => ReportView::Analysis,
-                            ReportView::Analysis => ReportView::Charts,
-                        };
-                    }
-                    _ => {}
-                },
+    /// Post-process the selected snippet before it becomes the typing target
+    fn apply_transforms(&self, text: String) -> String {
+        let text = match self.transform {
+            Some(TextTransform::ShuffleWords) => {
+                use rand::SeedableRng;
+                let mut rng = match self.seed {
+                    Some(seed) => rand::rngs::StdRng::seed_from_u64(seed),
+                    None => rand::rngs::StdRng::from_entropy(),
+                };
+                shuffle_words(&text, &mut rng)
             }
-        }
-        Ok(())
-    }
+            Some(TextTransform::ReverseLines) => reverse_lines(&text),
+            None => text,
+        };
+        let text = if self.lowercase {
+            text.to_lowercase()
+        } else {
+            text
+        };
 
-    fn export_report(&self) -> io::Result<()> {
-        if let Some(session) = &self.session {
-            let report = session.generate_report();
-            let json = serde_json::to_string_pretty(&report)?;
-            let filename = format!(
-                "typing_report_{}.json",
-                chrono::Utc::now().format("%Y%m%d_%H%M%S")
-            );
-            std::fs::write(&filename, json)?;
+        if self.repeat > 1 {
+            vec![text; self.repeat].join("\n")
+        } else {
+            text
         }
-        Ok(())
     }
 }
 
-fn ui_typing(f: &mut Frame, app: &App) {
-    if let Some(session) = &app.session {
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .margin(2)
-            .constraints([
-                Constraint::Min(1),
-                Constraint::Length(2),
-                Constraint::Length(3),
-            ])
-            .split(f.area());
+/// The most frequent English letter pairs; anything else counts as a rare digraph
+const COMMON_DIGRAPHS: [&str; 50] = [
+    "th", "he", "in", "er", "an", "re", "on", "at", "en", "nd", "ti", "es", "or", "te", "of", "ed",
+    "is", "it", "al", "ar", "st", "to", "nt", "ng", "se", "ha", "as", "ou", "io", "le", "ve", "co",
+    "me", "de", "hi", "ri", "ro", "ic", "ne", "ea", "ra", "ce", "li", "ch", "ll", "be", "ma", "si",
+    "om", "ur",
+];
+
+/// Rough typing difficulty from 1.0 (plain prose) to 5.0, weighing symbol
+/// density, case changes, word length and uncommon letter pairs
+fn estimate_difficulty(text: &str) -> f32 {
+    let visible: Vec<char> = text.chars().filter(|c| !c.is_whitespace()).collect();
+    if visible.is_empty() {
+        return 1.0;
+    }
 
-        // Create horizontal layout for centering text in 80% width
-        let horizontal_chunks = Layout::default()
-            .direction(Direction::Horizontal)
-            .constraints([
-                Constraint::Percentage(10), // Left padding
-                Constraint::Percentage(80), // Text area (center 80%)
-                Constraint::Percentage(10), // Right padding
-            ])
-            .split(chunks[0]);
+    let symbols = visible.iter().filter(|c| !c.is_alphanumeric()).count();
+    let symbol_density = symbols as f32 / visible.len() as f32;
 
-        // Main typing area - centered text with styling
-        let text_block = Block::default().borders(Borders::NONE);
+    let letters: Vec<char> = visible
+        .iter()
+        .copied()
+        .filter(|c| c.is_alphabetic())
+        .collect();
+    let case_changes = letters
+        .windows(2)
+        .filter(|pair| pair[0].is_uppercase() != pair[1].is_uppercase())
+        .count();
+    let case_change_rate = case_changes as f32 / letters.len().max(1) as f32;
+
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let average_word_length = visible.len() as f32 / words.len().max(1) as f32;
+
+    let mut digraphs = 0;
+    let mut rare_digraphs = 0;
+    for word in &words {
+        let lower: Vec<char> = word.to_lowercase().chars().collect();
+        for pair in lower.windows(2) {
+            if pair[0].is_alphabetic() && pair[1].is_alphabetic() {
+                digraphs += 1;
+                let digraph: String = pair.iter().collect();
+                if !COMMON_DIGRAPHS.contains(&digraph.as_str()) {
+                    rare_digraphs += 1;
+                }
+            }
+        }
+    }
+    let rare_rate = rare_digraphs as f32 / digraphs.max(1) as f32;
 
-        let styled_lines = session.generate_styled_text();
-        let paragraph = Paragraph::new(styled_lines)
-            .block(text_block)
-            .wrap(Wrap { trim: false })
-            .alignment(Alignment::Left);
+    let raw = 0.35 * (symbol_density / 0.25).min(1.0)
+        + 0.2 * (case_change_rate / 0.15).min(1.0)
+        + 0.2 * ((average_word_length - 3.0) / 5.0).clamp(0.0, 1.0)
+        + 0.25 * (rare_rate / 0.8).min(1.0);
+    1.0 + raw * 4.0
+}
 
-        f.render_widget(paragraph, horizontal_chunks[1]);
+/// Render a man page as plain 80-column text; None when man finds no entry
+fn run_man(page: &str) -> io::Result<Option<Vec<u8>>> {
+    let output = std::process::Command::new("man")
+        .arg(page)
+        .env("MANPAGER", "cat")
+        .env("MANWIDTH", "80")
+        .env_remove("MAN_KEEP_FORMATTING")
+        .output()
+        .map_err(|e| io::Error::new(e.kind(), format!("cannot run man: {}", e)))?;
+    Ok(output.status.success().then_some(output.stdout))
+}
 
-        // Status message
-        let status_color = if session.is_frozen {
-            Color::Red
-        } else if session.has_error {
-            Color::Yellow
+/// Undo terminal overstrike (bold as `c\bc`, underline as `_\bc`), like `col -b`
+fn strip_overstrike(text: &str) -> String {
+    let mut plain = String::with_capacity(text.len());
+    for c in text.chars() {
+        if c == '\x08' {
+            plain.pop();
         } else {
-            Color::Green
-        };
+            plain.push(c);
+        }
+    }
+    plain
+}
 
-        let status = Paragraph::new(session.get_status())
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(status_color));
-        f.render_widget(status, chunks[1]);
+/// The latency at `percentile` (0-100), interpolated between the two nearest
+/// ranks so that even in a short session the single slowest key lies above it
+fn percentile_cutoff(mut latencies: Vec<Duration>, percentile: f64) -> Option<Duration> {
+    if latencies.is_empty() {
+        return None;
+    }
+    latencies.sort_unstable();
+    let rank = (percentile / 100.0).clamp(0.0, 1.0) * (latencies.len() - 1) as f64;
+    let (lower, upper) = (
+        latencies[rank.floor() as usize],
+        latencies[rank.ceil() as usize],
+    );
+    Some(lower + (upper - lower).mul_f64(rank.fract()))
+}
 
-        // Simple help text at bottom
-        let help = Paragraph::new("Type the text above. Ctrl+Q: Back to selection")
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::DarkGray));
-        f.render_widget(help, chunks[2]);
+/// Remove or replace every control character except newline and tab.
+/// Carriage returns are always dropped so CRLF files don't gain placeholders.
+fn sanitize_control_chars(text: &str, mode: ControlChars) -> String {
+    text.chars()
+        .filter(|&c| c != '\r')
+        .filter_map(|c| {
+            if !c.is_control() || c == '\n' || c == '\t' {
+                Some(c)
+            } else {
+                match mode {
+                    ControlChars::Strip => None,
+                    ControlChars::Replace => Some('?'),
+                }
+            }
+        })
+        .collect()
+}
+
+/// Replace typographic punctuation that has no key on a standard keyboard
+/// with its ASCII spelling
+fn ascii_fold(text: &str) -> String {
+    let mut folded = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' | '\u{2032}' | '\u{2039}'
+            | '\u{203A}' => folded.push('\''),
+            '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{2033}' | '\u{00AB}'
+            | '\u{00BB}' => folded.push('"'),
+            '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}' | '\u{2212}' => folded.push('-'),
+            '\u{2014}' | '\u{2015}' => folded.push_str("--"),
+            '\u{2026}' => folded.push_str("..."),
+            '\u{00A0}' | '\u{2007}' | '\u{202F}' => folded.push(' '),
+            _ => folded.push(c),
+        }
     }
+    folded
 }
 
-fn ui_report(f: &mut Frame, app: &App) {
-    if let Some(session) = &app.session {
-        let report = session.generate_report();
+/// Shuffle the words of `text` while leaving every whitespace run in place,
+/// so indentation and line structure survive
+fn shuffle_words(text: &str, rng: &mut impl rand::Rng) -> String {
+    use rand::seq::SliceRandom;
+
+    let mut words: Vec<&str> = text.split_whitespace().collect();
+    words.shuffle(rng);
+
+    let mut words = words.into_iter();
+    let mut result = String::with_capacity(text.len());
+    let mut in_word = false;
+    for ch in text.chars() {
+        if ch.is_whitespace() {
+            result.push(ch);
+            in_word = false;
+        } else if !in_word {
+            result.push_str(words.next().unwrap_or_default());
+            in_word = true;
+        }
+    }
+    result
+}
 
-        let chunks = Layout::default()
-            .direction(Direction::Vertical)
-            .margin(2)
-            .constraints([
-                Constraint::Length(3),
-                Constraint::Min(1),
-                Constraint::Length(3),
-            ])
-            .split(f.area());
+fn reverse_lines(text: &str) -> String {
+    text.lines().rev().collect::<Vec<_>>().join("\n")
+}
 
-        // Title with view indicator
-        let view_name = match app.report_view {
-            ReportView::Charts => "Visual Analysis",
-            ReportView::Analysis => "Detailed Insights",
-        };
-        let title = Paragraph::new(format!("Typing Session Complete! - {}", view_name))
-            .alignment(Alignment::Center)
-            .style(
-                Style::default()
-                    .fg(Color::Green)
-                    .add_modifier(Modifier::BOLD),
-            );
-        f.render_widget(title, chunks[0]);
+#[derive(Debug, Clone)]
+struct TextParagraph {
+    content: String,
+    char_count: usize,
+    score: f32,
+}
 
-        // Render different views based on report_view
-        match app.report_view {
-            ReportView::Charts => render_consolidated_charts_view(f, chunks[1], &report),
-            ReportView::Analysis => render_consolidated_analysis_view(f, chunks[1], &report),
+impl TextSource {
+    /// Read a file, named pipe or process substitution (`--file <(cmd)`) to the end
+    fn load_from_file(path: &Path, options: &SnippetOptions) -> io::Result<Self> {
+        Self::load_from_file_with(path, options, SourceMeta::for_path(path))
+    }
+
+    /// load_from_file with the origin already known, so git isn't asked again
+    /// for a file that was loaded before
+    fn load_from_file_with(
+        path: &Path,
+        options: &SnippetOptions,
+        meta: SourceMeta,
+    ) -> io::Result<Self> {
+        let filename = path
+            .file_name()
+            .and_then(|n| n.to_str())
+            .unwrap_or("unknown")
+            .to_string();
+        Self::from_content(filename, &fs::read_to_string(path)?, options, Some(meta))
+    }
+
+    /// Everything piped in on stdin, picked from like a prose file
+    fn load_from_stdin(options: &SnippetOptions) -> io::Result<Self> {
+        let content = io::read_to_string(io::stdin())?;
+        Self::from_content("stdin".to_string(), &content, options, None)
+    }
+
+    fn from_content(
+        filename: String,
+        content: &str,
+        options: &SnippetOptions,
+        meta: Option<SourceMeta>,
+    ) -> io::Result<Self> {
+        let mut content = sanitize_control_chars(content, options.control_chars);
+        if options.ascii_fold {
+            content = ascii_fold(&content);
         }
 
-        // Help
-        let help = Paragraph::new("Left/Right: Switch views  'e': Export  'r': Retry  'q': Back")
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::DarkGray));
-        f.render_widget(help, chunks[2]);
+        let (processed_content, score) = match options.lines {
+            Some(range) => (Self::extract_line_range(&content, range)?, None),
+            None => Self::extract_file_snippet(&content, &filename, options),
+        };
+        let processed_content = options.apply_transforms(processed_content);
+        Ok(TextSource::File(filename, processed_content, score, meta))
     }
-}
 
-fn render_consolidated_charts_view(
-    f: &mut Frame,
-    area: ratatui::layout::Rect,
-    report: &SessionReport,
-) {
-    let main_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(75), Constraint::Percentage(25)])
-        .split(area);
+    /// Render `page` with the system `man` and pick a snippet from it like any file
+    fn load_man_page(page: &str, options: &SnippetOptions) -> io::Result<Self> {
+        Self::load_man_page_with(page, options, run_man)
+    }
 
-    let chart_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage(25), // Summary stats
-            Constraint::Percentage(35), // Key frequency and errors
-            Constraint::Percentage(40), // Error timeline and hesitation
-        ])
-        .split(main_chunks[0]);
+    /// load_man_page with the `man` invocation swapped out: `run` returns the
+    /// rendered page, or None when there is no manual entry
+    fn load_man_page_with(
+        page: &str,
+        options: &SnippetOptions,
+        run: impl FnOnce(&str) -> io::Result<Option<Vec<u8>>>,
+    ) -> io::Result<Self> {
+        let Some(output) = run(page)? else {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no manual entry for {}", page),
+            ));
+        };
 
-    // Summary stats bar
-    let stats_text = format!(
-        "WPM: {:.1} | Accuracy: {:.1}% | Errors: {} | Duration: {:.1}s | Avg Latency: {}ms",
-        report.wpm,
-        report.accuracy,
-        report.errors.len(),
-        report.session_duration.as_secs_f64(),
-        report.average_latency.as_millis()
-    );
+        let rendered = strip_overstrike(&String::from_utf8_lossy(&output));
+        let mut content = sanitize_control_chars(&rendered, options.control_chars);
+        if options.ascii_fold {
+            content = ascii_fold(&content);
+        }
 
-    let stats = Paragraph::new(stats_text)
-        .block(
-            Block::default()
-                .title("Session Summary")
-                .borders(Borders::ALL),
-        )
-        .alignment(Alignment::Center)
-        .style(
-            Style::default()
-                .fg(Color::Green)
-                .add_modifier(Modifier::BOLD),
-        );
-    f.render_widget(stats, chart_chunks[0]);
+        let name = format!("man {}", page);
+        let (snippet, score) = Self::extract_file_snippet(&content, &name, options);
+        Ok(TextSource::File(
+            name,
+            options.apply_transforms(snippet),
+            score,
+            None,
+        ))
+    }
 
-    // Key charts - top row
-    let key_charts = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(chart_chunks[1]);
+    fn extract_line_range(content: &str, range: LineRange) -> io::Result<String> {
+        let lines: Vec<&str> = content.lines().collect();
 
-    // Most frequent keys chart
-    let mut key_data: Vec<_> = report
-        .key_stats
-        .iter()
-        .map(|(key, stats)| {
-            let display_key = if *key == ' ' {
-                "Space".to_string()
-            } else {
-                key.to_string()
-            };
-            (display_key, stats.count as u64)
-        })
-        .collect();
-    key_data.sort_by(|a, b| b.1.cmp(&a.1));
-    key_data.truncate(8);
+        if range.end > lines.len() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "line range {}:{} is out of bounds (file has {} lines)",
+                    range.start,
+                    range.end,
+                    lines.len()
+                ),
+            ));
+        }
 
-    let key_chart_data: Vec<_> = key_data
-        .iter()
-        .map(|(key, count)| (key.as_str(), *count))
-        .collect();
+        Ok(lines[range.start - 1..range.end].join("\n"))
+    }
 
-    let key_chart = BarChart::default()
-        .block(
-            Block::default()
-                .title("Most Used Keys")
-                .borders(Borders::ALL),
-        )
-        .data(&key_chart_data)
-        .bar_width(3)
-        .bar_style(Style::default().fg(Color::Green))
-        .value_style(
-            Style::default()
-                .fg(Color::White)
-                .add_modifier(Modifier::BOLD),
-        );
-    f.render_widget(key_chart, key_charts[0]);
+    /// Pick a snippet, returning it with its paragraph score (None when the
+    /// line-slice fallback was used)
+    fn extract_file_snippet(
+        content: &str,
+        filename: &str,
+        options: &SnippetOptions,
+    ) -> (String, Option<f32>) {
+        let (target_min_chars, target_max_chars) = options.char_range();
+
+        // License headers and shebangs make dull practice, drop them up front
+        let content = if !options.keep_headers && is_code_file(filename) {
+            Self::strip_leading_header(content)
+        } else {
+            content
+        };
 
-    // Error-prone keys chart
-    let mut error_data: Vec<_> = report
-        .key_stats
-        .iter()
-        .filter(|(_, stats)| stats.error_count > 0)
-        .map(|(key, stats)| {
-            let display_key = if *key == ' ' {
-                "Space".to_string()
-            } else {
-                key.to_string()
-            };
-            (display_key, stats.error_count as u64)
-        })
-        .collect();
-    error_data.sort_by(|a, b| b.1.cmp(&a.1));
-    error_data.truncate(8);
+        // Find all meaningful paragraphs/sections
+        let mut paragraphs = Self::find_paragraphs(content, filename);
+        paragraphs.retain(|p| has_visible_chars(&p.content) && !options.is_excluded(&p.content));
 
-    if !error_data.is_empty() {
-        let error_chart_data: Vec<_> = error_data
+        // Score paragraphs strategically (higher score = better for typing practice)
+        for paragraph in &mut paragraphs {
+            paragraph.score = Self::calculate_paragraph_score(&paragraph.content, filename);
+        }
+        // Adjacent paragraphs can still be combined, keep one copy in file order
+        let paragraphs_in_order = paragraphs.clone();
+
+        // Sort by score (best first) then randomize within score tiers
+        paragraphs.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+
+        // Filter paragraphs that fit within the size constraints
+        let suitable_paragraphs: Vec<_> = paragraphs
             .iter()
-            .map(|(key, count)| (key.as_str(), *count))
+            .filter(|p| p.char_count >= target_min_chars && p.char_count <= target_max_chars)
             .collect();
 
-        let error_chart = BarChart::default()
-            .block(
-                Block::default()
-                    .title("Error-Prone Keys")
-                    .borders(Borders::ALL),
-            )
-            .data(&error_chart_data)
-            .bar_width(3)
-            .bar_style(Style::default().fg(Color::Red))
-            .value_style(
-                Style::default()
-                    .fg(Color::White)
-                    .add_modifier(Modifier::BOLD),
+        // If we have suitable paragraphs, pick strategically with randomness
+        if !suitable_paragraphs.is_empty() {
+            let selected = Self::select_strategic_paragraph(&suitable_paragraphs);
+
+            return (
+                options.finish_snippet(&selected.content),
+                Some(selected.score),
             );
-        f.render_widget(error_chart, key_charts[1]);
-    } else {
-        let no_errors = Paragraph::new("No errors! Perfect typing!")
-            .block(
-                Block::default()
-                    .title("Error-Prone Keys")
-                    .borders(Borders::ALL),
-            )
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::Green));
-        f.render_widget(no_errors, key_charts[1]);
-    }
+        }
 
-    // Bottom charts row
-    let bottom_charts = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(chart_chunks[2]);
+        // No single paragraph fits: try a run of adjacent ones that does together
+        if let Some(combined) = Self::combine_adjacent_paragraphs(
+            &paragraphs_in_order,
+            target_min_chars,
+            target_max_chars,
+        ) {
+            return (
+                options.finish_snippet(&combined.content),
+                Some(combined.score),
+            );
+        }
 
-    // Error timeline
-    if !report.errors.is_empty() {
-        let timeline_text = report
-            .errors
+        // If no perfect fit, find the best-scoring paragraph that's still meaningful
+        let acceptable_paragraphs: Vec<_> = paragraphs
             .iter()
-            .take(10)
-            .enumerate()
-            .map(|(i, error)| {
-                let timestamp = error.timestamp.as_secs_f64();
-                format!(
-                    "{}. {:.1}s: {} '{}' -> '{}'",
-                    i + 1,
-                    timestamp,
-                    match error.error_type {
-                        ErrorType::Substitution => "Sub",
-                        ErrorType::Insertion => "Ins",
-                        ErrorType::Omission => "Omi",
-                        ErrorType::Repeat => "Rep",
-                    },
-                    error.expected_char.unwrap_or('?'),
-                    error.actual_char.unwrap_or('?')
-                )
-            })
-            .collect::<Vec<_>>()
-            .join("\n");
+            .filter(|p| p.char_count >= target_min_chars / 2) // At least half the target
+            .collect();
 
-        let timeline = Paragraph::new(timeline_text)
-            .block(
-                Block::default()
-                    .title("Error Timeline")
-                    .borders(Borders::ALL),
-            )
-            .wrap(Wrap { trim: true });
-        f.render_widget(timeline, bottom_charts[0]);
-    } else {
-        let no_errors = Paragraph::new("No errors recorded!\nPerfect session!")
-            .block(
-                Block::default()
-                    .title("Error Timeline")
-                    .borders(Borders::ALL),
-            )
-            .alignment(Alignment::Center)
-            .style(Style::default().fg(Color::Green));
-        f.render_widget(no_errors, bottom_charts[0]);
+        if !acceptable_paragraphs.is_empty() {
+            let selected = Self::select_strategic_paragraph(&acceptable_paragraphs);
+
+            return (
+                options.finish_snippet(&selected.content),
+                Some(selected.score),
+            );
+        }
+
+        // Fallback: create a chunk of the target size from the middle of the file
+        let lines: Vec<&str> = content.lines().collect();
+        let (_target_lines_min, target_lines_max) = options.line_range();
+        let start_idx = lines.len() / 3; // Start from 1/3 into the file
+        let end_idx = (start_idx + target_lines_max).min(lines.len());
+        let snippet_lines = &lines[start_idx..end_idx];
+        let content_str = snippet_lines.join("\n");
+
+        (options.finish_snippet(&content_str), None)
     }
 
-    // Hesitation visualization
-    let hesitation_text = if report.hesitation_patterns.is_empty() {
-        "No significant hesitations!\nGood rhythm maintained.".to_string()
-    } else {
-        report
-            .hesitation_patterns
+    /// The best-scoring (by average) run of consecutive paragraphs whose combined
+    /// size falls within the band, joined with blank lines in file order
+    fn combine_adjacent_paragraphs(
+        paragraphs: &[TextParagraph],
+        min_chars: usize,
+        max_chars: usize,
+    ) -> Option<TextParagraph> {
+        const SEPARATOR: &str = "\n\n";
+        let mut best: Option<(usize, usize, f32)> = None; // (start, end, average score)
+
+        for start in 0..paragraphs.len() {
+            let mut char_count = 0;
+            let mut score_sum = 0.0;
+            for (end, paragraph) in paragraphs.iter().enumerate().skip(start) {
+                if end > start {
+                    char_count += SEPARATOR.len();
+                }
+                char_count += paragraph.char_count;
+                score_sum += paragraph.score;
+                if char_count > max_chars {
+                    break;
+                }
+
+                let average = score_sum / (end - start + 1) as f32;
+                if end > start
+                    && char_count >= min_chars
+                    && best.is_none_or(|(_, _, best_average)| average > best_average)
+                {
+                    best = Some((start, end, average));
+                }
+            }
+        }
+
+        let (start, end, score) = best?;
+        let content = paragraphs[start..=end]
             .iter()
-            .take(8)
-            .map(|h| {
-                format!(
-                    "{}ms at pos {} ({})",
-                    h.duration.as_millis(),
-                    h.position,
-                    match h.pattern_type {
-                        HesitationType::LongPause => "Long Pause",
-                        HesitationType::DoubleDigraph => "Common Combo",
-                        HesitationType::Transition => "Hand Switch",
-                        HesitationType::Punctuation => "Punctuation",
-                        HesitationType::CaseChange => "Case Change",
-                        HesitationType::NumberSymbol => "Number/Symbol",
-                    }
-                )
-            })
+            .map(|p| p.content.as_str())
             .collect::<Vec<_>>()
-            .join("\n")
-    };
+            .join(SEPARATOR);
+        Some(TextParagraph {
+            char_count: content.chars().count(),
+            content,
+            score,
+        })
+    }
 
-    let hesitation = Paragraph::new(hesitation_text)
-        .block(
-            Block::default()
-                .title("Hesitation Patterns")
-                .borders(Borders::ALL),
-        )
-        .wrap(Wrap { trim: true });
-    f.render_widget(hesitation, bottom_charts[1]);
+    /// Skip a leading shebang and any comment block (license headers, module
+    /// banners) so snippets start at real code
+    fn strip_leading_header(content: &str) -> &str {
+        let mut offset = 0;
+        let mut in_block_comment = false;
 
-    // Educational sidebar
-    let education_text = "VISUAL ANALYSIS GUIDE\n\n\
-        📊 WHAT YOU'RE SEEING:\n\
-        • Key Usage: Shows which keys you type most\n\
-        • Error Patterns: Reveals problem keys\n\
-        • Error Timeline: When mistakes occur\n\
-        • Hesitation Points: Where you slow down\n\n\
-        🎯 HOW THIS HELPS:\n\
-        • Identify weak finger positions\n\
-        • Spot rhythm disruption patterns\n\
-        • Focus practice on problem areas\n\
-        • Track improvement over time\n\n\
-        💡 ACTION ITEMS:\n\
-        • Practice error-prone keys separately\n\
-        • Work on smooth transitions\n\
-        • Build muscle memory for hesitation points\n\
-        • Maintain consistent rhythm\n\n\
-        Switch to 'Detailed Insights' →\n\
-        for specific recommendations";
+        for line in content.split_inclusive('\n') {
+            let trimmed = line.trim();
 
-    let education = Paragraph::new(education_text)
-        .wrap(Wrap { trim: true })
-        .style(Style::default().fg(Color::DarkGray))
-        .block(Block::default().padding(Padding::uniform(2)));
-    f.render_widget(education, main_chunks[1]);
-}
+            let is_header = if in_block_comment {
+                if trimmed.contains("*/") {
+                    in_block_comment = false;
+                }
+                true
+            } else if offset == 0 && trimmed.starts_with("#!") && !trimmed.starts_with("#![") {
+                true
+            } else if trimmed.starts_with("/*") {
+                in_block_comment = !trimmed.contains("*/");
+                true
+            } else {
+                trimmed.is_empty()
+                    || trimmed.starts_with("//")
+                    || trimmed == "#"
+                    || trimmed.starts_with("# ")
+                    || trimmed.starts_with("##")
+            };
 
-fn render_consolidated_analysis_view(
-    f: &mut Frame,
-    area: ratatui::layout::Rect,
-    report: &SessionReport,
-) {
-    let main_chunks = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(75), Constraint::Percentage(25)])
-        .split(area);
+            if !is_header {
+                break;
+            }
+            offset += line.len();
+        }
 
-    let analysis_chunks = Layout::default()
-        .direction(Direction::Vertical)
-        .constraints([
-            Constraint::Percentage(20), // Key stats
-            Constraint::Percentage(25), // Slowest digraphs and finger errors
-            Constraint::Percentage(25), // Weakness summary and rhythm
-            Constraint::Percentage(30), // Detailed recommendations
-        ])
-        .split(main_chunks[0]);
+        // A file that is nothing but comments is still better than nothing
+        if content[offset..].trim().is_empty() {
+            content
+        } else {
+            &content[offset..]
+        }
+    }
 
-    // Key performance metrics
-    let total_latency: Duration = report
-        .key_stats
-        .values()
-        .map(|stat| stat.total_latency)
-        .sum();
-    let total_keys: u32 = report.key_stats.values().map(|stat| stat.count).sum();
-    let avg_latency = if total_keys > 0 {
-        total_latency.as_millis() / total_keys as u128
-    } else {
-        0
-    };
+    fn find_paragraphs(content: &str, filename: &str) -> Vec<TextParagraph> {
+        let lines: Vec<&str> = content.lines().collect();
+        let mut paragraphs = Vec::new();
 
-    let metrics_text = format!(
-        "PERFORMANCE METRICS\n\
-         • Speed: {:.1} WPM (Target: 40+ WPM)\n\
-         • Accuracy: {:.1}% (Target: 95%+)\n\
-         • Consistency: {}ms avg latency\n\
-         • Error Rate: {:.2}% (Target: <2%)\n\
-         • Rhythm Stability: {} breaks detected",
-        report.wpm,
-        report.accuracy,
-        avg_latency,
-        (report.errors.len() as f64 / report.total_characters as f64) * 100.0,
-        report.weakness_analysis.rhythm_breaks.len()
-    );
+        // For code files, find function/struct/impl blocks
+        if is_code_file(filename) {
+            let mut current_start = 0;
+            let mut brace_depth = 0;
+            let mut in_block = false;
 
-    let metrics = Paragraph::new(metrics_text)
-        .block(
-            Block::default()
-                .title("📈 Performance Overview")
-                .borders(Borders::ALL),
-        )
-        .style(Style::default().fg(Color::Green));
-    f.render_widget(metrics, analysis_chunks[0]);
+            for (i, line) in lines.iter().enumerate() {
+                let trimmed = line.trim();
+
+                // Detect start of code blocks
+                if (trimmed.starts_with("fn ")
+                    || trimmed.starts_with("pub fn ")
+                    || trimmed.starts_with("struct ")
+                    || trimmed.starts_with("impl ")
+                    || trimmed.starts_with("enum ")
+                    || trimmed.starts_with("class ")
+                    || trimmed.starts_with("def ")
+                    || trimmed.starts_with("function "))
+                    && brace_depth == 0
+                {
+                    current_start = i;
+                    in_block = true;
+                }
+
+                // Track braces
+                brace_depth += line.matches('{').count() as i32;
+                brace_depth -= line.matches('}').count() as i32;
+
+                // End of block
+                if in_block && brace_depth == 0 && line.contains('}') {
+                    let block_lines = &lines[current_start..=i];
+                    let content = block_lines.join("\n");
+                    if content.len() > 200 {
+                        // Only meaningful blocks
+                        paragraphs.push(TextParagraph {
+                            content,
+                            char_count: block_lines.join("\n").len(),
+                            score: 0.0, // Will be calculated later
+                        });
+                    }
+                    in_block = false;
+                }
+            }
+        } else {
+            // For text files, split by double newlines (paragraphs)
+            let content_str = content.to_string();
+
+            for paragraph_text in content_str.split("\n\n") {
+                if paragraph_text.trim().len() > 100 {
+                    // Only meaningful paragraphs
+                    paragraphs.push(TextParagraph {
+                        content: paragraph_text.to_string(),
+                        char_count: paragraph_text.len(),
+                        score: 0.0, // Will be calculated later
+                    });
+                }
+            }
+        }
+
+        paragraphs
+    }
+
+    fn calculate_paragraph_score(content: &str, filename: &str) -> f32 {
+        let mut score = 0.0f32;
+
+        // Base score from content length (sweet spot around 100-200 chars per complexity)
+        let len = content.len() as f32;
+        score += if len > 50.0 && len < 500.0 { 10.0 } else { 5.0 };
+
+        // Bonus for diverse character usage (good for typing practice)
+        let unique_chars = content
+            .chars()
+            .collect::<std::collections::HashSet<_>>()
+            .len() as f32;
+        score += unique_chars * 0.5;
+
+        // Code-specific scoring
+        if filename.ends_with(".rs")
+            || filename.ends_with(".py")
+            || filename.ends_with(".js")
+            || filename.ends_with(".ts")
+            || filename.ends_with(".cpp")
+            || filename.ends_with(".java")
+        {
+            // Bonus for function implementations (good typing practice)
+            if content.contains("fn ") || content.contains("function ") || content.contains("def ")
+            {
+                score += 15.0;
+            }
+
+            // Bonus for control structures (interesting patterns)
+            if content.contains("if ")
+                || content.contains("for ")
+                || content.contains("while ")
+                || content.contains("match ")
+                || content.contains("switch ")
+            {
+                score += 10.0;
+            }
+
+            // Bonus for data structures
+            if content.contains("struct ")
+                || content.contains("class ")
+                || content.contains("enum ")
+            {
+                score += 12.0;
+            }
+
+            // Bonus for error handling (challenging typing)
+            if content.contains("Result")
+                || content.contains("Option")
+                || content.contains("Error")
+                || content.contains("try")
+                || content.contains("catch")
+                || content.contains("except")
+            {
+                score += 8.0;
+            }
+
+            // Bonus for generics and advanced syntax (very good practice)
+            if content.contains('<') && content.contains('>') || content.contains("impl ") {
+                score += 12.0;
+            }
+
+            // Penalty for mostly comments or too simple
+            let comment_ratio = content.lines().filter(|line| is_comment_line(line)).count() as f32
+                / content.lines().count().max(1) as f32;
+            score -= comment_ratio * 10.0;
+
+            // Penalty for too many imports/includes (boring)
+            if content.contains("import ")
+                || content.contains("use ")
+                || content.contains("#include")
+            {
+                let import_lines = content
+                    .lines()
+                    .filter(|line| {
+                        line.contains("import ")
+                            || line.contains("use ")
+                            || line.contains("#include")
+                    })
+                    .count();
+                if import_lines > 3 {
+                    score -= 5.0;
+                }
+            }
+        } else {
+            // Text file scoring
+            let word_count = content.split_whitespace().count() as f32;
+
+            // Bonus for good paragraph length
+            if word_count > 20.0 && word_count < 150.0 {
+                score += 10.0;
+            }
+
+            // Bonus for punctuation variety (good typing practice)
+            let punct_chars = content
+                .chars()
+                .filter(|c| ".,;:!?\"'()-[]{}".contains(*c))
+                .count() as f32;
+            score += punct_chars * 0.3;
+
+            // Bonus for sentences (complete thoughts)
+            let sentence_count = content.matches(|c| ".!?".contains(c)).count() as f32;
+            score += sentence_count * 2.0;
+        }
+
+        // Avoid very short or very long content
+        if len < 100.0 {
+            score -= 5.0;
+        }
+        if len > 1000.0 {
+            score -= 3.0;
+        }
+
+        // Ensure non-negative score
+        score.max(0.0)
+    }
+
+    fn select_strategic_paragraph<'a>(paragraphs: &'a [&'a TextParagraph]) -> &'a TextParagraph {
+        use rand::seq::SliceRandom;
+        use rand::thread_rng;
+
+        paragraphs.choose(&mut thread_rng()).unwrap()
+    }
+
+    fn load_inception(options: &SnippetOptions) -> io::Result<Self> {
+        // Include the source code directly at compile time
+        let full_content = include_str!("main.rs");
+
+        // Split into meaningful code sections and select one based on size
+        let (snippet, score) = Self::extract_code_section(full_content, options);
+        Ok(TextSource::Inception(
+            options.apply_transforms(snippet),
+            score,
+        ))
+    }
+
+    /// A short run of quotations for --calibrate, never transformed
+    fn load_calibration() -> Self {
+        let (quotes, authors) =
+            Self::draw_quotes(CALIBRATION_MIN_CHARS, CALIBRATION_MAX_CHARS, None);
+        TextSource::Quote(quotes, authors)
+    }
+
+    /// Chain randomly drawn quotations until the text falls in the size band.
+    /// Each quote is used at most once per text, so repeats can't happen within a run.
+    fn load_quotes(options: &SnippetOptions) -> Self {
+        let (target_min_chars, target_max_chars) = options.char_range();
+        let (quotes, authors) = Self::draw_quotes(target_min_chars, target_max_chars, options.seed);
+        TextSource::Quote(options.apply_transforms(quotes), authors)
+    }
+
+    /// Newline-joined quotes totalling between `min_chars` and `max_chars`, with
+    /// their authors in order of appearance
+    fn draw_quotes(min_chars: usize, max_chars: usize, seed: Option<u64>) -> (String, Vec<String>) {
+        use rand::SeedableRng;
+        use rand::seq::SliceRandom;
+
+        let mut quotes: Vec<(&str, &str)> = include_str!("quotes.txt")
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.rsplit_once(" | "))
+            .collect();
+
+        let mut rng = match seed {
+            Some(seed) => rand::rngs::StdRng::seed_from_u64(seed),
+            None => rand::rngs::StdRng::from_entropy(),
+        };
+        quotes.shuffle(&mut rng);
+
+        let mut selected: Vec<&str> = Vec::new();
+        let mut authors: Vec<String> = Vec::new();
+        let mut char_count = 0;
+        for (quote, author) in quotes {
+            if char_count >= min_chars {
+                break;
+            }
+            // +1 for the newline joining it to the previous quote
+            let quote_chars = quote.chars().count() + 1;
+            if char_count + quote_chars > max_chars {
+                continue;
+            }
+            char_count += quote_chars;
+            selected.push(quote);
+            if !authors.iter().any(|a| a == author) {
+                authors.push(author.to_string());
+            }
+        }
+
+        (selected.join("\n"), authors)
+    }
+
+    /// Doc-comment code examples of a crate, drawn at random (reproducible with
+    /// --seed) until the text reaches the size band
+    fn load_docs(krate: &str, options: &SnippetOptions) -> io::Result<Self> {
+        use rand::SeedableRng;
+        use rand::seq::SliceRandom;
+
+        let dir = crate_source_dir(krate)?;
+        let mut files = Vec::new();
+        collect_rust_files(&dir, &mut files)?;
+        files.sort();
+
+        let mut blocks: Vec<String> = files
+            .iter()
+            .filter_map(|path| fs::read_to_string(path).ok())
+            .flat_map(|source| doc_code_blocks(&source))
+            .filter(|block| has_visible_chars(block) && !options.is_excluded(block))
+            .collect();
+        if blocks.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("no doc-comment code examples under {}", dir.display()),
+            ));
+        }
+
+        let mut rng = match options.seed {
+            Some(seed) => rand::rngs::StdRng::seed_from_u64(seed),
+            None => rand::rngs::StdRng::from_entropy(),
+        };
+        blocks.shuffle(&mut rng);
+
+        let (target_min_chars, target_max_chars) = options.char_range();
+        let mut selected: Vec<&str> = Vec::new();
+        let mut char_count = 0;
+        for block in &blocks {
+            if char_count >= target_min_chars {
+                break;
+            }
+            // +2 for the blank line separating it from the previous example
+            let len = block.chars().count() + 2;
+            if !selected.is_empty() && char_count + len > target_max_chars {
+                continue;
+            }
+            selected.push(block);
+            char_count += len;
+        }
+
+        Ok(TextSource::File(
+            format!("docs: {}", krate),
+            options.apply_transforms(selected.join("\n\n")),
+            None,
+            None,
+        ))
+    }
+
+    /// A drill made only of SYMBOL_OPERATORS and balanced SYMBOL_BRACKETS
+    fn load_symbols(options: &SnippetOptions) -> Self {
+        let (target_min_chars, _) = options.char_range();
+        let text = generate_symbols(target_min_chars, options.seed);
+        TextSource::File(
+            "symbols drill".to_string(),
+            options.apply_transforms(text),
+            None,
+            None,
+        )
+    }
+
+    /// A drill of the words and keys a past report shows as weak
+    fn load_report_drill(path: &Path, options: &SnippetOptions) -> io::Result<Self> {
+        let report: SessionReport = serde_json::from_str(&fs::read_to_string(path)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+        let words = report_drill_words(&report);
+        if words.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the report has no missed words or weak keys to practice",
+            ));
+        }
+
+        let (target_min_chars, _) = options.char_range();
+        let text = drill_passage(&words, target_min_chars, options.seed);
+        let name = path
+            .file_name()
+            .unwrap_or(path.as_os_str())
+            .to_string_lossy();
+        Ok(TextSource::File(
+            format!("drill: {}", name),
+            options.apply_transforms(text),
+            None,
+            None,
+        ))
+    }
+
+    fn extract_code_section(content: &str, options: &SnippetOptions) -> (String, Option<f32>) {
+        let (target_min_chars, target_max_chars) = options.char_range();
+
+        // Use the same strategic paragraph logic for the source code
+        let mut paragraphs = Self::find_paragraphs(content, "main.rs");
+        paragraphs.retain(|p| !options.is_excluded(&p.content));
+
+        // Score paragraphs strategically (higher score = better for typing practice)
+        for paragraph in &mut paragraphs {
+            paragraph.score = Self::calculate_paragraph_score(&paragraph.content, "main.rs");
+        }
+
+        // Sort by score (best first)
+        paragraphs.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap());
+
+        // Filter paragraphs that fit the size requirement
+        let suitable_paragraphs: Vec<_> = paragraphs
+            .iter()
+            .filter(|p| p.char_count >= target_min_chars && p.char_count <= target_max_chars)
+            .collect();
+
+        if !suitable_paragraphs.is_empty() {
+            let selected = Self::select_strategic_paragraph(&suitable_paragraphs);
+            return (
+                options.finish_snippet(&selected.content),
+                Some(selected.score),
+            );
+        }
+
+        // If no perfect fit, find the best available paragraph
+        let acceptable_paragraphs: Vec<_> = paragraphs
+            .iter()
+            .filter(|p| p.char_count >= target_min_chars / 2)
+            .collect();
+
+        if !acceptable_paragraphs.is_empty() {
+            let selected = Self::select_strategic_paragraph(&acceptable_paragraphs);
+            return (
+                options.finish_snippet(&selected.content),
+                Some(selected.score),
+            );
+        }
+
+        // Fallback: use a chunk from the beginning
+        let lines: Vec<&str> = content.lines().collect();
+        let (_, target_max_lines) = options.line_range();
+        let end = target_max_lines.min(lines.len());
+        let content_str = lines[0..end].join("\n");
+
+        (options.finish_snippet(&content_str), None)
+    }
+
+    fn get_content(&self) -> Option<(String, String)> {
+        match self {
+            TextSource::File(name, content, ..) => Some((name.clone(), content.clone())),
+            TextSource::Inception(content, _) => {
+                Some(("main.rs (INCEPTION MODE)".to_string(), content.clone()))
+            }
+            TextSource::Quote(content, _) => Some(("quotes".to_string(), content.clone())),
+        }
+    }
+
+    fn snippet_score(&self) -> Option<f32> {
+        match self {
+            TextSource::File(_, _, score, _) | TextSource::Inception(_, score) => *score,
+            TextSource::Quote(..) => None,
+        }
+    }
+
+    /// Whether passages flagged with 'b' are skipped the next time this source
+    /// is loaded. Quotes are drawn whole, and drills and ghost races are
+    /// generated or replayed, so flagging them would have no effect
+    fn honours_exclusions(&self) -> bool {
+        match self {
+            TextSource::File(name, ..) => {
+                !(name == "symbols drill" || name == "ghost race" || name.starts_with("drill: "))
+            }
+            TextSource::Inception(..) => true,
+            TextSource::Quote(..) => false,
+        }
+    }
+
+    fn attribution(&self) -> Option<String> {
+        match self {
+            TextSource::Quote(_, authors) => Some(authors.join(", ")),
+            _ => None,
+        }
+    }
+
+    /// A fresh snippet from the same source, for extending an --until-wpm
+    /// passage. Files are read again; text that can't be (stdin, a process
+    /// substitution, a ghost) is repeated as is
+    fn another_snippet(&self, options: &SnippetOptions) -> Option<String> {
+        let source = match self {
+            TextSource::File(.., Some(meta)) => {
+                TextSource::load_from_file_with(Path::new(&meta.path), options, meta.clone()).ok()
+            }
+            TextSource::File(..) => None,
+            TextSource::Inception(..) => TextSource::load_inception(options).ok(),
+            TextSource::Quote(..) => Some(TextSource::load_quotes(options)),
+        };
+        source
+            .as_ref()
+            .unwrap_or(self)
+            .get_content()
+            .map(|(_, content)| content)
+            .filter(|content| has_visible_chars(content))
+    }
+
+    /// Where the practiced code came from. Inception names this crate's
+    /// embedded source; its commit is only known if KEYSENTRY_COMMIT was set
+    /// when the binary was built
+    fn source_meta(&self) -> Option<SourceMeta> {
+        match self {
+            TextSource::File(.., meta) => meta.clone(),
+            TextSource::Inception(..) => Some(SourceMeta {
+                path: concat!(
+                    env!("CARGO_PKG_NAME"),
+                    "-",
+                    env!("CARGO_PKG_VERSION"),
+                    "/src/main.rs"
+                )
+                .to_string(),
+                commit: option_env!("KEYSENTRY_COMMIT").map(str::to_string),
+            }),
+            TextSource::Quote(..) => None,
+        }
+    }
+}
+
+impl App {
+    /// Start typing `text_source` right away, or open the file browser in the
+    /// current directory when there is none
+    fn new(
+        text_source: Option<TextSource>,
+        snippet_options: SnippetOptions,
+        session_config: SessionConfig,
+        ui_options: UiOptions,
+        history: Option<History>,
+        ghost: Option<Ghost>,
+    ) -> io::Result<Self> {
+        let browser = match text_source {
+            Some(_) => None,
+            None => Some(FileBrowser::open(std::env::current_dir()?)?),
+        };
+
+        let warmup_pending = ui_options.warmup;
+        let mut app = Self {
+            session: None,
+            session_config,
+            ui_options,
+            text_source,
+            snippet_options,
+            browser,
+            ghost,
+            warmup_pending,
+            warming_up: false,
+            drilling: false,
+            clipboard: None,
+            report_message: None,
+            should_quit: false,
+            state: AppState::Browsing,
+            report_view: ReportView::Charts,
+            review_index: None,
+            error_inspector: false,
+            inspector_scroll: 0,
+            history,
+            streak: 0,
+            personal_best: None,
+            pomodoro: Pomodoro::default(),
+            source_metas: HashMap::new(),
+            export_dir: PathBuf::new(),
+        };
+        app.update_streak();
+
+        // Immediately start typing session
+        app.start_typing_session();
+
+        Ok(app)
+    }
+
+    fn start_typing_session(&mut self) {
+        if self.text_source.is_some() && self.warmup_pending {
+            self.start_warmup();
+            return;
+        }
+
+        if let Some(text_source) = &self.text_source
+            && let Some((name, content)) = text_source.get_content()
+        {
+            if !has_visible_chars(&content) {
+                if let Some(browser) = &mut self.browser {
+                    browser.message = Some(format!("{} has no text to type", name));
+                }
+                self.text_source = None;
+                return;
+            }
+            let mut session = TypingSession::new(content, self.session_config.clone());
+            session.snippet_score = text_source.snippet_score();
+            session.attribution = text_source.attribution();
+            session.source_meta = text_source.source_meta();
+            self.session = Some(session);
+            self.warming_up = false;
+            if self.ui_options.pomodoro.is_some() && self.pomodoro.focus_start.is_none() {
+                self.pomodoro.focus_start = Some(Instant::now());
+            }
+            self.state = AppState::Typing;
+            self.review_index = None;
+            self.error_inspector = false;
+            self.drilling = false;
+            self.report_message = None;
+        }
+    }
+
+    /// Re-practice just the worst error cluster of the finished session
+    fn start_cluster_drill(&mut self) {
+        let Some(mut text) = self
+            .session
+            .as_ref()
+            .and_then(TypingSession::weakest_cluster_text)
+        else {
+            return;
+        };
+        // Follow up with a few words saved from earlier sessions
+        let saved = weak_words_path()
+            .and_then(|path| load_weak_words(&path).ok())
+            .unwrap_or_default();
+        let extra: Vec<&str> = saved
+            .iter()
+            .rev()
+            .filter(|word| !text.contains(word.as_str()))
+            .take(WEAK_WORDS_PER_DRILL)
+            .map(String::as_str)
+            .collect();
+        if !extra.is_empty() {
+            text = format!("{}\n{}", text, extra.join(" "));
+        }
+        self.session = Some(TypingSession::new(text, self.session_config.clone()));
+        self.state = AppState::Typing;
+        self.review_index = None;
+        self.error_inspector = false;
+        self.drilling = true;
+        self.report_message = None;
+    }
+
+    /// Save the words where errors clustered to the weak word list
+    fn save_weak_words(&mut self) {
+        let (Some(session), Some(path)) = (&self.session, weak_words_path()) else {
+            return;
+        };
+        self.report_message = Some(
+            match persist_weak_words(&session.generate_report(), &path) {
+                Ok(0) => "No new weak words to save".to_string(),
+                Ok(added) => format!("Saved {} weak words to {}", added, path.display()),
+                Err(e) => format!("Could not save weak words: {}", e),
+            },
+        );
+    }
+
+    /// Never pick the practiced passage again, here or in later runs
+    fn flag_bad_snippet(&mut self) {
+        let (Some(session), Some(path)) = (&self.session, excluded_snippets_path()) else {
+            return;
+        };
+        let flaggable = self
+            .text_source
+            .as_ref()
+            .is_some_and(TextSource::honours_exclusions);
+        if !flaggable || self.drilling {
+            self.report_message =
+                Some("Only passages from files, docs or --inception can be flagged".to_string());
+            return;
+        }
+        self.report_message = Some(
+            match persist_excluded_snippet(&session.target_text, &path) {
+                Ok(added) if added.is_empty() => "Passage already flagged".to_string(),
+                Ok(added) => {
+                    self.snippet_options.excluded.extend(added);
+                    "Passage flagged, it won't be picked again".to_string()
+                }
+                Err(e) => format!("Could not flag passage: {}", e),
+            },
+        );
+    }
+
+    /// Put the report JSON on the system clipboard, reporting failure (e.g. no
+    /// display server) on screen instead of erroring out
+    fn copy_report(&mut self) {
+        let Some(session) = &self.session else {
+            return;
+        };
+        let json = match serde_json::to_string_pretty(&session.generate_report()) {
+            Ok(json) => json,
+            Err(e) => {
+                self.report_message = Some(format!("Could not copy report: {}", e));
+                return;
+            }
+        };
+
+        let clipboard = match self.clipboard.take() {
+            Some(clipboard) => Ok(clipboard),
+            None => arboard::Clipboard::new().map(|c| Box::new(c) as Box<dyn Clipboard>),
+        };
+        let result = clipboard.and_then(|mut clipboard| {
+            let copied = clipboard.set_text(json);
+            self.clipboard = Some(clipboard);
+            copied
+        });
+
+        self.report_message = Some(match result {
+            Ok(()) => "Report copied to clipboard!".to_string(),
+            Err(e) => format!("Could not copy to clipboard: {}", e),
+        });
+    }
+
+    /// Pause the session once the user has been idle for the configured timeout
+    fn check_idle(&mut self) {
+        if self.state != AppState::Typing {
+            return;
+        }
+        if let (Some(timeout), Some(session)) = (self.ui_options.idle_timeout, &mut self.session)
+            && session.idle_time() >= timeout
+        {
+            session.pause_idle();
+        }
+    }
+
+    /// Start a break once the focus interval is over. A running session is never
+    /// interrupted: the break waits until it is finished and the report or the
+    /// browser is showing.
+    fn check_pomodoro(&mut self, now: Instant) {
+        let Some(focus) = self.ui_options.pomodoro else {
+            return;
+        };
+        match self.state {
+            AppState::Break => {
+                if self.pomodoro.break_end.is_some_and(|end| now >= end) {
+                    self.end_break();
+                }
+            }
+            AppState::Browsing | AppState::ShowingReport => {
+                let Some(start) = self.pomodoro.focus_start else {
+                    return;
+                };
+                let focused = now.duration_since(start);
+                if focused >= focus {
+                    self.pomodoro.focused += focused;
+                    self.pomodoro.intervals += 1;
+                    self.pomodoro.focus_start = None;
+                    self.pomodoro.break_end = Some(now + POMODORO_BREAK);
+                    self.pomodoro.back_to_report = self.state == AppState::ShowingReport;
+                    self.state = AppState::Break;
+                }
+            }
+            AppState::Typing | AppState::Completed => {}
+        }
+    }
+
+    /// Return to where the break started; the next session opens a new interval
+    fn end_break(&mut self) {
+        self.pomodoro.break_end = None;
+        self.state = if self.pomodoro.back_to_report {
+            AppState::ShowingReport
+        } else {
+            AppState::Browsing
+        };
+    }
+
+    /// Total focus time this run, including the interval in progress
+    fn focused_time(&self) -> Duration {
+        self.pomodoro.focused
+            + self
+                .pomodoro
+                .focus_start
+                .map_or(Duration::ZERO, |start| start.elapsed())
+    }
+
+    /// A couple of quotes typed in zen mode; the session is thrown away on completion
+    fn start_warmup(&mut self) {
+        let (text, _) = TextSource::draw_quotes(WARMUP_MIN_CHARS, WARMUP_MAX_CHARS, None);
+        // Zen records no WPM, so an --until-wpm target could never be reached
+        let config = SessionConfig {
+            zen: true,
+            telemetry: None,
+            until_wpm: None,
+            ..self.session_config.clone()
+        };
+        self.session = Some(TypingSession::new(text, config));
+        self.warmup_pending = false;
+        self.warming_up = true;
+        self.state = AppState::Typing;
+        self.review_index = None;
+        self.error_inspector = false;
+    }
+
+    /// Leave the current session: back to the browser if we came from it
+    fn leave_session(&mut self) {
+        if let Some(session) = &mut self.session {
+            session.flush_telemetry();
+        }
+        self.warming_up = false;
+        if self.browser.is_some() {
+            self.state = AppState::Browsing;
+        } else {
+            self.should_quit = true;
+        }
+    }
+
+    fn handle_browser_key(&mut self, code: KeyCode) {
+        let Some(browser) = &mut self.browser else {
+            return;
+        };
+
+        match code {
+            KeyCode::Char('q') | KeyCode::Esc => self.should_quit = true,
+            KeyCode::Up | KeyCode::Char('k') => browser.move_selection(-1),
+            KeyCode::Down | KeyCode::Char('j') => browser.move_selection(1),
+            KeyCode::Backspace | KeyCode::Left | KeyCode::Char('h') => browser.go_up(),
+            KeyCode::Enter | KeyCode::Right | KeyCode::Char('l') => {
+                if let Some(path) = browser.activate() {
+                    self.open_browsed_file(path);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Start a session on a file picked in the browser. Its git commit is
+    /// looked up the first time the file is picked and reused after that
+    fn open_browsed_file(&mut self, path: PathBuf) {
+        let meta = self
+            .source_metas
+            .entry(path.clone())
+            .or_insert_with(|| SourceMeta::for_path(&path))
+            .clone();
+        match TextSource::load_from_file_with(&path, &self.snippet_options, meta) {
+            Ok(text_source) => {
+                self.text_source = Some(text_source);
+                self.start_typing_session();
+            }
+            Err(e) => {
+                if let Some(browser) = &mut self.browser {
+                    browser.message = Some(format!("Cannot load file: {}", e));
+                }
+            }
+        }
+    }
+
+    fn handle_event(&mut self, event: Event) {
+        if let Event::Key(key) = event {
+            match self.state {
+                AppState::Browsing => self.handle_browser_key(key.code),
+                AppState::Completed => self.state = AppState::ShowingReport,
+                AppState::Break => match key.code {
+                    KeyCode::Enter => self.end_break(),
+                    KeyCode::Char('q') => self.should_quit = true,
+                    _ => {}
+                },
+                AppState::Typing => {
+                    if let Some(session) = &mut self.session {
+                        match key.code {
+                            KeyCode::Char('q')
+                                if key.modifiers.contains(event::KeyModifiers::CONTROL) =>
+                            {
+                                // Partial report: session_end is unset, so elapsed time is used
+                                let saved = (self.ui_options.save_on_quit && !self.warming_up)
+                                    .then(|| self.export_report());
+                                self.leave_session();
+                                if let (Some(Err(e)), Some(browser)) = (saved, &mut self.browser) {
+                                    browser.message =
+                                        Some(format!("Could not save partial report: {}", e));
+                                }
+                                return;
+                            }
+                            // The key that ends an idle pause isn't typed
+                            _ if session.is_paused() => session.resume(),
+                            KeyCode::Char('s')
+                                if key.modifiers.contains(event::KeyModifiers::CONTROL) =>
+                            {
+                                session.skip_word();
+                            }
+                            KeyCode::Char(c) => {
+                                session.handle_key(c);
+                            }
+                            KeyCode::Enter => {
+                                session.handle_key('\n');
+                            }
+                            KeyCode::Tab => {
+                                session.handle_tab();
+                            }
+                            KeyCode::Backspace => {
+                                session.handle_key('\x08');
+                            }
+                            // Input is append-only: the cursor always sits after the last
+                            // typed character, so there is nothing ahead of it to delete.
+                            // Moving it would break the position/latency bookkeeping, so
+                            // Delete and the arrows are ignored rather than logged as keys.
+                            KeyCode::Delete
+                            | KeyCode::Left
+                            | KeyCode::Right
+                            | KeyCode::Up
+                            | KeyCode::Down
+                            | KeyCode::Home
+                            | KeyCode::End => {}
+                            _ => {}
+                        }
+
+                        if session.is_complete()
+                            && session.wants_more_text()
+                            && let Some(more) = self
+                                .text_source
+                                .as_ref()
+                                .and_then(|source| source.another_snippet(&self.snippet_options))
+                        {
+                            session.extend_text(&more);
+                        } else if session.is_complete() {
+                            if self.warming_up {
+                                // Discard the warm-up and go straight into the real session
+                                self.warming_up = false;
+                                self.start_typing_session();
+                            } else {
+                                self.finish_session();
+                            }
+                        }
+                    }
+                }
+                AppState::ShowingReport => match key.code {
+                    KeyCode::Char('q') | KeyCode::Char('Q') | KeyCode::Esc => {
+                        self.leave_session();
+                    }
+                    KeyCode::Char('e') => {
+                        let saved = self.export_report();
+                        self.note_export("Report", saved);
+                    }
+                    KeyCode::Char('a') => {
+                        let saved = self.export_anonymized();
+                        self.note_export("Anonymized summary", saved);
+                    }
+                    KeyCode::Char('x') => {
+                        let saved = self.export_external();
+                        self.note_export("Site export", saved);
+                    }
+                    KeyCode::Char('c') => {
+                        self.copy_report();
+                    }
+                    KeyCode::Char('r') => {
+                        self.start_typing_session();
+                    }
+                    KeyCode::Char('w') => {
+                        self.start_cluster_drill();
+                    }
+                    KeyCode::Char('k') => {
+                        self.save_weak_words();
+                    }
+                    KeyCode::Char('b') => {
+                        self.flag_bad_snippet();
+                    }
+                    KeyCode::Char('d') => {
+                        self.review_index = None;
+                        self.error_inspector = !self.error_inspector;
+                        self.inspector_scroll = 0;
+                    }
+                    KeyCode::Up if self.error_inspector => {
+                        self.inspector_scroll = self.inspector_scroll.saturating_sub(1);
+                    }
+                    KeyCode::Down if self.error_inspector => {
+                        let errors = self.session.as_ref().map_or(0, |s| s.errors.len());
+                        self.inspector_scroll =
+                            (self.inspector_scroll + 1).min(errors.saturating_sub(1));
+                    }
+                    KeyCode::Left | KeyCode::Char('h') => {
+                        self.review_index = None;
+                        self.error_inspector = false;
+                        self.report_view = self.report_view.previous();
+                    }
+                    KeyCode::Right | KeyCode::Char('l') => {
+                        self.review_index = None;
+                        self.error_inspector = false;
+                        self.report_view = self.report_view.next();
+                    }
+                    KeyCode::Char(']') => self.step_review(true),
+                    KeyCode::Char('[') => self.step_review(false),
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    /// Switch to the report, comparing against and then recording into history
+    fn finish_session(&mut self) {
+        self.state = if self.ui_options.hold_on_complete {
+            AppState::Completed
+        } else {
+            AppState::ShowingReport
+        };
+        if let Some(session) = &mut self.session {
+            session.flush_telemetry();
+        }
+
+        if self.ui_options.calibrate {
+            self.save_calibration();
+            return;
+        }
+        if self.drilling {
+            return;
+        }
+        let (Some(session), Some(history)) = (&self.session, &mut self.history) else {
+            return;
+        };
+        let source = self
+            .text_source
+            .as_ref()
+            .and_then(TextSource::get_content)
+            .map(|(name, _)| name)
+            .unwrap_or_default();
+        let report = session.generate_report();
+        if report.paste_suspected && session.config.paste_guard == PasteGuard::Reject {
+            return;
+        }
+
+        self.personal_best = history.best_for(&source);
+        // History is a nice-to-have; a failed write must not end the session
+        if let Err(e) = history.append(HistoryEntry {
+            timestamp: chrono::Utc::now(),
+            source,
+            wpm: report.wpm,
+            accuracy: report.accuracy,
+            duration: report.session_duration,
+        }) {
+            self.report_message = Some(format!("History not saved: {}", e));
+        }
+        self.update_streak();
+    }
+
+    /// Store thresholds measured on the calibration passage in the preferences
+    fn save_calibration(&mut self) {
+        let Some(session) = &self.session else {
+            return;
+        };
+        let Some(calibration) = Calibration::from_report(&session.generate_report()) else {
+            self.report_message =
+                Some("Too few keystrokes to calibrate, press 'r' to try again".to_string());
+            return;
+        };
+
+        // Saving over a file that didn't parse would throw away the user's edits
+        let mut preferences = match Preferences::load() {
+            Ok(preferences) => preferences,
+            Err(e) => {
+                self.report_message = Some(format!("Calibration not saved, fix {}", e));
+                return;
+            }
+        };
+        preferences.hesitation_ms = Some(calibration.hesitation_ms);
+        preferences.target_wpm = Some(calibration.target_wpm);
+        self.report_message = Some(match preferences.save() {
+            Ok(path) => format!(
+                "Calibrated: hesitations over {}ms, target {} WPM (saved to {})",
+                calibration.hesitation_ms,
+                calibration.target_wpm,
+                path.display()
+            ),
+            Err(e) => format!("Could not save calibration: {}", e),
+        });
+    }
+
+    fn update_streak(&mut self) {
+        if let (Some(history), Some(goal)) = (&self.history, &self.ui_options.daily_goal) {
+            self.streak = history.daily_streak(goal, chrono::Local::now().date_naive());
+        }
+    }
+
+    /// Move the keystroke review cursor, entering review on the first step
+    fn step_review(&mut self, forward: bool) {
+        let keystrokes = self
+            .session
+            .as_ref()
+            .map_or(0, |session| session.typing_rhythm.len());
+        if keystrokes == 0 {
+            return;
+        }
+
+        self.error_inspector = false;
+        self.review_index = Some(match (self.review_index, forward) {
+            (None, true) => 0,
+            (None, false) => keystrokes - 1,
+            (Some(i), true) => (i + 1).min(keystrokes - 1),
+            (Some(i), false) => i.saturating_sub(1),
+        });
+    }
+
+    /// Show how an export went under the report. Failures (read-only
+    /// directory, full disk) are only reported, never fatal.
+    fn note_export(&mut self, what: &str, saved: io::Result<Option<String>>) {
+        self.report_message = match saved {
+            Ok(Some(filename)) => Some(format!("{} saved to {}", what, filename)),
+            Ok(None) => None,
+            Err(e) => Some(format!("Could not save {}: {}", what.to_lowercase(), e)),
+        };
+    }
+
+    /// Write the shareable, text-free summary next to the regular exports.
+    /// Each export returns the file it wrote, or None without a session
+    fn export_anonymized(&self) -> io::Result<Option<String>> {
+        if let Some(session) = &self.session {
+            let anonymized = session.generate_report().anonymized();
+            let json = serde_json::to_string_pretty(&anonymized)?;
+            let filename = format!(
+                "typing_report_anon_{}.json",
+                chrono::Utc::now().format("%Y%m%d_%H%M%S")
+            );
+            return self.write_export(&filename, json).map(Some);
+        }
+        Ok(None)
+    }
+
+    fn export_external(&self) -> io::Result<Option<String>> {
+        if let Some(session) = &self.session {
+            let result = session.generate_report().to_external_schema();
+            let json = serde_json::to_string_pretty(&result)?;
+            let filename = format!(
+                "typing_result_{}.json",
+                chrono::Utc::now().format("%Y%m%d_%H%M%S")
+            );
+            return self.write_export(&filename, json).map(Some);
+        }
+        Ok(None)
+    }
+
+    fn export_report(&self) -> io::Result<Option<String>> {
+        if let Some(session) = &self.session {
+            let report = session.generate_report();
+            let json = serde_json::to_string_pretty(&report)?;
+            let label = report
+                .label
+                .as_deref()
+                .map(|label| format!("{}_", sanitize_for_filename(label)))
+                .unwrap_or_default();
+            let filename = format!(
+                "typing_report_{}{}.json",
+                label,
+                chrono::Utc::now().format("%Y%m%d_%H%M%S")
+            );
+            return self.write_export(&filename, json).map(Some);
+        }
+        Ok(None)
+    }
+
+    /// Write `json` to `filename` in the export directory, returning the path written
+    fn write_export(&self, filename: &str, json: String) -> io::Result<String> {
+        let path = self.export_dir.join(filename);
+        fs::write(&path, json)?;
+        Ok(path.display().to_string())
+    }
+}
+
+fn ui_break(f: &mut Frame, app: &App) {
+    let left = app
+        .pomodoro
+        .break_end
+        .map_or(Duration::ZERO, |end| {
+            end.saturating_duration_since(Instant::now())
+        })
+        .as_secs();
+    let text = vec![
+        Line::from(Span::styled(
+            format!("☕ Break: {}:{:02} left", left / 60, left % 60),
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        )),
+        Line::from(""),
+        Line::from(format!(
+            "Focused for {} min over {} interval(s)",
+            app.pomodoro.focused.as_secs() / 60,
+            app.pomodoro.intervals
+        )),
+        Line::from(""),
+        Line::from(Span::styled(
+            "Stand up, stretch, rest your eyes. Enter: skip the break | q: quit",
+            Style::default().fg(Color::DarkGray),
+        )),
+    ];
+    let area = f.area();
+    let middle = ratatui::layout::Rect {
+        y: area.height.saturating_sub(text.len() as u16) / 2,
+        height: (text.len() as u16).min(area.height),
+        ..area
+    };
+    f.render_widget(Paragraph::new(text).alignment(Alignment::Center), middle);
+}
+
+fn ui_browser(f: &mut Frame, app: &App) {
+    let Some(browser) = &app.browser else {
+        return;
+    };
+
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints([
+            Constraint::Length(2),
+            Constraint::Min(1),
+            Constraint::Length(2),
+            Constraint::Length(3),
+        ])
+        .split(f.area());
+
+    let title = Paragraph::new(format!(
+        "Choose a text to practice: {}{}",
+        browser.dir.display(),
+        streak_suffix(app.streak)
+    ))
+    .alignment(Alignment::Center)
+    .style(
+        Style::default()
+            .fg(Color::Green)
+            .add_modifier(Modifier::BOLD),
+    );
+    f.render_widget(title, chunks[0]);
+
+    let items: Vec<ListItem> = browser
+        .entries
+        .iter()
+        .map(|entry| {
+            let (text, style) = match entry.kind {
+                EntryKind::Parent | EntryKind::Directory => {
+                    (format!("{}/", entry.name), Style::default().fg(Color::Blue))
+                }
+                EntryKind::TextFile => (entry.name.clone(), Style::default().fg(Color::White)),
+                EntryKind::Unsupported => {
+                    (entry.name.clone(), Style::default().fg(Color::DarkGray))
+                }
+            };
+            ListItem::new(text).style(style)
+        })
+        .collect();
+
+    let list = List::new(items)
+        .block(Block::default().borders(Borders::ALL))
+        .highlight_style(Style::default().add_modifier(Modifier::REVERSED))
+        .highlight_symbol("> ");
+    let mut list_state = ListState::default().with_selected(Some(browser.selected));
+    f.render_stateful_widget(list, chunks[1], &mut list_state);
+
+    if let Some(message) = &browser.message {
+        let status = Paragraph::new(message.as_str())
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::Yellow));
+        f.render_widget(status, chunks[2]);
+    }
+
+    let help = Paragraph::new("Up/Down: Navigate  Enter: Open  Backspace: Parent  'q': Quit")
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(help, chunks[3]);
+}
+
+fn ui_typing(f: &mut Frame, app: &App) {
+    if let Some(session) = &app.session {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints([
+                Constraint::Length(if app.ui_options.live_header { 2 } else { 0 }),
+                Constraint::Min(1),
+                Constraint::Length(if app.ui_options.sparkline { 2 } else { 0 }),
+                Constraint::Length(2),
+                Constraint::Length(3),
+            ])
+            .split(f.area());
+
+        if app.ui_options.live_header {
+            let accuracy = session.calculate_accuracy();
+            let header = Paragraph::new(Line::from(vec![
+                Span::styled(
+                    format!("WPM: {:.0}", session.calculate_wpm()),
+                    Style::default()
+                        .fg(accuracy_color(accuracy))
+                        .add_modifier(Modifier::BOLD),
+                ),
+                Span::styled(
+                    format!("   Accuracy: {:.1}%", accuracy),
+                    Style::default().fg(Color::DarkGray),
+                ),
+            ]))
+            .alignment(Alignment::Center);
+            f.render_widget(header, chunks[0]);
+        }
+
+        // Create horizontal layout for centering text in 80% width
+        let horizontal_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(10), // Left padding
+                Constraint::Percentage(80), // Text area (center 80%)
+                Constraint::Percentage(10), // Right padding
+            ])
+            .split(chunks[1]);
+
+        // Main typing area - centered text with styling
+        let text_block = Block::default().borders(Borders::NONE);
+
+        // Soft-wrap ourselves so very long lines break at word boundaries and
+        // the cursor's visual line is known for scrolling
+        let mut text_area = horizontal_chunks[1];
+        if app.ui_options.ruler && text_area.height > 1 {
+            let ruler_area = ratatui::layout::Rect {
+                height: 1,
+                ..text_area
+            };
+            text_area.y += 1;
+            text_area.height -= 1;
+            let ruler = Paragraph::new(column_ruler(ruler_area.width as usize))
+                .style(Style::default().fg(Color::DarkGray));
+            f.render_widget(ruler, ruler_area);
+        }
+        // The ghost only races on the exact text it was recorded on
+        let ghost_position = app
+            .ghost
+            .as_ref()
+            .filter(|ghost| ghost.target_text == session.target_text)
+            .map(|ghost| ghost.position_at(session.elapsed()));
+        let styled_lines = soft_wrap_lines(
+            session.generate_styled_text(ghost_position),
+            text_area.width as usize,
+        );
+        let scroll = cursor_line_index(&styled_lines).saturating_sub(text_area.height as usize / 3);
+
+        // Dim the passage while over the soft error cap
+        let text_style = if session.over_error_cap() && !session.is_frozen {
+            Style::default().add_modifier(Modifier::DIM)
+        } else {
+            Style::default()
+        };
+
+        let paragraph = Paragraph::new(styled_lines)
+            .block(text_block)
+            .style(text_style)
+            .scroll((scroll as u16, 0))
+            .alignment(Alignment::Left);
+
+        f.render_widget(paragraph, text_area);
+
+        // Recent keystroke latencies, oldest on the left: slow keys show up as spikes
+        if app.ui_options.sparkline {
+            let latencies: Vec<u64> = session.recent_latencies.iter().copied().collect();
+            let sparkline = Sparkline::default()
+                .data(&latencies)
+                .style(Style::default().fg(Color::Cyan));
+            let sparkline_area = Layout::default()
+                .direction(Direction::Horizontal)
+                .constraints([
+                    Constraint::Percentage(10),
+                    Constraint::Percentage(80),
+                    Constraint::Percentage(10),
+                ])
+                .split(chunks[2])[1];
+            f.render_widget(sparkline, sparkline_area);
+        }
+
+        // Status message
+        let status_color = if session.is_frozen || session.over_error_cap() {
+            Color::Red
+        } else if session.has_error {
+            Color::Yellow
+        } else {
+            Color::Green
+        };
+
+        let mut status_style = Style::default().fg(status_color);
+        if session.over_error_cap() && !session.is_frozen {
+            status_style = status_style.add_modifier(Modifier::SLOW_BLINK | Modifier::BOLD);
+        }
+
+        let status_text = if app.state == AppState::Completed {
+            "Done! Press any key for the report".to_string()
+        } else {
+            session.get_status()
+        };
+        let mut status_lines = vec![Line::from(Span::styled(status_text, status_style))];
+        if app.ghost.is_some() {
+            let race = match ghost_position {
+                None => "Ghost was recorded on a different text - not racing".to_string(),
+                Some(ghost) => match session.current_position.cmp(&ghost) {
+                    std::cmp::Ordering::Greater => {
+                        format!(
+                            "You're {} chars ahead of your ghost",
+                            session.current_position - ghost
+                        )
+                    }
+                    std::cmp::Ordering::Less => {
+                        format!(
+                            "Your ghost is {} chars ahead",
+                            ghost - session.current_position
+                        )
+                    }
+                    std::cmp::Ordering::Equal => "Neck and neck with your ghost".to_string(),
+                },
+            };
+            status_lines.push(Line::from(Span::styled(
+                race,
+                Style::default().fg(Color::Blue),
+            )));
+        }
+        let status = Paragraph::new(status_lines).alignment(Alignment::Center);
+        f.render_widget(status, chunks[3]);
+
+        // Simple help text at bottom
+        let help_text = if app.warming_up {
+            "Warm-up: not recorded, the real passage follows. Ctrl+Q: Back to selection"
+        } else {
+            "Type the text above. Ctrl+S: Skip word  Ctrl+Q: Back to selection"
+        };
+        let rating = session.difficulty.round() as usize;
+        let difficulty = format!(
+            "Difficulty: {}{} ({}/5)",
+            "★".repeat(rating),
+            "☆".repeat(5 - rating),
+            rating
+        );
+        let typed: String = session
+            .target_text
+            .chars()
+            .take(session.current_position)
+            .collect();
+        let footer = format!(
+            "{}   Typed: {} chars, {} words",
+            difficulty,
+            session.current_position,
+            completed_words(&typed)
+        );
+        let help = Paragraph::new(vec![Line::from(help_text), Line::from(footer)])
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::DarkGray));
+        f.render_widget(help, chunks[4]);
+    }
+}
+
+/// Words finished in a typed prefix: each word followed by whitespace, so the
+/// one still being typed doesn't count until the space after it
+fn completed_words(prefix: &str) -> usize {
+    prefix
+        .chars()
+        .zip(prefix.chars().skip(1))
+        .filter(|(a, b)| !a.is_whitespace() && b.is_whitespace())
+        .count()
+}
+
+/// Symbols that programming fonts commonly fuse into ligatures (->, !=, <=, ...)
+const LIGATURE_CHARS: &str = "-=!<>:|&/*+.~#?_";
+
+/// Append a zero-width non-joiner to a span when it and the next span differ
+/// in style and meet on two ligature symbols, so a font can't draw e.g. a
+/// half-typed "->" as one glyph with the cursor underline in its middle
+fn separate_ligatures(line: &mut Line<'static>) {
+    for i in 1..line.spans.len() {
+        let (before, after) = line.spans.split_at_mut(i);
+        let (prev, next) = (&mut before[i - 1], &after[0]);
+        let joins = prev
+            .content
+            .chars()
+            .last()
+            .is_some_and(|c| LIGATURE_CHARS.contains(c))
+            && next
+                .content
+                .chars()
+                .next()
+                .is_some_and(|c| LIGATURE_CHARS.contains(c));
+        if joins && prev.style != next.style {
+            prev.content.to_mut().push('\u{200C}');
+        }
+    }
+}
+
+/// Break lines wider than `width` into several visual lines, preferring to break
+/// after whitespace. Target newlines are untouched: these breaks are display-only.
+fn soft_wrap_lines(lines: Vec<Line<'static>>, width: usize) -> Vec<Line<'static>> {
+    if width == 0 {
+        return lines;
+    }
+
+    let span_width = |span: &Span| span.width();
+    let mut wrapped = Vec::new();
+
+    for line in lines {
+        let mut current: Vec<Span<'static>> = Vec::new();
+        let mut current_width = 0;
+
+        for span in line.spans {
+            let width_of_span = span_width(&span);
+
+            if current_width + width_of_span > width && !current.is_empty() {
+                // Carry the partial word over to the next visual line
+                let carry = current
+                    .iter()
+                    .rposition(|s| s.content.chars().all(char::is_whitespace))
+                    .map(|i| i + 1)
+                    .filter(|&i| i < current.len())
+                    .map(|i| current.split_off(i))
+                    .unwrap_or_default();
+
+                wrapped.push(Line::from(std::mem::take(&mut current)));
+                current_width = carry.iter().map(span_width).sum();
+                current = carry;
+            }
+
+            current_width += width_of_span;
+            current.push(span);
+        }
+
+        wrapped.push(Line::from(current));
+    }
+
+    wrapped
+}
+
+/// "····+····1····+····2…" marking every fifth column, `width` columns long
+fn column_ruler(width: usize) -> String {
+    (1..=width)
+        .map(|column| match column {
+            c if c % 10 == 0 => char::from_digit((c / 10 % 10) as u32, 10).unwrap_or('|'),
+            c if c % 5 == 0 => '+',
+            _ => '·',
+        })
+        .collect()
+}
+
+/// Index of the visual line holding the (underlined) cursor, 0 if none
+fn cursor_line_index(lines: &[Line]) -> usize {
+    lines
+        .iter()
+        .rposition(|line| {
+            line.spans
+                .iter()
+                .any(|span| span.style.add_modifier.contains(Modifier::UNDERLINED))
+        })
+        .unwrap_or(0)
+}
+
+/// Accuracy band colour: green at 97%+, yellow at 90%+, red below
+fn accuracy_color(accuracy: f64) -> Color {
+    if accuracy >= 97.0 {
+        Color::Green
+    } else if accuracy >= 90.0 {
+        Color::Yellow
+    } else {
+        Color::Red
+    }
+}
+
+fn ui_report(f: &mut Frame, app: &App) {
+    if let Some(session) = &app.session {
+        let report = session.generate_report();
+
+        if session.config.zen {
+            render_zen_summary(f, &report);
+            return;
+        }
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .margin(2)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Min(1),
+                Constraint::Length(3),
+            ])
+            .split(f.area());
+
+        // Title with view indicator
+        let view_name = match app.report_view {
+            ReportView::Charts => "Visual Analysis",
+            ReportView::Analysis => "Detailed Insights",
+            ReportView::HesitationMap => "Hesitation Map",
+        };
+        let title_text = match &report.label {
+            Some(label) => format!("Typing Session Complete! [{}] - {}", label, view_name),
+            None => format!("Typing Session Complete! - {}", view_name),
+        } + &streak_suffix(app.streak);
+        let mut title_lines = vec![Line::from(Span::styled(
+            title_text,
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        ))];
+        if let Some(attribution) = &report.attribution {
+            title_lines.push(Line::from(Span::styled(
+                format!("Quotes by {}", attribution),
+                Style::default().fg(Color::DarkGray),
+            )));
+        }
+        if report.paste_suspected {
+            title_lines.push(Line::from(Span::styled(
+                "⚠ Possibly pasted — results unreliable",
+                Style::default().fg(Color::Red).add_modifier(Modifier::BOLD),
+            )));
+        }
+        if let Some(chars) = report.error_free_chars {
+            let total = report.target_text.chars().count();
+            let (text, color) = if chars >= total {
+                (
+                    format!("Sudden death: all {} characters error-free!", total),
+                    Color::Green,
+                )
+            } else {
+                (
+                    format!(
+                        "💀 Sudden death: {} of {} characters error-free",
+                        chars, total
+                    ),
+                    Color::Red,
+                )
+            };
+            title_lines.push(Line::from(Span::styled(
+                text,
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            )));
+        }
+        if let Some((target, reached)) = report.until_wpm {
+            let (text, color) = match reached {
+                Some(time) => (
+                    format!("Reached {} WPM after {:.0}s", target, time.as_secs_f64()),
+                    Color::Green,
+                ),
+                None => (
+                    format!(
+                        "{} WPM not held for {}s yet",
+                        target,
+                        UNTIL_WPM_HOLD.as_secs()
+                    ),
+                    Color::Yellow,
+                ),
+            };
+            title_lines.push(Line::from(Span::styled(
+                text,
+                Style::default().fg(color).add_modifier(Modifier::BOLD),
+            )));
+        }
+        if let Some((start, end)) = report.ramp {
+            let mut spans = vec![Span::styled(
+                format!("Ramp {}→{} WPM: ", start, end),
+                Style::default().fg(Color::DarkGray),
+            )];
+            for &kept_up in &report.ramp_segments {
+                spans.push(if kept_up {
+                    Span::styled("✓ ", Style::default().fg(Color::Green))
+                } else {
+                    Span::styled("✗ ", Style::default().fg(Color::Red))
+                });
+            }
+            title_lines.push(Line::from(spans));
+        }
+        if let Some(best) = app.personal_best {
+            title_lines.push(personal_best_line(&report, best));
+        }
+        let title = Paragraph::new(title_lines).alignment(Alignment::Center);
+        f.render_widget(title, chunks[0]);
+
+        // Render different views based on report_view
+        if app.error_inspector {
+            render_error_inspector(f, chunks[1], &report, app.inspector_scroll);
+        } else if let Some(state) = app.review_index.and_then(|i| session.reconstruct_at(i)) {
+            render_keystroke_review(f, chunks[1], session, &state);
+        } else {
+            match app.report_view {
+                ReportView::Charts => {
+                    render_consolidated_charts_view(f, chunks[1], &report, &app.ui_options)
+                }
+                ReportView::Analysis => {
+                    render_consolidated_analysis_view(f, chunks[1], &report, &app.ui_options)
+                }
+                ReportView::HesitationMap => {
+                    render_hesitation_map_view(f, chunks[1], session, &report)
+                }
+            }
+        }
+
+        // Help
+        let mut help_lines = vec![Line::from(Span::styled(
+            "Left/Right: Switch views  '['/']': Step keystrokes  'e': Export  'a': Anonymized export  'x': Site export  'c': Copy  'r': Retry  'w': Drill weakest spot  'k': Keep weak words  'b': Bad passage  'd': Error diffs  'q': Back",
+            Style::default().fg(Color::DarkGray),
+        ))];
+        if let Some(message) = &app.report_message {
+            help_lines.push(Line::from(Span::styled(
+                message.clone(),
+                Style::default().fg(Color::Yellow),
+            )));
+        }
+        let help = Paragraph::new(help_lines).alignment(Alignment::Center);
+        f.render_widget(help, chunks[2]);
+    }
+}
+
+/// Readable name for a key in charts and lists: whitespace and control
+/// characters would otherwise render as blanks or break the layout
+fn display_label(key: char) -> String {
+    match key {
+        ' ' => "Space".to_string(),
+        '\t' => "Tab".to_string(),
+        '\n' => "Enter".to_string(),
+        c if c.is_control() => format!("U+{:04X}", c as u32),
+        c => c.to_string(),
+    }
+}
+
+/// A digraph with each key labelled, spaced out only when a label is a word
+fn display_digraph(digraph: &str) -> String {
+    let labels: Vec<String> = digraph.chars().map(display_label).collect();
+    if labels.iter().all(|label| label.chars().count() == 1) {
+        labels.concat()
+    } else {
+        labels.join(" ")
+    }
+}
+
+/// "  🔥 5-day streak" once the daily goal has been met at least one day
+fn streak_suffix(streak: u32) -> String {
+    match streak {
+        0 => String::new(),
+        days => format!("  🔥 {}-day streak", days),
+    }
+}
+
+/// "Best: 52.0 WPM ↑ | 97.5% ↓" comparing this run against the personal best
+fn personal_best_line(report: &SessionReport, best: PersonalBest) -> Line<'static> {
+    let arrow = |current: f64, best: f64| {
+        if current > best {
+            Span::styled("↑", Style::default().fg(Color::Green))
+        } else if current < best {
+            Span::styled("↓", Style::default().fg(Color::Red))
+        } else {
+            Span::styled("=", Style::default().fg(Color::Yellow))
+        }
+    };
+
+    let label_style = Style::default().fg(Color::DarkGray);
+    Line::from(vec![
+        Span::styled(format!("Personal best: {:.1} WPM ", best.wpm), label_style),
+        arrow(report.wpm, best.wpm),
+        Span::styled(format!("  |  {:.1}% ", best.accuracy), label_style),
+        arrow(report.accuracy, best.accuracy),
+    ])
+}
+
+fn render_zen_summary(f: &mut Frame, report: &SessionReport) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .margin(2)
+        .constraints([
+            Constraint::Min(1),
+            Constraint::Length(3),
+            Constraint::Min(1),
+            Constraint::Length(3),
+        ])
+        .split(f.area());
+
+    let summary = Paragraph::new(format!(
+        "WPM: {:.1} | Accuracy: {:.1}% | Time: {:.1}s",
+        report.wpm,
+        report.accuracy,
+        report.session_duration.as_secs_f64()
+    ))
+    .alignment(Alignment::Center)
+    .style(
+        Style::default()
+            .fg(Color::Green)
+            .add_modifier(Modifier::BOLD),
+    );
+    f.render_widget(summary, chunks[1]);
+
+    let help = Paragraph::new("'r': Retry  'q': Back")
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(Color::DarkGray));
+    f.render_widget(help, chunks[3]);
+}
+
+/// Report body and guide sidebar; the sidebar gets no room under --quiet
+fn report_columns(
+    area: ratatui::layout::Rect,
+    quiet: bool,
+) -> std::rc::Rc<[ratatui::layout::Rect]> {
+    let sidebar = if quiet { 0 } else { 25 };
+    Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(100 - sidebar),
+            Constraint::Percentage(sidebar),
+        ])
+        .split(area)
+}
+
+/// Bar chart rows for the `top_n` keys with the highest nonzero `count`, highest first
+fn top_keys_by(
+    report: &SessionReport,
+    top_n: usize,
+    count: impl Fn(&KeyStat) -> u32,
+) -> Vec<(String, u64)> {
+    let mut data: Vec<_> = report
+        .key_stats
+        .iter()
+        .filter(|(_, stats)| count(stats) > 0)
+        .map(|(key, stats)| (display_label(*key), count(stats) as u64))
+        .collect();
+    data.sort_by_key(|b| std::cmp::Reverse(b.1));
+    data.truncate(top_n);
+    data
+}
+
+fn render_consolidated_charts_view(
+    f: &mut Frame,
+    area: ratatui::layout::Rect,
+    report: &SessionReport,
+    options: &UiOptions,
+) {
+    let top_n = options.top_n;
+    let main_chunks = report_columns(area, options.quiet);
+
+    let chart_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(25), // Summary stats
+            Constraint::Percentage(35), // Key frequency and errors
+            Constraint::Percentage(40), // Error timeline and hesitation
+        ])
+        .split(main_chunks[0]);
+
+    // Summary stats bar
+    let stats_text = format!(
+        "WPM: {} | Accuracy: {:.1}% | Errors: {} | Duration: {:.1}s | Avg Latency: {}ms",
+        report.wpm_display(),
+        report.accuracy,
+        report.total_errors(),
+        report.session_duration.as_secs_f64(),
+        report.average_latency.as_millis()
+    );
+
+    let stats = Paragraph::new(stats_text)
+        .block(
+            Block::default()
+                .title("Session Summary")
+                .borders(Borders::ALL),
+        )
+        .alignment(Alignment::Center)
+        .style(
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD),
+        );
+    f.render_widget(stats, chart_chunks[0]);
+
+    // Key charts - top row
+    let key_charts = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chart_chunks[1]);
+
+    // Most frequent keys chart
+    let key_data = top_keys_by(report, top_n, |stats| stats.count);
+
+    let key_chart_data: Vec<_> = key_data
+        .iter()
+        .map(|(key, count)| (key.as_str(), *count))
+        .collect();
+
+    let key_chart = BarChart::default()
+        .block(
+            Block::default()
+                .title("Most Used Keys")
+                .borders(Borders::ALL),
+        )
+        .data(&key_chart_data)
+        .bar_width(3)
+        .bar_style(Style::default().fg(Color::Green))
+        .value_style(
+            Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+        );
+    f.render_widget(key_chart, key_charts[0]);
+
+    // Error-prone keys chart
+    let error_data = top_keys_by(report, top_n, |stats| stats.error_count);
+
+    if !error_data.is_empty() {
+        let error_chart_data: Vec<_> = error_data
+            .iter()
+            .map(|(key, count)| (key.as_str(), *count))
+            .collect();
+
+        let error_chart = BarChart::default()
+            .block(
+                Block::default()
+                    .title("Error-Prone Keys")
+                    .borders(Borders::ALL),
+            )
+            .data(&error_chart_data)
+            .bar_width(3)
+            .bar_style(Style::default().fg(Color::Red))
+            .value_style(
+                Style::default()
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            );
+        f.render_widget(error_chart, key_charts[1]);
+    } else {
+        let no_errors = Paragraph::new("No errors! Perfect typing!")
+            .block(
+                Block::default()
+                    .title("Error-Prone Keys")
+                    .borders(Borders::ALL),
+            )
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::Green));
+        f.render_widget(no_errors, key_charts[1]);
+    }
+
+    // Bottom charts row
+    let bottom_charts = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(chart_chunks[2]);
+
+    // Error timeline
+    if !report.errors.is_empty() {
+        let timeline_text = report
+            .errors
+            .iter()
+            .take(10)
+            .enumerate()
+            .map(|(i, error)| {
+                let timestamp = error.timestamp.as_secs_f64();
+                format!(
+                    "{}. {:.1}s: {} '{}' -> '{}'",
+                    i + 1,
+                    timestamp,
+                    match error.error_type {
+                        ErrorType::Substitution => "Sub",
+                        ErrorType::Insertion => "Ins",
+                        ErrorType::Omission => "Omi",
+                        ErrorType::Repeat => "Rep",
+                        ErrorType::Doubling => "Dbl",
+                    },
+                    error.expected_char.map_or("?".to_string(), display_label),
+                    error.actual_char.map_or("?".to_string(), display_label)
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let timeline = Paragraph::new(timeline_text)
+            .block(
+                Block::default()
+                    .title("Error Timeline")
+                    .borders(Borders::ALL),
+            )
+            .wrap(Wrap { trim: true });
+        f.render_widget(timeline, bottom_charts[0]);
+    } else {
+        let no_errors = Paragraph::new("No errors recorded!\nPerfect session!")
+            .block(
+                Block::default()
+                    .title("Error Timeline")
+                    .borders(Borders::ALL),
+            )
+            .alignment(Alignment::Center)
+            .style(Style::default().fg(Color::Green));
+        f.render_widget(no_errors, bottom_charts[0]);
+    }
+
+    // Hesitation visualization
+    let hesitation_text = if report.hesitation_patterns.is_empty() {
+        "No significant hesitations!\nGood rhythm maintained.".to_string()
+    } else {
+        report
+            .hesitation_patterns
+            .iter()
+            .take(8)
+            .map(|h| {
+                format!(
+                    "{}ms at pos {} ({})",
+                    h.duration.as_millis(),
+                    h.position,
+                    match h.pattern_type {
+                        HesitationType::LongPause => "Long Pause",
+                        HesitationType::DoubleDigraph => "Common Combo",
+                        HesitationType::Transition => "Hand Switch",
+                        HesitationType::Punctuation => "Punctuation",
+                        HesitationType::CaseChange => "Case Change",
+                        HesitationType::NumberSymbol => "Number/Symbol",
+                    }
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    };
+
+    let hesitation = Paragraph::new(hesitation_text)
+        .block(
+            Block::default()
+                .title("Hesitation Patterns")
+                .borders(Borders::ALL),
+        )
+        .wrap(Wrap { trim: true });
+    let hesitation_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(3)])
+        .split(bottom_charts[1]);
+    f.render_widget(hesitation, hesitation_chunks[0]);
+
+    // Accuracy at each WPM sample, to show whether it slipped as the session went on
+    let accuracy: Vec<u64> = report
+        .accuracy_over_time
+        .iter()
+        .map(|(_, acc)| acc.round() as u64)
+        .collect();
+    let accuracy_title = match accuracy.iter().min() {
+        Some(lowest) => format!("Accuracy over time (low {}%)", lowest),
+        None => "Accuracy over time".to_string(),
+    };
+    let accuracy_strip = Sparkline::default()
+        .block(Block::default().title(accuracy_title).borders(Borders::ALL))
+        .data(&accuracy)
+        .max(100)
+        .style(Style::default().fg(Color::Cyan));
+    f.render_widget(accuracy_strip, hesitation_chunks[1]);
+
+    if options.quiet {
+        return;
+    }
+
+    // Educational sidebar
+    let education_text = "VISUAL ANALYSIS GUIDE\n\n\
+        📊 WHAT YOU'RE SEEING:\n\
+        • Key Usage: Shows which keys you type most\n\
+        • Error Patterns: Reveals problem keys\n\
+        • Error Timeline: When mistakes occur\n\
+        • Hesitation Points: Where you slow down\n\
+        • Accuracy Strip: Whether you tired\n\n\
+        🎯 HOW THIS HELPS:\n\
+        • Identify weak finger positions\n\
+        • Spot rhythm disruption patterns\n\
+        • Focus practice on problem areas\n\
+        • Track improvement over time\n\n\
+        💡 ACTION ITEMS:\n\
+        • Practice error-prone keys separately\n\
+        • Work on smooth transitions\n\
+        • Build muscle memory for hesitation points\n\
+        • Maintain consistent rhythm\n\n\
+        Switch to 'Detailed Insights' →\n\
+        for specific recommendations";
+
+    let education = Paragraph::new(education_text)
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(Color::DarkGray))
+        .block(Block::default().padding(Padding::uniform(2)));
+    f.render_widget(education, main_chunks[1]);
+}
+
+fn render_hesitation_map_view(
+    f: &mut Frame,
+    area: ratatui::layout::Rect,
+    session: &TypingSession,
+    report: &SessionReport,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(1)])
+        .split(area);
+
+    let legend = Paragraph::new(format!(
+        "Highlighted characters mark where you hesitated ({} pauses)",
+        report.hesitation_patterns.len()
+    ))
+    .alignment(Alignment::Center)
+    .style(Style::default().fg(Color::Magenta));
+    f.render_widget(legend, chunks[0]);
+
+    let positions: Vec<usize> = report
+        .hesitation_patterns
+        .iter()
+        .map(|h| h.position)
+        .collect();
+
+    let passage = Paragraph::new(session.generate_review_text(&positions))
+        .block(Block::default().title("Passage").borders(Borders::ALL))
+        .wrap(Wrap { trim: false });
+    f.render_widget(passage, chunks[1]);
+}
+
+/// A character that stays visible and one column wide in a diff row
+fn diff_char(c: char) -> char {
+    match c {
+        ' ' => '␣',
+        '\n' => '⏎',
+        '\t' => '→',
+        c if c.is_control() => '�',
+        c => c,
+    }
+}
+
+/// The expected text around an error above what was typed, aligned so the
+/// differing character sits in the same column. A missing character (an
+/// omission, or the target side of an insertion) shows as '·'.
+fn error_diff_lines(error: &ErrorEvent) -> [Line<'static>; 2] {
+    let context = |text: &str| text.chars().map(diff_char).collect::<String>();
+    let cell = |c: Option<char>| c.map_or('·', diff_char).to_string();
+    let before = context(&error.context_before);
+
+    let expected = Line::from(vec![
+        Span::styled("expected  ", Style::default().fg(Color::DarkGray)),
+        Span::raw(before.clone()),
+        Span::styled(
+            cell(error.expected_char),
+            Style::default()
+                .fg(Color::Green)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        ),
+        Span::raw(context(&error.context_after)),
+    ]);
+    let typed = Line::from(vec![
+        Span::styled("typed     ", Style::default().fg(Color::DarkGray)),
+        Span::styled(before, Style::default().fg(Color::DarkGray)),
+        Span::styled(
+            cell(error.actual_char),
+            Style::default()
+                .fg(Color::Red)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+        ),
+    ]);
+    [expected, typed]
+}
+
+/// Every error as a small expected/typed diff, in the order they were made,
+/// starting from error number `scroll`
+fn render_error_inspector(
+    f: &mut Frame,
+    area: ratatui::layout::Rect,
+    report: &SessionReport,
+    scroll: usize,
+) {
+    let mut lines = Vec::new();
+    let mut first_line = 0;
+    for (i, error) in report.errors.iter().enumerate() {
+        if i == scroll {
+            first_line = lines.len();
+        }
+        lines.push(Line::from(Span::styled(
+            format!(
+                "#{}  {:.1}s  {:?} at {}",
+                i + 1,
+                error.timestamp.as_secs_f64(),
+                error.error_type,
+                error.position
+            ),
+            Style::default().fg(Color::Yellow),
+        )));
+        lines.extend(error_diff_lines(error));
+        lines.push(Line::from(""));
+    }
+    if lines.is_empty() {
+        lines.push(Line::from("No errors recorded!"));
+    }
+
+    let inspector = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title(format!(
+                    "Error Inspector ({} errors) - Up/Down to scroll, 'd' to close",
+                    report.errors.len()
+                ))
+                .borders(Borders::ALL),
+        )
+        .scroll((first_line as u16, 0));
+    f.render_widget(inspector, area);
+}
+
+fn render_keystroke_review(
+    f: &mut Frame,
+    area: ratatui::layout::Rect,
+    session: &TypingSession,
+    state: &KeystrokeState,
+) {
+    let chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Length(1), Constraint::Min(1)])
+        .split(area);
+
+    let correct = state.expected_char == Some(state.typed_char);
+    let summary = format!(
+        "Keystroke {}/{}  |  {:.2}s  |  WPM: {:.1}  |  typed {:?}{}",
+        state.index + 1,
+        session.typing_rhythm.len(),
+        state.elapsed.as_secs_f64(),
+        state.wpm,
+        state.typed_char,
+        match state.expected_char {
+            Some(expected) if !correct => format!(" (expected {:?})", expected),
+            _ => String::new(),
+        }
+    );
+    let header = Paragraph::new(summary)
+        .alignment(Alignment::Center)
+        .style(Style::default().fg(if correct { Color::Green } else { Color::Red }));
+    f.render_widget(header, chunks[0]);
+
+    // Typed prefix, the keystroke under review, then the rest of the target
+    let mut lines = Vec::new();
+    let mut spans = Vec::new();
+    let typed_style = if correct {
+        Style::default().fg(Color::Green)
+    } else {
+        Style::default().bg(Color::Red).fg(Color::White)
+    };
+    let remaining: String = session
+        .target_text
+        .chars()
+        .skip(state.correct_prefix.chars().count() + usize::from(correct))
+        .collect();
+
+    for (text, style) in [
+        (
+            state.correct_prefix.as_str(),
+            Style::default().fg(Color::Green),
+        ),
+        (
+            &state.typed_char.to_string(),
+            typed_style.add_modifier(Modifier::UNDERLINED | Modifier::BOLD),
+        ),
+        (remaining.as_str(), Style::default().fg(Color::DarkGray)),
+    ] {
+        for (i, segment) in text.split('\n').enumerate() {
+            if i > 0 {
+                lines.push(Line::from(std::mem::take(&mut spans)));
+            }
+            spans.push(Span::styled(segment.replace('\t', "    "), style));
+        }
+    }
+    lines.push(Line::from(spans));
+
+    let passage = Paragraph::new(lines)
+        .block(
+            Block::default()
+                .title("Keystroke Review")
+                .borders(Borders::ALL),
+        )
+        .wrap(Wrap { trim: false });
+    f.render_widget(passage, chunks[1]);
+}
+
+/// Error counts in `bins` equal slices of a passage `len` characters long
+fn error_density(errors: &[ErrorEvent], len: usize, bins: usize) -> Vec<u64> {
+    let mut density = vec![0; bins];
+    if bins == 0 || len == 0 {
+        return density;
+    }
+    for error in errors {
+        let bin = (error.position * bins / len).min(bins - 1);
+        density[bin] += 1;
+    }
+    density
+}
+
+fn render_consolidated_analysis_view(
+    f: &mut Frame,
+    area: ratatui::layout::Rect,
+    report: &SessionReport,
+    options: &UiOptions,
+) {
+    let top_n = options.top_n;
+    let main_chunks = report_columns(area, options.quiet);
+
+    // Under --quiet the bottom row only holds the slowest lines, if any
+    // Multi-line text breaks down by line, a prose paragraph by sentence
+    let by_line = report.line_times.len() > 1;
+    let by_sentence = !by_line && report.sentence_stats.len() > 1;
+    let plan_row = if options.quiet && !by_line && !by_sentence {
+        0
+    } else {
+        30
+    };
+    let analysis_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([
+            Constraint::Percentage(20),                       // Key stats
+            Constraint::Percentage(25 + (30 - plan_row) / 2), // Slowest digraphs and finger errors
+            Constraint::Percentage(25 + (30 - plan_row) / 2), // Weakness summary and rhythm
+            Constraint::Percentage(plan_row),                 // Detailed recommendations
+        ])
+        .split(main_chunks[0]);
+
+    // Key performance metrics (average_latency already has outliers trimmed when asked)
+    let avg_latency = report.average_latency.as_millis();
+
+    let metrics_text = format!(
+        "PERFORMANCE METRICS\n\
+         • Speed: {} WPM (Target: 40+ WPM)\n\
+         • Raw / Gross / Net: {:.1} / {:.1} / {:.1} WPM\n\
+         • Accuracy: {:.1}% (Target: 95%+)\n\
+         • Consistency: {}ms avg latency\n\
+         • Error Rate: {:.2}% (Target: <2%)\n\
+         • Rhythm Stability: {} breaks detected\n\
+         • Double Letters: {} doubling errors",
+        report.wpm_display(),
+        report.raw_wpm,
+        report.gross_wpm(),
+        report.wpm,
+        report.accuracy,
+        avg_latency,
+        (report.total_errors() as f64 / report.total_characters as f64) * 100.0,
+        report.weakness_analysis.rhythm_breaks.len(),
+        report.doubling_errors
+    );
+    let metrics_text = match &report.content_wpm {
+        Some(content) => format!(
+            "{}\n• Code / Comments: {:.1} / {:.1} WPM",
+            metrics_text, content.code, content.comment
+        ),
+        None => metrics_text,
+    };
+
+    let metrics_row = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([
+            Constraint::Percentage(45),
+            Constraint::Percentage(30),
+            Constraint::Percentage(25),
+        ])
+        .split(analysis_chunks[0]);
+
+    let metrics = Paragraph::new(metrics_text)
+        .block(
+            Block::default()
+                .title("📈 Performance Overview")
+                .borders(Borders::ALL),
+        )
+        .style(Style::default().fg(Color::Green));
+    f.render_widget(metrics, metrics_row[0]);
+
+    // Number row and symbol accuracy
+    let symbol_stats = report.symbol_stats();
+    let describe_group =
+        |name: &str, group: &KeyGroupStats| match (group.accuracy(), group.average_latency()) {
+            (Some(accuracy), Some(latency)) => format!(
+                "• {}: {:.1}% accuracy, {}ms avg ({} keys)\n",
+                name,
+                accuracy,
+                latency.as_millis(),
+                group.keystrokes
+            ),
+            _ => format!("• {}: not practiced\n", name),
+        };
+    let mut symbol_text = format!(
+        "NUMBER ROW & SYMBOLS\n{}{}",
+        describe_group("Digits", &symbol_stats.digits),
+        describe_group("!@#$%^&*()", &symbol_stats.symbols)
+    );
+    let missing = report.coverage.missing();
+    if !missing.is_empty() {
+        let missing: String = missing.into_iter().collect();
+        symbol_text.push_str(&format!("• Never typed: {}\n", missing));
+    }
+
+    let symbols = Paragraph::new(symbol_text)
+        .block(
+            Block::default()
+                .title("🔢 Numbers & Symbols")
+                .borders(Borders::ALL),
+        )
+        .wrap(Wrap { trim: true });
+    f.render_widget(symbols, metrics_row[1]);
+
+    // Keys with the lowest hit rate, among those typed often enough to tell
+    let mut key_accuracy: Vec<(char, f64, &KeyStat)> = report
+        .key_stats
+        .iter()
+        .filter(|(_, stat)| stat.count >= KEY_ACCURACY_MIN_COUNT && stat.error_count > 0)
+        .filter_map(|(key, stat)| Some((*key, stat.accuracy()?, stat)))
+        .collect();
+    key_accuracy.sort_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal));
+    let accuracy_text = if key_accuracy.is_empty() {
+        format!(
+            "✅ No key below 100%\n(keys typed {}+ times)",
+            KEY_ACCURACY_MIN_COUNT
+        )
+    } else {
+        key_accuracy
+            .iter()
+            .take(top_n)
+            .map(|(key, accuracy, stat)| {
+                format!(
+                    "• {}: {:.0}% ({}/{})\n",
+                    display_label(*key),
+                    accuracy,
+                    stat.count.saturating_sub(stat.error_count),
+                    stat.count
+                )
+            })
+            .collect()
+    };
+    let least_accurate = Paragraph::new(accuracy_text)
+        .block(
+            Block::default()
+                .title("🎯 Least Accurate Keys")
+                .borders(Borders::ALL),
+        )
+        .style(Style::default().fg(Color::Yellow));
+    f.render_widget(least_accurate, metrics_row[2]);
+
+    // Weakness analysis - top row
+    let weakness_top = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(analysis_chunks[1]);
+
+    // Slowest digraphs
+    let digraph_text = if report.weakness_analysis.slowest_digraphs.is_empty() {
+        "✅ No problematic letter combinations found!\nAll transitions are smooth.".to_string()
+    } else {
+        let mut text = "⚠️  SLOW LETTER COMBINATIONS:\n".to_string();
+        for (digraph, avg_ms) in report.weakness_analysis.slowest_digraphs.iter().take(top_n) {
+            text.push_str(&format!(
+                "• '{}': {:.0}ms\n",
+                display_digraph(digraph),
+                avg_ms
+            ));
+        }
+        text.push_str("\nFocus practice on these pairs!");
+        text
+    };
+
+    let digraphs = Paragraph::new(digraph_text)
+        .block(
+            Block::default()
+                .title("🔤 Letter Combinations")
+                .borders(Borders::ALL),
+        )
+        .wrap(Wrap { trim: true });
+    f.render_widget(digraphs, weakness_top[0]);
+
+    // Finger positioning errors
+    let finger_text = if report.weakness_analysis.finger_errors.is_empty() {
+        "✅ Perfect finger positioning!\n\nNo cross-finger errors detected.\n\n\n".to_string()
+    } else {
+        let mut text = "⚠️  FINGER POSITIONING ERRORS:\n".to_string();
+        let errors: Vec<_> = report
+            .weakness_analysis
+            .finger_errors
+            .iter()
+            .take(5)
+            .collect();
+        for (pattern, count) in &errors {
+            text.push_str(&format!("• {}: {} times\n", pattern, count));
+        }
+        // Add padding lines to maintain consistent height
+        for _ in errors.len()..5 {
+            text.push('\n');
+        }
+        text.push_str("Practice proper finger placement!");
+        text
+    };
+
+    let finger_text = match &report.weakness_analysis.hand_balance {
+        Some(balance) => format!(
+            "{}\n✋ Hands: L {:.0}% {:.0}ms | R {:.0}% {:.0}ms{}",
+            finger_text,
+            balance.left_share * 100.0,
+            balance.left_avg_latency_ms,
+            balance.right_share * 100.0,
+            balance.right_avg_latency_ms,
+            balance
+                .imbalance()
+                .map(|imbalance| format!(" ({})", imbalance))
+                .unwrap_or_default()
+        ),
+        None => finger_text,
+    };
+
+    let travel = &report.weakness_analysis.finger_travel;
+    let finger_text = format!(
+        "{}\n📏 Travel: {:.1}m (L {:.1}m | R {:.1}m)",
+        finger_text,
+        FingerTravel::to_meters(travel.total()),
+        FingerTravel::to_meters(travel.left),
+        FingerTravel::to_meters(travel.right)
+    );
+
+    let fingers = Paragraph::new(finger_text)
+        .block(
+            Block::default()
+                .title("👆 Finger Analysis")
+                .borders(Borders::ALL),
+        )
+        .wrap(Wrap { trim: true });
+    f.render_widget(fingers, weakness_top[1]);
+
+    // Analysis middle row
+    let weakness_mid = Layout::default()
+        .direction(Direction::Horizontal)
+        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
+        .split(analysis_chunks[2]);
+
+    // Error clustering
+    let cluster_text = if report.weakness_analysis.error_clusters.is_empty() {
+        "✅ No error clustering detected!\nMistakes are well-distributed.".to_string()
+    } else {
+        let mut text = "⚠️  ERROR CLUSTERS FOUND:\n".to_string();
+        for (start, end) in &report.weakness_analysis.error_clusters {
+            text.push_str(&format!(
+                "• Positions {}-{} ({} chars)\n",
+                start,
+                end,
+                end - start + 1
+            ));
+        }
+        text.push_str("\nThese sections need extra practice!");
+        text
+    };
+
+    let clusters = Paragraph::new(cluster_text)
+        .block(
+            Block::default()
+                .title("🎯 Error Hotspots")
+                .borders(Borders::ALL),
+        )
+        .wrap(Wrap { trim: true });
+    let hotspot_chunks = Layout::default()
+        .direction(Direction::Vertical)
+        .constraints([Constraint::Min(0), Constraint::Length(3)])
+        .split(weakness_mid[0]);
+    f.render_widget(clusters, hotspot_chunks[0]);
+
+    // Where in the passage errors fell, start on the left and end on the right
+    let passage_len = report
+        .target_text
+        .chars()
+        .count()
+        .max(report.total_characters);
+    let bins = hotspot_chunks[1].width.saturating_sub(2) as usize;
+    let density = error_density(&report.errors, passage_len, bins);
+    let strip = Sparkline::default()
+        .block(
+            Block::default()
+                .title("Errors along the passage")
+                .borders(Borders::ALL),
+        )
+        .data(&density)
+        .style(Style::default().fg(Color::Red));
+    f.render_widget(strip, hotspot_chunks[1]);
+
+    // Rhythm analysis
+    let rhythm_text = if report.weakness_analysis.rhythm_breaks.is_empty() {
+        "✅ Excellent rhythm consistency!\n\nSteady typing pace maintained.\n\n\n".to_string()
+    } else {
+        let break_count = report.weakness_analysis.rhythm_breaks.len();
+        let positions = if break_count <= 8 {
+            report
+                .weakness_analysis
+                .rhythm_breaks
+                .iter()
+                .map(|pos| pos.to_string())
+                .collect::<Vec<_>>()
+                .join(", ")
+        } else {
+            format!(
+                "{} (and {} more)",
+                report
+                    .weakness_analysis
+                    .rhythm_breaks
+                    .iter()
+                    .take(6)
+                    .map(|pos| pos.to_string())
+                    .collect::<Vec<_>>()
+                    .join(", "),
+                break_count - 6
+            )
+        };
+
+        format!(
+            "⚠️  RHYTHM DISRUPTIONS:\n\
+                 • {} sudden slowdowns detected\n\
+                 • Positions: {}\n\n\
+                 Work on maintaining steady pace!",
+            break_count, positions
+        )
+    };
+
+    let rhythm = Paragraph::new(rhythm_text)
+        .block(
+            Block::default()
+                .title("⏱️  Rhythm Analysis")
+                .borders(Borders::ALL),
+        )
+        .wrap(Wrap { trim: true });
+    f.render_widget(rhythm, weakness_mid[1]);
+
+    // Detailed recommendations
+    let mut recommendations = "🎯 PERSONALIZED IMPROVEMENT PLAN:\n\n".to_string();
+
+    if report.wpm < 30.0 {
+        recommendations
+            .push_str("🐌 SPEED: Focus on accuracy first, then gradually increase pace\n");
+    } else if report.wpm < 50.0 {
+        recommendations.push_str("🚶 SPEED: Good foundation! Work on consistent 40+ WPM\n");
+    } else {
+        recommendations
+            .push_str("🏃 SPEED: Excellent! Maintain this pace while improving accuracy\n");
+    }
+
+    if report.accuracy < 90.0 {
+        recommendations.push_str("❌ ACCURACY: Slow down and focus on correct keystrokes\n");
+    } else if report.accuracy < 97.0 {
+        recommendations.push_str("✓ ACCURACY: Good! Aim for 97%+ accuracy\n");
+    } else {
+        recommendations.push_str("🎯 ACCURACY: Excellent precision! Keep it up!\n");
+    }
+
+    if !report.weakness_analysis.slowest_digraphs.is_empty() {
+        recommendations.push_str("🔤 PRACTICE: Drill slow letter combinations separately\n");
+    }
+
+    if !report.weakness_analysis.finger_errors.is_empty() {
+        recommendations.push_str("👆 FINGERS: Review proper finger positioning\n");
+    }
+
+    if report.weakness_analysis.rhythm_breaks.len() > 3 {
+        recommendations.push_str("⏱️  RHYTHM: Practice with metronome for consistency\n");
+    }
+
+    recommendations.push_str(
+        "\n💡 NEXT STEPS:\n\
+                              1. Practice identified weak areas daily\n\
+                              2. Use typing games for problem keys\n\
+                              3. Maintain proper posture and hand position\n\
+                              4. Take breaks to avoid fatigue",
+    );
+
+    let rec_widget = Paragraph::new(recommendations)
+        .block(
+            Block::default()
+                .title("📋 Action Plan")
+                .borders(Borders::ALL),
+        )
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(Color::Yellow));
+
+    if by_line || by_sentence {
+        // Under --quiet the slowest lines or hardest sentence take the whole row
+        let plan_share = if options.quiet { 0 } else { 60 };
+        let plan_chunks = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints([
+                Constraint::Percentage(plan_share),
+                Constraint::Percentage(100 - plan_share),
+            ])
+            .split(analysis_chunks[3]);
+        if !options.quiet {
+            f.render_widget(rec_widget, plan_chunks[0]);
+        }
+
+        let (title, text) = if by_line {
+            let mut slowest = report.line_times.clone();
+            slowest.sort_by_key(|&(_, duration)| std::cmp::Reverse(duration));
+            let lines: Vec<&str> = report.target_text.lines().collect();
+            let mut text = String::new();
+            for (line, duration) in slowest.iter().take(3) {
+                text.push_str(&format!(
+                    "Line {} ({:.1}s):\n  {}\n",
+                    line,
+                    duration.as_secs_f64(),
+                    lines.get(line - 1).map_or("", |l| l.trim())
+                ));
+            }
+            ("🐢 Slowest Lines", text)
+        } else {
+            let mut text = String::new();
+            if let Some(hardest) = report.hardest_sentence() {
+                let sentence: String = report
+                    .target_text
+                    .chars()
+                    .skip(hardest.start)
+                    .take(hardest.end - hardest.start)
+                    .collect();
+                text.push_str(&format!(
+                    "{} errors at {:.1} WPM:\n  {}\n\n",
+                    hardest.errors, hardest.wpm, sentence
+                ));
+            }
+            let speeds = report.sentence_stats.iter().map(|s| s.wpm);
+            text.push_str(&format!(
+                "{} sentences, {:.1}-{:.1} WPM",
+                report.sentence_stats.len(),
+                speeds.clone().fold(f64::INFINITY, f64::min),
+                speeds.fold(0.0, f64::max)
+            ));
+            ("✍️ Hardest Sentence", text)
+        };
+        let slow_parts = Paragraph::new(text)
+            .block(Block::default().title(title).borders(Borders::ALL))
+            .wrap(Wrap { trim: false });
+        f.render_widget(slow_parts, plan_chunks[1]);
+    } else if !options.quiet {
+        f.render_widget(rec_widget, analysis_chunks[3]);
+    }
+
+    if options.quiet {
+        return;
+    }
+
+    // Educational sidebar
+    let education_text = "DETAILED INSIGHTS GUIDE\n\n\
+        🔍 UNDERSTANDING THE DATA:\n\
+        • Performance Metrics: Core stats vs targets\n\
+        • Letter Combinations: Transition speed analysis\n\
+        • Finger Analysis: Hand positioning accuracy\n\
+        • Error Hotspots: Problem text sections\n\
+        • Rhythm Analysis: Pace consistency check\n\n\
+        📈 SKILL DEVELOPMENT:\n\
+        • Focus on one weakness at a time\n\
+        • Use targeted practice exercises\n\
+        • Track progress over multiple sessions\n\
+        • Build muscle memory gradually\n\n\
+        🏆 IMPROVEMENT STRATEGY:\n\
+        • Accuracy before speed\n\
+        • Consistent practice beats marathon sessions\n\
+        • Learn from each mistake\n\
+        • Celebrate small improvements\n\n\
+        ← Switch to 'Visual Analysis'\n\
+        for charts and graphs";
+
+    let education = Paragraph::new(education_text)
+        .wrap(Wrap { trim: true })
+        .style(Style::default().fg(Color::DarkGray))
+        .block(Block::default().padding(Padding::uniform(2)));
+    f.render_widget(education, main_chunks[1]);
+}
+
+/// Capture the mouse and, unless --no-alt-screen asked to keep the session in
+/// the scrollback, switch to the alternate screen
+fn enter_screen(out: &mut impl io::Write, alt_screen: bool) -> io::Result<()> {
+    if alt_screen {
+        execute!(out, EnterAlternateScreen, EnableMouseCapture)
+    } else {
+        execute!(out, EnableMouseCapture)
+    }
+}
+
+/// Undo enter_screen with the same `alt_screen`
+fn leave_screen(out: &mut impl io::Write, alt_screen: bool) -> io::Result<()> {
+    if alt_screen {
+        execute!(out, LeaveAlternateScreen, DisableMouseCapture)
+    } else {
+        execute!(out, DisableMouseCapture)
+    }
+}
+
+/// Redraw rate while something on screen moves on its own
+const FRAME_INTERVAL: Duration = Duration::from_millis(50);
+/// Wake-up rate otherwise; key presses still wake the loop immediately
+const IDLE_FRAME_INTERVAL: Duration = Duration::from_millis(500);
+
+/// How long the main loop may wait for input before redrawing. Only a running
+/// session with a live header, pace marker, ghost or keystroke flash changes
+/// without input, so everything else can sleep longer.
+fn frame_interval(app: &App) -> Duration {
+    let animating = app.state == AppState::Typing
+        && app.session.as_ref().is_some_and(|session| {
+            let running = session.last_keystroke.is_some()
+                && !session.is_paused()
+                && session.session_end.is_none();
+            let config = &session.config;
+            running
+                && (app.ui_options.live_header
+                    || app.ghost.is_some()
+                    || config.target_wpm.is_some()
+                    || config.ramp.is_some()
+                    || config.feedback)
+        });
+    if animating {
+        FRAME_INTERVAL
+    } else {
+        IDLE_FRAME_INTERVAL
+    }
+}
+
+/// Handle `first`, then every event already queued behind it (fast typing,
+/// a paste) before the next draw, instead of one key per frame. `pending`
+/// returns the next queued event without waiting, or None once there is none.
+fn drain_events(
+    app: &mut App,
+    first: Event,
+    mut pending: impl FnMut() -> io::Result<Option<Event>>,
+) -> io::Result<()> {
+    app.handle_event(first);
+    while !app.should_quit
+        && let Some(event) = pending()?
+    {
+        app.handle_event(event);
+    }
+    Ok(())
+}
+
+/// Length of the synthetic passage replayed by --bench
+const BENCH_CHARS: usize = 20_000;
+
+/// A deterministic keystroke log for `text`: varying latencies, a typo fixed
+/// with Backspace every 37 characters and a long pause every 400
+fn bench_keystroke_log(text: &str) -> Vec<(char, Duration)> {
+    let mut log = Vec::new();
+    let mut at = Duration::ZERO;
+    for (i, c) in text.chars().enumerate() {
+        at += Duration::from_millis(80 + (i * 53 % 140) as u64);
+        if i % 400 == 399 {
+            at += Duration::from_millis(1500);
+        }
+        if i % 37 == 36 {
+            log.push((if c == 'x' { 'z' } else { 'x' }, at));
+            at += Duration::from_millis(150);
+            log.push(('\x08', at));
+            at += Duration::from_millis(120);
+        }
+        log.push((c, at));
+    }
+    log
+}
+
+/// Time replaying and reporting a synthetic session of `chars` characters,
+/// without touching the terminal
+fn run_bench(iterations: u32, chars: usize, config: &SessionConfig) {
+    let source = "The quick brown fox jumps over the lazy dog, then (quietly) naps; \
+                  fn main() { let x = [1, 2, 3]; }\n";
+    let text: String = source.chars().cycle().take(chars).collect();
+    let log = bench_keystroke_log(&text);
+
+    let mut replay = Duration::ZERO;
+    let mut report = Duration::ZERO;
+    for _ in 0..iterations {
+        let start = Instant::now();
+        let session = TypingSession::from_keystroke_log(text.clone(), &log, config.clone());
+        replay += start.elapsed();
+
+        let start = Instant::now();
+        std::hint::black_box(session.generate_report());
+        report += start.elapsed();
+    }
+
+    println!(
+        "{} chars, {} keystrokes, {} iterations",
+        chars,
+        log.len(),
+        iterations
+    );
+    println!(
+        "replay: {:>8.2} ms/iter",
+        replay.as_secs_f64() * 1000.0 / iterations as f64
+    );
+    println!(
+        "report: {:>8.2} ms/iter",
+        report.as_secs_f64() * 1000.0 / iterations as f64
+    );
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let matches = Cli::command().get_matches();
+    let mut cli = Cli::from_arg_matches(&matches).unwrap_or_else(|e| e.exit());
+    let preferences = (!cli.no_preferences).then(Preferences::load_or_default);
+    if let Some(preferences) = &preferences {
+        preferences.apply(&mut cli, &matches);
+    }
+
+    if cli.list_layouts {
+        list_layouts(&mut io::stdout())?;
+        return Ok(());
+    }
+
+    let snippet_options = SnippetOptions {
+        size: cli.size,
+        chars: cli.chars,
+        lines: cli.lines,
+        keep_headers: cli.keep_headers,
+        transform: cli.transform,
+        seed: cli.seed,
+        preserve_whitespace: cli.preserve_whitespace,
+        control_chars: cli.control_chars,
+        ascii_fold: cli.ascii_fold,
+        lowercase: cli.lowercase,
+        repeat: cli.repeat,
+        excluded: excluded_snippets_path()
+            .and_then(|path| load_excluded_snippets(&path).ok())
+            .unwrap_or_default(),
+    };
+
+    let ghost = cli.ghost.as_deref().map(|path| {
+        fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| {
+                serde_json::from_str::<SessionReport>(&json).map_err(|e| e.to_string())
+            })
+            .and_then(|report| Ghost::from_report(&report))
+            .unwrap_or_else(|e| {
+                eprintln!("Error: cannot race ghost {}: {}", path.display(), e);
+                std::process::exit(1);
+            })
+    });
+
+    let finger_map = cli.finger_map.as_deref().map(|path| {
+        load_finger_map(path).unwrap_or_else(|e| {
+            eprintln!("Error: {}", e);
+            std::process::exit(1);
+        })
+    });
+
+    if let Some(path) = &cli.telemetry
+        && let Err(e) = Telemetry::open(path)
+    {
+        eprintln!(
+            "Error: cannot open telemetry file {}: {}",
+            path.display(),
+            e
+        );
+        std::process::exit(1);
+    }
+
+    let session_config = SessionConfig {
+        zen: cli.zen,
+        max_samples: cli.max_samples,
+        finger_map,
+        soft_cap: cli.soft_cap,
+        instant: cli.instant,
+        freeze_recovery: cli.freeze_recovery,
+        feedback: cli.feedback,
+        sudden_death: cli.sudden_death,
+        paste_guard: cli.paste_guard,
+        split_wpm: cli.split_wpm,
+        cursor_char: cli.cursor_char,
+        break_ligatures: cli.break_ligatures,
+        mask_char: cli.mask_char,
+        target_wpm: cli.target_wpm,
+        hesitation_ms: cli.hesitation_ms,
+        ramp: cli.ramp,
+        until_wpm: cli.until_wpm,
+        telemetry: cli.telemetry.clone(),
+        sample_interval: cli.sample_interval_ms.map(Duration::from_millis),
+        trim_outliers: cli.trim_outliers,
+        blind: cli.blind,
+        label: cli.label.clone(),
+        require_clean: cli.require_clean,
+        layout: cli.layout,
+    };
+    if let Some(iterations) = cli.bench {
+        let config = SessionConfig {
+            telemetry: None,
+            ..session_config
+        };
+        run_bench(iterations, BENCH_CHARS, &config);
+        return Ok(());
+    }
+
+    if let Some(path) = &cli.reanalyze {
+        let report: SessionReport = fs::read_to_string(path)
+            .map_err(|e| e.to_string())
+            .and_then(|json| serde_json::from_str(&json).map_err(|e| e.to_string()))
+            .unwrap_or_else(|e| {
+                eprintln!("Error: cannot read report {}: {}", path.display(), e);
+                std::process::exit(1);
+            });
+        if report.keystroke_log.is_empty() || report.target_text.is_empty() {
+            eprintln!(
+                "Error: {} has no keystroke log to replay (exported by an older version?)",
+                path.display()
+            );
+            std::process::exit(1);
+        }
+
+        let config = SessionConfig {
+            telemetry: None,
+            ..session_config
+        };
+        let mut session =
+            TypingSession::from_keystroke_log(report.target_text, &report.keystroke_log, config);
+        session.source_meta = report.source_meta;
+        session.started_at = report.started_at;
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&session.generate_report())?
+        );
+        return Ok(());
+    }
+
+    // Determine the text source based on CLI arguments. Only done now that
+    // the headless modes are out of the way, as stdin may be read to the end
+    // Keys are still read from the controlling terminal when stdin is a pipe
+    let stdin_is_terminal = std::io::IsTerminal::is_terminal(&io::stdin());
+    let text_source = match SourceChoice::from_cli(&cli, stdin_is_terminal) {
+        SourceChoice::Browse => None,
+        SourceChoice::Inception => Some(TextSource::load_inception(&snippet_options)?),
+        SourceChoice::Quotes => Some(TextSource::load_quotes(&snippet_options)),
+        SourceChoice::Symbols => Some(TextSource::load_symbols(&snippet_options)),
+        SourceChoice::Calibrate => Some(TextSource::load_calibration()),
+        SourceChoice::Docs(krate) => Some(
+            TextSource::load_docs(krate, &snippet_options).unwrap_or_else(|e| {
+                eprintln!("Error: cannot read docs of {}: {}", krate, e);
+                std::process::exit(1);
+            }),
+        ),
+        SourceChoice::Drill(path) => Some(
+            TextSource::load_report_drill(path, &snippet_options).unwrap_or_else(|e| {
+                eprintln!("Error: cannot drill {}: {}", path.display(), e);
+                std::process::exit(1);
+            }),
+        ),
+        SourceChoice::Man(page) => Some(
+            TextSource::load_man_page(page, &snippet_options).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }),
+        ),
+        SourceChoice::Ghost => ghost.as_ref().map(|ghost| {
+            TextSource::File(
+                "ghost race".to_string(),
+                ghost.target_text.clone(),
+                None,
+                None,
+            )
+        }),
+        SourceChoice::File(file_path) => Some(
+            TextSource::load_from_file(file_path, &snippet_options).unwrap_or_else(|e| {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }),
+        ),
+        SourceChoice::Stdin => Some(
+            TextSource::load_from_stdin(&snippet_options).unwrap_or_else(|e| {
+                eprintln!("Error: cannot read stdin: {}", e);
+                std::process::exit(1);
+            }),
+        ),
+    };
+
+    if let Some((name, content)) = text_source.as_ref().and_then(TextSource::get_content)
+        && !has_visible_chars(&content)
+    {
+        eprintln!("Error: {} has no text to type (only whitespace)", name);
+        std::process::exit(1);
+    }
+
+    if cli.print {
+        let Some(text_source) = &text_source else {
+            eprintln!(
+                "Error: --print needs a text source (--file, --inception, --quotes, --man, --symbols or --docs)"
+            );
+            std::process::exit(1);
+        };
+        if let Some((name, content)) = text_source.get_content() {
+            match text_source.snippet_score() {
+                Some(score) => println!("# {} (score: {:.1})", name, score),
+                None => println!("# {}", name),
+            }
+            println!("{}", content);
+        }
+        return Ok(());
+    }
+
+    let ui_options = UiOptions {
+        live_header: cli.live_header,
+        top_n: cli.top_n,
+        save_on_quit: cli.save_on_quit,
+        sparkline: cli.sparkline,
+        idle_timeout: (cli.idle_timeout > 0).then(|| Duration::from_secs(cli.idle_timeout)),
+        warmup: cli.warmup,
+        hold_on_complete: cli.hold_on_complete,
+        quiet: cli.quiet,
+        daily_goal: preferences.as_ref().and_then(|p| p.daily_goal),
+        ruler: cli.ruler,
+        pomodoro: cli
+            .pomodoro
+            .map(|minutes| Duration::from_secs(minutes * 60)),
+        calibrate: cli.calibrate,
+        monochrome: colors_disabled(),
+    };
+    let history = if cli.no_history {
+        None
+    } else {
+        History::default_path().and_then(|path| History::load(path).ok())
+    };
+    let mut app = App::new(
+        text_source,
+        snippet_options,
+        session_config,
+        ui_options,
+        history,
+        ghost,
+    )?;
+
+    // Raw mode is always toggled; only the alternate screen depends on the flag
+    enable_raw_mode()?;
+    let mut stdout = io::stdout();
+    enter_screen(&mut stdout, !cli.no_alt_screen)?;
+    let backend = CrosstermBackend::new(stdout);
+    let mut terminal = Terminal::new(backend)?;
+
+    loop {
+        terminal.draw(|f| {
+            match app.state {
+                AppState::Browsing => ui_browser(f, &app),
+                AppState::Typing | AppState::Completed => ui_typing(f, &app),
+                AppState::ShowingReport => ui_report(f, &app),
+                AppState::Break => ui_break(f, &app),
+            }
+            if app.ui_options.monochrome {
+                decolorize(f.buffer_mut());
+            }
+        })?;
+
+        if event::poll(frame_interval(&app))? {
+            drain_events(&mut app, event::read()?, || {
+                Ok(if event::poll(Duration::ZERO)? {
+                    Some(event::read()?)
+                } else {
+                    None
+                })
+            })?;
+        }
+        app.check_idle();
+        app.check_pomodoro(Instant::now());
+
+        if app.should_quit {
+            break;
+        }
+    }
+
+    disable_raw_mode()?;
+    leave_screen(terminal.backend_mut(), !cli.no_alt_screen)?;
+    terminal.show_cursor()?;
+
+    if app.ui_options.pomodoro.is_some() {
+        println!(
+            "Focused for {} min over {} completed interval(s)",
+            app.focused_time().as_secs() / 60,
+            app.pomodoro.intervals
+        );
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Replay `keys` typed `step_ms` apart, the first one at time zero
+    fn replay(text: &str, keys: &str, step_ms: u64, config: SessionConfig) -> TypingSession {
+        let log: Vec<(char, Duration)> = keys
+            .chars()
+            .enumerate()
+            .map(|(i, key)| (key, Duration::from_millis(i as u64 * step_ms)))
+            .collect();
+        TypingSession::from_keystroke_log(text.to_string(), &log, config)
+    }
+
+    fn error_at(position: usize) -> ErrorEvent {
+        ErrorEvent {
+            error_type: ErrorType::Substitution,
+            position,
+            expected_char: Some('a'),
+            actual_char: Some('b'),
+            timestamp: Duration::ZERO,
+            correction_timestamp: None,
+            correction_latency: None,
+            context_before: String::new(),
+            context_after: String::new(),
+        }
+    }
+
+    fn snippet_options() -> SnippetOptions {
+        SnippetOptions {
+            size: ChunkSize::Small,
+            chars: None,
+            lines: None,
+            keep_headers: false,
+            transform: None,
+            seed: Some(7),
+            preserve_whitespace: false,
+            control_chars: ControlChars::Strip,
+            ascii_fold: false,
+            lowercase: false,
+            repeat: 1,
+            excluded: HashSet::new(),
+        }
+    }
+
+    #[test]
+    fn zen_session_records_no_rhythm() {
+        let config = SessionConfig {
+            zen: true,
+            ..Default::default()
+        };
+        let session = replay("hello world", "hello world", 100, config);
+        assert!(session.is_complete());
+
+        let report = session.generate_report();
+        assert!(report.typing_rhythm.is_empty());
+        assert!(report.key_stats.is_empty());
+        assert!(report.hesitation_patterns.is_empty());
+        assert_eq!(report.accuracy, 100.0);
+        assert!(report.wpm > 0.0);
+    }
+
+    #[test]
+    fn injected_clock_makes_timing_deterministic() {
+        let start = Instant::now();
+        let time = std::rc::Rc::new(std::cell::Cell::new(start));
+        let mut session = TypingSession::with_clock(
+            "hello".to_string(),
+            SessionConfig::default(),
+            Box::new(ReplayClock(time.clone())),
+        );
+
+        // 250ms per key after the first, with a 600ms hesitation before the 'o'
+        for (key, ms) in [('h', 0), ('e', 250), ('l', 500), ('l', 750), ('o', 1350)] {
+            time.set(start + Duration::from_millis(ms));
+            session.handle_key(key);
+        }
+        assert!(session.is_complete());
+
+        let report = session.generate_report();
+        assert_eq!(report.session_duration, Duration::from_millis(1350));
+        // 5 characters in 1.35s
+        assert!((report.wpm - 5.0 / 5.0 / (1.35 / 60.0)).abs() < 1e-9);
+        let latencies: Vec<u64> = report
+            .typing_rhythm
+            .iter()
+            .map(|r| r.latency.as_millis() as u64)
+            .collect();
+        assert_eq!(latencies, [0, 250, 250, 250, 600]);
+        assert_eq!(report.key_stats[&'l'].latencies_us, [250_000, 250_000]);
+        assert_eq!(report.hesitation_patterns.len(), 1);
+        assert_eq!(report.hesitation_patterns[0].position, 4);
+    }
+
+    fn key(code: KeyCode, modifiers: event::KeyModifiers) -> Event {
+        Event::Key(event::KeyEvent::new(code, modifiers))
+    }
+
+    fn quote_app(ui_options: UiOptions, session_config: SessionConfig) -> App {
+        let source = TextSource::Quote("ab".to_string(), Vec::new());
+        App::new(
+            Some(source),
+            snippet_options(),
+            session_config,
+            ui_options,
+            None,
+            None,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn quitting_the_warmup_does_not_leak_into_the_next_session() {
+        let ui_options = UiOptions {
+            warmup: true,
+            ..Default::default()
+        };
+        let mut app = quote_app(ui_options, SessionConfig::default());
+        app.browser = Some(FileBrowser::open(std::env::temp_dir()).unwrap());
+        assert!(app.warming_up);
+
+        app.handle_event(key(KeyCode::Char('q'), event::KeyModifiers::CONTROL));
+        assert!(app.state == AppState::Browsing);
+        assert!(!app.warming_up);
+
+        // Picking a file starts the real session, which ends in its report
+        app.start_typing_session();
+        for c in "ab".chars() {
+            app.handle_event(key(KeyCode::Char(c), event::KeyModifiers::NONE));
+        }
+        assert!(app.state == AppState::ShowingReport);
+    }
+
+    #[test]
+    fn error_inspector_scrolls_through_the_errors() {
+        let mut app = quote_app(UiOptions::default(), SessionConfig::default());
+        let press = |app: &mut App, code| app.handle_event(key(code, event::KeyModifiers::NONE));
+        for code in [
+            KeyCode::Char('x'),
+            KeyCode::Backspace,
+            KeyCode::Char('a'),
+            KeyCode::Char('x'),
+            KeyCode::Backspace,
+            KeyCode::Char('b'),
+        ] {
+            press(&mut app, code);
+        }
+        assert!(app.state == AppState::ShowingReport);
+
+        press(&mut app, KeyCode::Char('d'));
+        for _ in 0..3 {
+            press(&mut app, KeyCode::Down);
+        }
+        assert_eq!(app.inspector_scroll, 1);
+        press(&mut app, KeyCode::Up);
+        assert_eq!(app.inspector_scroll, 0);
+
+        let report = app.session.as_ref().unwrap().generate_report();
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(60, 6)).unwrap();
+        terminal
+            .draw(|f| render_error_inspector(f, f.area(), &report, 1))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        let first_row: String = (0..60).map(|x| buffer[(x, 1)].symbol()).collect();
+        assert!(first_row.contains("#2"), "{}", first_row);
+    }
+
+    #[test]
+    fn no_color_swaps_colours_for_attributes() {
+        use std::ffi::OsStr;
+        assert!(colors_disabled_by(
+            Some(OsStr::new("1")),
+            Some(OsStr::new("xterm"))
+        ));
+        assert!(!colors_disabled_by(
+            Some(OsStr::new("")),
+            Some(OsStr::new("xterm"))
+        ));
+        assert!(colors_disabled_by(None, Some(OsStr::new("dumb"))));
+        assert!(!colors_disabled_by(None, None));
+
+        let mut buffer = Buffer::empty(ratatui::layout::Rect::new(0, 0, 3, 1));
+        buffer[(0, 0)].set_fg(Color::Red);
+        buffer[(1, 0)].set_bg(Color::Red);
+        buffer[(2, 0)].set_fg(Color::DarkGray);
+        decolorize(&mut buffer);
+        assert_eq!(
+            buffer[(0, 0)].modifier,
+            Modifier::BOLD | Modifier::UNDERLINED
+        );
+        assert_eq!(buffer[(1, 0)].modifier, Modifier::REVERSED);
+        assert_eq!(buffer[(2, 0)].modifier, Modifier::DIM);
+        assert!(
+            buffer
+                .content
+                .iter()
+                .all(|cell| cell.fg == Color::Reset && cell.bg == Color::Reset)
+        );
+    }
+
+    #[test]
+    fn queued_keys_are_all_handled_before_the_next_draw() {
+        let mut app = quote_app(UiOptions::default(), SessionConfig::default());
+        let mut queue: std::collections::VecDeque<Event> = ['b', 'z']
+            .into_iter()
+            .map(|c| key(KeyCode::Char(c), event::KeyModifiers::NONE))
+            .collect();
+        let first = key(KeyCode::Char('a'), event::KeyModifiers::NONE);
+        drain_events(&mut app, first, || Ok(queue.pop_front())).unwrap();
+        assert!(queue.is_empty());
+        // "ab" completed the quote, and 'z' does nothing on the report
+        assert!(app.state == AppState::ShowingReport);
+
+        // Quitting stops the drain, leaving later events queued
+        let mut app = quote_app(UiOptions::default(), SessionConfig::default());
+        let mut queue: std::collections::VecDeque<Event> =
+            [key(KeyCode::Char('a'), event::KeyModifiers::NONE)].into();
+        let quit = key(KeyCode::Char('q'), event::KeyModifiers::CONTROL);
+        drain_events(&mut app, quit, || Ok(queue.pop_front())).unwrap();
+        assert!(app.should_quit);
+        assert_eq!(queue.len(), 1);
+    }
+
+    #[test]
+    fn clean_run_completes_with_full_accuracy() {
+        let session = replay("abc def", "abc def", 200, SessionConfig::default());
+        let report = session.generate_report();
+        assert!(session.is_complete());
+        assert_eq!(report.correct_characters, 7);
+        assert_eq!(report.accuracy, 100.0);
+        // 7 characters in 1.2s
+        assert!((report.wpm - 70.0).abs() < 1e-6);
+    }
+
+    #[test]
+    fn max_samples_caps_the_logs_but_not_the_totals() {
+        let config = SessionConfig {
+            max_samples: Some(100),
+            ..Default::default()
+        };
+        let text = "ab".repeat(250);
+        // Every 'b' is first mistyped as 'x', then overtyped
+        let keys = "axb".repeat(250);
+        let session = replay(&text, &keys, 100, config);
+        assert!(session.is_complete());
+
+        let report = session.generate_report();
+        assert!(report.typing_rhythm.len() <= 100);
+        assert!(report.errors.len() <= 100);
+        assert_eq!(report.total_errors(), 250);
+        assert_eq!(report.keystroke_log.len(), keys.len());
+        assert_eq!(report.to_external_schema().char_stats[1], 250);
+
+        for stat in report.key_stats.values() {
+            assert!(stat.latencies_us.len() <= 100);
+            assert_eq!(stat.count as usize, stat.latencies_us.len());
+            assert_eq!(
+                stat.total_latency,
+                Duration::from_micros(stat.latencies_us.iter().sum())
+            );
+            assert!(stat.error_count <= stat.count);
+        }
+        // The 'b' presses each follow a buffered error
+        assert_eq!(report.key_stats[&'b'].error_count, 100);
+    }
+
+    #[test]
+    fn replayed_log_matches_the_live_session() {
+        let text = "the cat sat\n\ton mats";
+        let start = Instant::now();
+        let time = std::rc::Rc::new(std::cell::Cell::new(start));
+        let mut live = TypingSession::with_clock(
+            text.to_string(),
+            SessionConfig::default(),
+            Box::new(ReplayClock(time.clone())),
+        );
+        // Uneven gaps, a corrected typo, an uncorrected one, a tab and a skip
+        for (i, key) in "thw\x08e cxat sat\n\t\x13mats".chars().enumerate() {
+            time.set(start + Duration::from_millis(i as u64 * 90 + (i as u64 * 37) % 140));
+            match key {
+                '\t' => live.handle_tab(),
+                SKIP_WORD_KEY => live.skip_word(),
+                _ => live.handle_key(key),
+            }
+        }
+        assert!(live.is_complete());
+        let live = live.generate_report();
+
+        let replayed = TypingSession::from_keystroke_log(
+            text.to_string(),
+            &live.keystroke_log,
+            SessionConfig::default(),
+        )
+        .generate_report();
+        assert_eq!(replayed.wpm, live.wpm);
+        assert_eq!(replayed.raw_wpm, live.raw_wpm);
+        assert_eq!(replayed.accuracy, live.accuracy);
+        assert_eq!(replayed.session_duration, live.session_duration);
+        assert_eq!(replayed.total_errors(), live.total_errors());
+        assert_eq!(replayed.error_counts, live.error_counts);
+        assert_eq!(replayed.total_corrections, live.total_corrections);
+        assert!(live.total_errors() > 0 && live.total_corrections > 0);
+    }
+
+    #[test]
+    fn line_range_parses_and_rejects_bad_ranges() {
+        let range: LineRange = "3:5".parse().unwrap();
+        assert_eq!((range.start, range.end), (3, 5));
+        assert!("0:2".parse::<LineRange>().is_err());
+        assert!("5:3".parse::<LineRange>().is_err());
+        assert!("7".parse::<LineRange>().is_err());
+    }
+
+    #[test]
+    fn line_range_extraction_is_bounds_checked() {
+        let text = "one\ntwo\nthree\nfour";
+        let range: LineRange = "2:3".parse().unwrap();
+        assert_eq!(
+            TextSource::extract_line_range(text, range).unwrap(),
+            "two\nthree"
+        );
+        let range: LineRange = "3:9".parse().unwrap();
+        assert!(TextSource::extract_line_range(text, range).is_err());
+    }
+
+    #[test]
+    fn leading_headers_are_stripped() {
+        let script = "#!/bin/sh\n# Copyright someone\n\necho hi\n";
+        assert_eq!(TextSource::strip_leading_header(script), "echo hi\n");
+        let rust = "/* License\n * text\n */\n#![allow(dead_code)]\nfn main() {}\n";
+        assert_eq!(
+            TextSource::strip_leading_header(rust),
+            "#![allow(dead_code)]\nfn main() {}\n"
+        );
+        // Nothing but comments: keep everything
+        assert_eq!(TextSource::strip_leading_header("// only\n"), "// only\n");
+    }
+
+    #[test]
+    fn skipped_word_counts_as_omissions() {
+        let mut session = TypingSession::new("foo bar".to_string(), SessionConfig::default());
+        session.handle_key('f');
+        session.skip_word();
+        assert_eq!(session.current_position, 4);
+        assert_eq!(session.outstanding_errors(), 3);
+        assert!(
+            session
+                .errors
+                .iter()
+                .all(|e| e.error_type == ErrorType::Omission)
+        );
+    }
+
+    #[test]
+    fn non_ascii_text_completes() {
+        let session = replay("café über", "café über", 100, SessionConfig::default());
+        assert!(session.is_complete());
+        let report = session.generate_report();
+        assert_eq!(report.total_characters, 9);
+        assert_eq!(report.accuracy, 100.0);
+
+        let mut session = TypingSession::new("naïve café".to_string(), SessionConfig::default());
+        session.skip_word();
+        assert!(!session.is_complete());
+        session.skip_word();
+        assert!(session.is_complete());
+    }
+
+    #[test]
+    fn soft_cap_keeps_accepting_keys() {
+        let keys = "x".repeat(MAX_CONSECUTIVE_ERRORS + 2);
+        let frozen = replay("abc", &keys, 50, SessionConfig::default());
+        assert!(frozen.is_frozen);
+        assert_eq!(frozen.user_input.chars().count(), MAX_CONSECUTIVE_ERRORS);
+
+        let config = SessionConfig {
+            soft_cap: true,
+            ..Default::default()
+        };
+        let soft = replay("abc", &keys, 50, config);
+        assert!(!soft.is_frozen);
+        assert!(soft.over_error_cap());
+        assert_eq!(soft.user_input.chars().count(), MAX_CONSECUTIVE_ERRORS + 2);
+    }
+
+    #[test]
+    fn wpm_confidence_needs_two_samples() {
+        assert_eq!(standard_error(&[40.0]), None);
+        assert!((standard_error(&[2.0, 4.0]).unwrap() - 1.0).abs() < 1e-9);
+        assert!((words_per_minute(300, Duration::from_secs(60)) - 60.0).abs() < 1e-9);
+        assert_eq!(words_per_minute(300, Duration::ZERO), 0.0);
+    }
+
+    #[test]
+    fn labels_are_filename_safe() {
+        assert_eq!(sanitize_for_filename("my run/1"), "my_run_1");
+        assert_eq!(sanitize_for_filename("warm-up_2"), "warm-up_2");
+    }
+
+    #[test]
+    fn label_reaches_the_exported_report() {
+        let dir = std::env::temp_dir().join(format!("keysentry-label-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let config = SessionConfig {
+            label: Some("warm up".to_string()),
+            ..Default::default()
+        };
+        let mut app = quote_app(UiOptions::default(), config);
+        app.export_dir = dir.clone();
+        for c in "ab".chars() {
+            app.handle_event(key(KeyCode::Char(c), event::KeyModifiers::NONE));
+        }
+
+        let path = PathBuf::from(app.export_report().unwrap().unwrap());
+        assert_eq!(path.parent(), Some(dir.as_path()));
+        let filename = path.file_name().unwrap().to_str().unwrap();
+        assert!(filename.starts_with("typing_report_warm_up_"), "{filename}");
+        assert!(filename.ends_with(".json"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["label"], "warm up");
+        assert_eq!(json["target_text"], "ab");
+        assert_eq!(json["correct_characters"], 2);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn failed_exports_are_reported_not_fatal() {
+        let mut app = quote_app(UiOptions::default(), SessionConfig::default());
+        app.export_dir =
+            std::env::temp_dir().join(format!("keysentry-missing-{}", std::process::id()));
+        for c in "ab".chars() {
+            app.handle_event(key(KeyCode::Char(c), event::KeyModifiers::NONE));
+        }
+        assert!(app.state == AppState::ShowingReport);
+
+        for (c, what) in [
+            ('e', "report"),
+            ('a', "anonymized summary"),
+            ('x', "site export"),
+        ] {
+            app.handle_event(key(KeyCode::Char(c), event::KeyModifiers::NONE));
+            let message = app.report_message.clone().unwrap();
+            assert!(
+                message.starts_with(&format!("Could not save {what}: ")),
+                "{message}"
+            );
+            assert!(app.state == AppState::ShowingReport);
+        }
+        assert!(!app.export_dir.exists());
+    }
+
+    #[test]
+    fn long_lines_soft_wrap_without_losing_text() {
+        let text = "lorem ipsum ".repeat(167).trim_end().to_string();
+        assert!(text.chars().count() >= 2000);
+        let session = TypingSession::new(text.clone(), SessionConfig::default());
+
+        let lines = soft_wrap_lines(session.generate_styled_text(None), 80);
+        let widths: Vec<usize> = lines.iter().map(|line| line.width()).collect();
+        assert!(lines.len() >= 25);
+        assert!(widths.iter().all(|&width| width <= 80), "{widths:?}");
+
+        let rows: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+        assert_eq!(rows.concat(), text);
+        // Breaks fall after a space, never inside a word
+        assert!(rows[..rows.len() - 1].iter().all(|row| row.ends_with(' ')));
+    }
+
+    #[test]
+    fn tab_is_one_keystroke_for_four_spaces() {
+        let session = replay("a    b", "a\tb", 100, SessionConfig::default());
+        assert!(session.is_complete());
+        assert_eq!(session.key_stats[&'\t'].count, 1);
+        assert!(!session.key_stats.contains_key(&' '));
+    }
+
+    #[test]
+    fn require_clean_blocks_completion_until_fixed() {
+        let config = SessionConfig {
+            require_clean: true,
+            ..Default::default()
+        };
+        let mut session = TypingSession::new("ab cd".to_string(), config);
+        session.handle_key('a');
+        session.handle_key('b');
+        session.handle_key(' ');
+        session.skip_word();
+        assert!(!session.is_complete());
+
+        // Backspace reopens the skipped word, which can then be typed
+        session.handle_key('\x08');
+        session.handle_key('\x08');
+        session.handle_key('c');
+        session.handle_key('d');
+        assert_eq!(session.outstanding_errors(), 0);
+        assert!(session.is_complete());
+    }
+
+    #[test]
+    fn backspace_crosses_a_line_break_into_a_skipped_word() {
+        let mut session = TypingSession::new("ab\ncd".to_string(), SessionConfig::default());
+        session.handle_key('a');
+        session.skip_word();
+        session.handle_key('c');
+        session.handle_key('d');
+        assert_eq!(session.outstanding_errors(), 2);
+
+        // Back over "cd" and the newline into the skipped "b"
+        for _ in 0..4 {
+            session.handle_key('\x08');
+        }
+        assert_eq!(session.current_position, 1);
+        assert_eq!(session.outstanding_errors(), 0);
+
+        for key in "b\ncd".chars() {
+            session.handle_key(key);
+        }
+        assert!(session.is_complete());
+        assert_eq!(session.outstanding_errors(), 0);
+        assert_eq!(session.generate_report().accuracy, 100.0);
+        assert!(
+            session
+                .errors
+                .iter()
+                .all(|e| e.correction_timestamp.is_some())
+        );
+    }
+
+    #[test]
+    fn frozen_buffer_accepts_backspace() {
+        let keys = "x".repeat(MAX_CONSECUTIVE_ERRORS);
+        let mut session = replay("ab", &keys, 50, SessionConfig::default());
+        assert!(session.is_frozen);
+        session.handle_key('a');
+        assert_eq!(session.user_input.chars().count(), MAX_CONSECUTIVE_ERRORS);
+
+        session.handle_key('\x08');
+        assert!(!session.is_frozen);
+        assert_eq!(
+            session.user_input.chars().count(),
+            MAX_CONSECUTIVE_ERRORS - 1
+        );
+    }
+
+    #[test]
+    fn report_keeps_label_and_snippet_score() {
+        let content = "Practice makes the hands remember the shape of each word. ".repeat(17);
+        let options = snippet_options();
+        let (_, score) = TextSource::extract_file_snippet(&content, "notes.txt", &options);
+        assert!(score.is_some());
+
+        let source = TextSource::from_content("notes.txt".to_string(), &content, &options, None);
+        let config = SessionConfig {
+            label: Some("drill".to_string()),
+            ..Default::default()
+        };
+        let app = App::new(
+            Some(source.unwrap()),
+            options,
+            config,
+            UiOptions::default(),
+            None,
+            None,
+        )
+        .unwrap();
+        let report = app.session.unwrap().generate_report();
+        assert_eq!(report.label.as_deref(), Some("drill"));
+        assert_eq!(report.snippet_score, score);
+    }
+
+    #[test]
+    fn review_reconstructs_state_at_a_keystroke() {
+        let session = replay("abc", "axbc", 100, SessionConfig::default());
+        let state = session.reconstruct_at(1).unwrap();
+        assert_eq!(state.typed_char, 'x');
+        assert_eq!(state.expected_char, Some('b'));
+        assert_eq!(state.correct_prefix, "a");
+        assert!(session.reconstruct_at(99).is_none());
+    }
+
+    #[test]
+    fn report_charts_keep_the_top_n_keys() {
+        // 'a' typed 12 times, 'b' 11 times, ... 'l' once
+        let text: String = ('a'..='l')
+            .enumerate()
+            .map(|(i, c)| c.to_string().repeat(12 - i))
+            .collect();
+        let report = replay(&text, &text, 100, SessionConfig::default()).generate_report();
+        assert_eq!(report.key_stats.len(), 12);
+
+        let top = top_keys_by(&report, 5, |stats| stats.count);
+        let keys: Vec<&str> = top.iter().map(|(key, _)| key.as_str()).collect();
+        assert_eq!(keys, ["a", "b", "c", "d", "e"]);
+        assert_eq!(top[0].1, 12);
+        assert_eq!(top_keys_by(&report, 20, |stats| stats.count).len(), 12);
+        assert!(top_keys_by(&report, 5, |stats| stats.error_count).is_empty());
+    }
+
+    #[test]
+    fn report_views_cycle() {
+        let view = ReportView::Charts;
+        assert!(view.next().previous() == view);
+        assert!(view.next() != view);
+        assert!(view.next().next().next() == view);
+    }
+
+    #[test]
+    fn review_text_marks_the_right_characters() {
+        let session = TypingSession::new("ab\ncd\tx".to_string(), SessionConfig::default());
+        let lines = session.generate_review_text(&[1, 2, 4, 5]);
+        let marked = |span: &Span| span.style.bg == Some(Color::Magenta);
+        let spans: Vec<Vec<(String, bool)>> = lines
+            .iter()
+            .map(|line| {
+                line.spans
+                    .iter()
+                    .map(|span| (span.content.to_string(), marked(span)))
+                    .collect()
+            })
+            .collect();
+
+        let span = |text: &str, marked| (text.to_string(), marked);
+        assert_eq!(
+            spans,
+            [
+                vec![span("a", false), span("b", true), span("↵", true)],
+                vec![
+                    span("c", false),
+                    span("d", true),
+                    span("    ", true),
+                    span("x", false)
+                ],
+            ]
+        );
+    }
+
+    #[test]
+    fn no_alt_screen_keeps_the_main_screen() {
+        const ENTER_ALT: &[u8] = b"\x1b[?1049h";
+        const LEAVE_ALT: &[u8] = b"\x1b[?1049l";
+        let writes = |alt_screen| {
+            let (mut enter, mut leave) = (Vec::new(), Vec::new());
+            enter_screen(&mut enter, alt_screen).unwrap();
+            leave_screen(&mut leave, alt_screen).unwrap();
+            (enter, leave)
+        };
+        let contains = |out: &[u8], seq: &[u8]| out.windows(seq.len()).any(|w| w == seq);
+
+        let (enter, leave) = writes(true);
+        assert!(contains(&enter, ENTER_ALT));
+        assert!(contains(&leave, LEAVE_ALT));
+
+        let cli = Cli::parse_from(["keysentry", "--no-alt-screen"]);
+        let (enter, leave) = writes(!cli.no_alt_screen);
+        assert!(!enter.is_empty());
+        assert!(!contains(&enter, ENTER_ALT));
+        assert!(!contains(&leave, LEAVE_ALT));
+    }
+
+    #[test]
+    fn live_header_colours_accuracy_bands() {
+        assert_eq!(accuracy_color(100.0), Color::Green);
+        assert_eq!(accuracy_color(97.0), Color::Green);
+        assert_eq!(accuracy_color(96.99), Color::Yellow);
+        assert_eq!(accuracy_color(90.0), Color::Yellow);
+        assert_eq!(accuracy_color(89.99), Color::Red);
+        assert_eq!(accuracy_color(0.0), Color::Red);
+    }
+
+    #[test]
+    fn finger_maps_load_and_fall_back_to_qwerty() {
+        let path = std::env::temp_dir().join(format!("keysentry-fingers-{}", std::process::id()));
+
+        fs::write(&path, r#"{"q": "R-Pinky", "1": "L-Ring"}"#).unwrap();
+        let custom = load_finger_map(&path).unwrap();
+        let config = SessionConfig {
+            finger_map: Some(custom),
+            ..Default::default()
+        };
+        let map = TypingSession::new("q".to_string(), config).create_finger_map();
+        assert_eq!(map[&'q'], "R-Pinky");
+        assert_eq!(map[&'1'], "L-Ring");
+        assert_eq!(map[&'w'], "L-Ring");
+        assert_eq!(map[&' '], "Thumb");
+
+        fs::write(&path, r#"{"q": "R-Pinky""#).unwrap();
+        let err = load_finger_map(&path).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+
+        fs::write(&path, r#"{"q": "Left pinky"}"#).unwrap();
+        let err = load_finger_map(&path).unwrap_err();
+        assert!(err.to_string().contains("unknown finger 'Left pinky'"));
+
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn transforms_shuffle_reverse_lowercase_and_repeat() {
+        let mut options = snippet_options();
+        options.transform = Some(TextTransform::ReverseLines);
+        assert_eq!(options.apply_transforms("a\nb\nc".to_string()), "c\nb\na");
+
+        options.transform = None;
+        options.lowercase = true;
+        options.repeat = 2;
+        assert_eq!(options.apply_transforms("Ab".to_string()), "ab\nab");
+
+        use rand::SeedableRng;
+        let text = "one two\n  three four";
+        let shuffled = shuffle_words(text, &mut rand::rngs::StdRng::seed_from_u64(1));
+        let mut words: Vec<&str> = shuffled.split_whitespace().collect();
+        words.sort_unstable();
+        assert_eq!(words, ["four", "one", "three", "two"]);
+        assert_eq!(shuffled.lines().count(), 2);
+        assert!(shuffled.lines().nth(1).unwrap().starts_with("  "));
+    }
+
+    #[test]
+    fn tab_in_target_is_typed_literally() {
+        let session = replay("a\tb", "a\tb", 100, SessionConfig::default());
+        assert!(session.is_complete());
+        assert_eq!(session.key_stats[&'\t'].count, 1);
+    }
+
+    #[test]
+    fn overtyping_the_right_key_corrects_the_buffer() {
+        let session = replay("abc", "abxc", 100, SessionConfig::default());
+        assert!(session.is_complete());
+        assert_eq!(session.user_input, "abc");
+        assert_eq!(session.errors.len(), 1);
+        assert_eq!(
+            session.errors[0].correction_latency,
+            Some(Duration::from_millis(100))
+        );
+    }
+
+    #[test]
+    fn symbols_and_digits_are_grouped() {
+        let session = replay("a1!", "a1!", 100, SessionConfig::default());
+        let stats = session.generate_report().symbol_stats();
+        assert_eq!(stats.digits.keystrokes, 1);
+        assert_eq!(stats.symbols.keystrokes, 1);
+        assert_eq!(stats.digits.accuracy(), Some(100.0));
+    }
+
+    #[test]
+    fn symbol_hesitations_follow_the_layout() {
+        let hesitation = |layout, key| {
+            let config = SessionConfig {
+                layout,
+                ..Default::default()
+            };
+            TypingSession::new("a".to_string(), config).detect_hesitation_type(key, 100, "a", "")
+        };
+
+        // '/' is Shift+7 on QWERTZ but unshifted on US QWERTY
+        assert!(symbol_chars(KeyboardLayout::De).contains('/'));
+        assert!(!symbol_chars(KeyboardLayout::Us).contains('/'));
+        assert_eq!(
+            hesitation(KeyboardLayout::De, '/'),
+            HesitationType::NumberSymbol
+        );
+        assert_eq!(
+            hesitation(KeyboardLayout::Us, '/'),
+            HesitationType::Punctuation
+        );
+        assert_eq!(
+            hesitation(KeyboardLayout::Uk, '£'),
+            HesitationType::NumberSymbol
+        );
+        assert!(symbol_chars(KeyboardLayout::Fr).contains('1'));
+    }
+
+    #[test]
+    fn personal_best_is_per_source_with_an_overall_fallback() {
+        let entry = |source: &str, wpm, accuracy| HistoryEntry {
+            timestamp: chrono::Utc::now(),
+            source: source.to_string(),
+            wpm,
+            accuracy,
+            duration: Duration::from_secs(60),
+        };
+        let history = History {
+            path: PathBuf::new(),
+            entries: vec![
+                entry("quotes", 40.0, 99.0),
+                entry("main.rs", 55.0, 92.0),
+                entry("quotes", 48.0, 95.0),
+                entry("main.rs", 50.0, 97.0),
+            ],
+            read_only: false,
+        };
+
+        // Best WPM and best accuracy may come from different sessions
+        let best = |wpm, accuracy| Some(PersonalBest { wpm, accuracy });
+        assert_eq!(history.best_for("quotes"), best(48.0, 99.0));
+        assert_eq!(history.best_for("main.rs"), best(55.0, 97.0));
+        assert_eq!(history.best_for("never practiced"), best(55.0, 99.0));
+
+        let empty = History {
+            entries: Vec::new(),
+            ..history
+        };
+        assert_eq!(empty.best_for("quotes"), None);
+    }
+
+    #[test]
+    fn quitting_mid_session_saves_a_sensible_partial_report() {
+        let dir = std::env::temp_dir().join(format!("keysentry-partial-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let ui_options = UiOptions {
+            save_on_quit: true,
+            ..Default::default()
+        };
+        let mut app = quote_app(ui_options, SessionConfig::default());
+        app.export_dir = dir.clone();
+        for c in "ax".chars() {
+            app.handle_event(key(KeyCode::Char(c), event::KeyModifiers::NONE));
+        }
+        app.handle_event(key(KeyCode::Char('q'), event::KeyModifiers::CONTROL));
+        assert!(app.should_quit);
+
+        let saved: Vec<PathBuf> = fs::read_dir(&dir)
+            .unwrap()
+            .map(|entry| entry.unwrap().path())
+            .collect();
+        assert_eq!(saved.len(), 1);
+        let report: SessionReport =
+            serde_json::from_str(&fs::read_to_string(&saved[0]).unwrap()).unwrap();
+        assert!(report.wpm.is_finite() && report.wpm >= 0.0);
+        assert_eq!(report.accuracy, 50.0);
+        let consistency = report.to_external_schema().consistency;
+        assert!((0.0..=100.0).contains(&consistency));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn sparkline_keeps_the_most_recent_latencies() {
+        // Key i comes i ms after the one before it
+        let text = "a".repeat(RECENT_LATENCY_CAPACITY + 10);
+        let log: Vec<(char, Duration)> = (0..text.len() as u64)
+            .map(|i| ('a', Duration::from_millis(i * (i + 1) / 2)))
+            .collect();
+        let session = TypingSession::from_keystroke_log(text, &log, SessionConfig::default());
+
+        let recent: Vec<u64> = session.recent_latencies.iter().copied().collect();
+        assert_eq!(recent.len(), RECENT_LATENCY_CAPACITY);
+        // The ten oldest were evicted, the rest stay oldest first, in µs
+        let expected: Vec<u64> = (10..RECENT_LATENCY_CAPACITY as u64 + 10)
+            .map(|i| i * 1000)
+            .collect();
+        assert_eq!(recent, expected);
+    }
+
+    #[test]
+    fn preserve_whitespace_keeps_indentation_and_space_runs() {
+        let content = "    let  aligned   = 1;   \n".repeat(40);
+        let mut options = snippet_options();
+        options.preserve_whitespace = true;
+        let (snippet, _) = TextSource::extract_file_snippet(&content, "notes.txt", &options);
+        assert!(
+            snippet.starts_with("    let  aligned   = 1;   \n"),
+            "{snippet:?}"
+        );
+        assert!(snippet.contains("   \n    let  aligned"));
+
+        let session = replay(&snippet, &snippet, 10, SessionConfig::default());
+        assert!(session.is_complete());
+        assert_eq!(session.calculate_accuracy(), 100.0);
+
+        options.preserve_whitespace = false;
+        let (trimmed, _) = TextSource::extract_file_snippet(&content, "notes.txt", &options);
+        assert!(trimmed.starts_with("let  aligned"));
+    }
+
+    #[test]
+    fn file_browser_lists_sorts_and_selects() {
+        let root = std::env::temp_dir().join(format!("keysentry-browser-{}", std::process::id()));
+        fs::create_dir_all(root.join("src")).unwrap();
+        fs::create_dir_all(root.join("docs")).unwrap();
+        fs::create_dir_all(root.join(".git")).unwrap();
+        fs::write(root.join("notes.txt"), "plain text").unwrap();
+        fs::write(root.join("b.rs"), "fn main() {}").unwrap();
+        fs::write(root.join("image.bin"), [0u8, 159, 146, 150]).unwrap();
+        fs::write(root.join(".hidden"), "secret").unwrap();
+        fs::write(root.join("src").join("lib.rs"), "pub fn f() {}").unwrap();
+
+        let mut browser = FileBrowser::open(root.clone()).unwrap();
+        let listing: Vec<(&str, EntryKind)> = browser
+            .entries
+            .iter()
+            .map(|entry| (entry.name.as_str(), entry.kind))
+            .collect();
+        assert_eq!(
+            listing,
+            [
+                ("..", EntryKind::Parent),
+                ("docs", EntryKind::Directory),
+                ("src", EntryKind::Directory),
+                ("b.rs", EntryKind::TextFile),
+                ("image.bin", EntryKind::Unsupported),
+                ("notes.txt", EntryKind::TextFile),
+            ]
+        );
+
+        // Selection stops at either end
+        browser.move_selection(-1);
+        assert_eq!(browser.selected, 0);
+        browser.move_selection(99);
+        assert_eq!(browser.selected, 5);
+        assert_eq!(browser.activate(), Some(root.join("notes.txt")));
+
+        browser.move_selection(-1);
+        assert_eq!(browser.activate(), None);
+        assert_eq!(
+            browser.message.as_deref(),
+            Some("image.bin is not a text file")
+        );
+
+        browser.selected = 2;
+        assert_eq!(browser.activate(), None);
+        assert_eq!(browser.dir, root.join("src"));
+        assert_eq!(browser.entries[1].name, "lib.rs");
+        assert_eq!(browser.selected, 0);
+        browser.go_up();
+        assert_eq!(browser.dir, root);
+
+        fs::remove_dir_all(&root).unwrap();
+    }
+
+    #[test]
+    fn pace_marker_moves_at_the_target_wpm() {
+        let start = Instant::now();
+        let time = std::rc::Rc::new(std::cell::Cell::new(start));
+        let config = SessionConfig {
+            target_wpm: Some(60), // 5 characters a second
+            ..Default::default()
+        };
+        let text = "a".repeat(20);
+        let mut session =
+            TypingSession::with_clock(text, config, Box::new(ReplayClock(time.clone())));
+        assert_eq!(session.pace_position(), Some(0));
+
+        // The pacer starts with the first keystroke, not when the text appears
+        time.set(start + Duration::from_secs(3));
+        assert_eq!(session.pace_position(), Some(0));
+        session.handle_key('a');
+        time.set(start + Duration::from_millis(3_000 + 2_100));
+        assert_eq!(session.pace_position(), Some(10));
+        time.set(start + Duration::from_millis(3_000 + 3_900));
+        assert_eq!(session.pace_position(), Some(19));
+        time.set(start + Duration::from_secs(3 + 4));
+        assert_eq!(session.pace_position(), None);
+
+        let unpaced = TypingSession::new("abc".to_string(), SessionConfig::default());
+        assert_eq!(unpaced.pace_position(), None);
+    }
+
+    #[test]
+    fn quotes_fill_the_band_without_repeats() {
+        let authors_by_quote: HashMap<&str, &str> = include_str!("quotes.txt")
+            .lines()
+            .filter(|line| !line.starts_with('#'))
+            .filter_map(|line| line.rsplit_once(" | "))
+            .collect();
+
+        for seed in 0..20 {
+            let (text, authors) = TextSource::draw_quotes(300, 600, Some(seed));
+            // Every quote but the first brings its joining newline
+            let chars = text.chars().count() + 1;
+            assert!((300..=600).contains(&chars), "seed {seed}: {chars} chars");
+
+            let quotes: Vec<&str> = text.lines().collect();
+            let unique: HashSet<&str> = quotes.iter().copied().collect();
+            assert_eq!(unique.len(), quotes.len(), "seed {seed} repeats a quote");
+
+            let mut in_order: Vec<String> = Vec::new();
+            for quote in &quotes {
+                let author = authors_by_quote[quote].to_string();
+                if !in_order.contains(&author) {
+                    in_order.push(author);
+                }
+            }
+            assert_eq!(authors, in_order);
+            assert_eq!(TextSource::draw_quotes(300, 600, Some(seed)).0, text);
+        }
+    }
+
+    #[test]
+    fn idle_pause_is_left_out_of_time_and_latencies() {
+        let start = Instant::now();
+        let time = std::rc::Rc::new(std::cell::Cell::new(start));
+        let at = |ms| time.set(start + Duration::from_millis(ms));
+        let mut session = TypingSession::with_clock(
+            "abcd".to_string(),
+            SessionConfig::default(),
+            Box::new(ReplayClock(time.clone())),
+        );
+
+        session.handle_key('a');
+        at(100);
+        session.handle_key('b');
+        at(5_100);
+        assert_eq!(session.idle_time(), Duration::from_secs(5));
+        session.pause_idle();
+        assert!(session.is_paused());
+
+        // A minute later a key press ends the pause without being typed
+        at(60_100);
+        session.resume();
+        assert!(!session.is_paused());
+        at(60_150);
+        session.handle_key('c');
+        at(60_250);
+        session.handle_key('d');
+        assert!(session.is_complete());
+
+        let report = session.generate_report();
+        assert_eq!(report.session_duration, Duration::from_millis(250));
+        // 'c' only shows how long the typist took to get going again
+        assert!(!report.key_stats.contains_key(&'c'));
+        assert_eq!(report.key_stats[&'d'].latencies_us, [100_000]);
+        let latencies: Vec<u128> = report
+            .typing_rhythm
+            .iter()
+            .map(|r| r.latency.as_millis())
+            .collect();
+        assert_eq!(latencies, [0, 100, 100]);
+    }
+
+    #[test]
+    fn telemetry_writes_one_json_line_per_key() {
+        let path = std::env::temp_dir().join(format!("keysentry-telemetry-{}", std::process::id()));
+        let config = SessionConfig {
+            telemetry: Some(path.clone()),
+            ..Default::default()
+        };
+        let mut session = replay("ab", "ax\x08b", 100, config);
+        assert!(session.is_complete());
+        session.flush_telemetry();
+
+        let written = fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = written.lines().collect();
+        assert_eq!(
+            lines,
+            [
+                r#"{"position":0,"char":"a","latency_us":0,"correct":true}"#,
+                r#"{"position":1,"char":"x","latency_us":100000,"correct":false}"#,
+                r#"{"position":1,"char":"\b","latency_us":100000,"correct":null}"#,
+                r#"{"position":1,"char":"b","latency_us":100000,"correct":true}"#,
+            ]
+        );
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn weakest_cluster_widens_to_whole_words() {
+        let text = "alpha beta gamma delta epsilon zeta eta theta iota kappa";
+        let drill = |positions: &[usize]| {
+            let mut session = TypingSession::new(text.to_string(), SessionConfig::default());
+            session.errors = positions.iter().map(|&p| error_at(p)).collect();
+            session.weakest_cluster_text()
+        };
+
+        assert_eq!(drill(&[]), None);
+        assert_eq!(drill(&[0, 2]).as_deref(), Some("alpha"));
+        assert_eq!(drill(&[55]).as_deref(), Some("kappa"));
+        // An error past the last character still lands on the last word
+        assert_eq!(drill(&[56]).as_deref(), Some("kappa"));
+        assert_eq!(drill(&[8, 13]).as_deref(), Some("beta gamma"));
+        // The cluster with the most errors wins
+        assert_eq!(drill(&[1, 3, 53]).as_deref(), Some("alpha"));
+        assert_eq!(drill(&[1, 51, 53]).as_deref(), Some("kappa"));
+    }
+
+    #[test]
+    fn hold_on_complete_waits_for_a_key() {
+        let ui_options = UiOptions {
+            hold_on_complete: true,
+            pomodoro: Some(Duration::from_secs(60)),
+            idle_timeout: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        let mut app = quote_app(ui_options, SessionConfig::default());
+        for c in "ab".chars() {
+            app.handle_event(key(KeyCode::Char(c), event::KeyModifiers::NONE));
+        }
+        assert!(app.state == AppState::Completed);
+
+        // Nothing but a key press moves on, however long the text stays up
+        app.check_idle();
+        app.check_pomodoro(Instant::now() + Duration::from_secs(3600));
+        assert!(app.state == AppState::Completed);
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(60, 12)).unwrap();
+        terminal.draw(|f| ui_typing(f, &app)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        assert!(screen.contains("Press any key for the report"));
+
+        // The key that dismisses the text isn't typed
+        app.handle_event(key(KeyCode::Char('z'), event::KeyModifiers::NONE));
+        assert!(app.state == AppState::ShowingReport);
+        assert_eq!(app.session.as_ref().unwrap().user_input, "ab");
+
+        let mut app = quote_app(UiOptions::default(), SessionConfig::default());
+        for c in "ab".chars() {
+            app.handle_event(key(KeyCode::Char(c), event::KeyModifiers::NONE));
+        }
+        assert!(app.state == AppState::ShowingReport);
+    }
+
+    #[test]
+    fn list_layouts_prints_every_layout_value() {
+        assert!(Cli::parse_from(["keysentry", "--list-layouts"]).list_layouts);
+        let mut out = Vec::new();
+        list_layouts(&mut out).unwrap();
+        assert_eq!(String::from_utf8(out).unwrap(), "us\nuk\nde\nfr\n");
+        // Every listed name is accepted back by --layout
+        for name in ["us", "uk", "de", "fr"] {
+            assert!(Cli::try_parse_from(["keysentry", "--layout", name]).is_ok());
+        }
+    }
+
+    #[test]
+    fn sub_millisecond_latencies_are_kept() {
+        let log: Vec<(char, Duration)> = [('a', 0), ('b', 300), ('a', 700), ('b', 1_000)]
+            .iter()
+            .map(|&(key, us)| (key, Duration::from_micros(us)))
+            .collect();
+        let report =
+            TypingSession::from_keystroke_log("abab".to_string(), &log, SessionConfig::default())
+                .generate_report();
+
+        assert_eq!(report.key_stats[&'b'].latencies_us, [300, 300]);
+        assert_eq!(report.key_stats[&'a'].latencies_us, [0, 400]);
+        assert_eq!(report.average_latency, Duration::from_micros(250));
+        let digraphs = &report.weakness_analysis.slowest_digraphs;
+        assert_eq!(digraphs.len(), 1);
+        assert_eq!(digraphs[0].0, "ab");
+        assert!((digraphs[0].1 - 0.3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn hand_balance_flags_the_slower_hand() {
+        let balance = HandBalance {
+            left_avg_latency_ms: 130.0,
+            right_avg_latency_ms: 100.0,
+            left_share: 0.5,
+            right_share: 0.5,
+        };
+        assert_eq!(balance.imbalance().as_deref(), Some("left hand 30% slower"));
+        let even = HandBalance {
+            right_avg_latency_ms: 128.0,
+            ..balance
+        };
+        assert_eq!(even.imbalance(), None);
+    }
+
+    #[test]
+    fn ghost_follows_the_recorded_progress() {
+        let report = replay("abc", "abc", 1000, SessionConfig::default()).generate_report();
+        let ghost = Ghost::from_report(&report).unwrap();
+        assert_eq!(ghost.position_at(Duration::ZERO), 1);
+        assert_eq!(ghost.position_at(Duration::from_millis(1500)), 2);
+        assert_eq!(ghost.position_at(Duration::from_secs(10)), 3);
+
+        let zen = SessionConfig {
+            zen: true,
+            ..Default::default()
+        };
+        let report = replay("abc", "abc", 1000, zen).generate_report();
+        assert!(Ghost::from_report(&report).is_err());
+    }
+
+    #[test]
+    fn control_characters_and_smart_punctuation_are_cleaned() {
+        assert_eq!(
+            sanitize_control_chars("a\r\nb\x07c", ControlChars::Strip),
+            "a\nbc"
+        );
+        assert_eq!(
+            sanitize_control_chars("a\r\nb\x07c", ControlChars::Replace),
+            "a\nb?c"
+        );
+        assert_eq!(
+            ascii_fold("\u{201C}hi\u{201D} \u{2014} it\u{2019}s\u{2026}"),
+            "\"hi\" -- it's..."
+        );
+    }
+
+    #[test]
+    fn sample_interval_controls_wpm_granularity() {
+        let config = SessionConfig {
+            sample_interval: Some(Duration::from_secs(1)),
+            ..Default::default()
+        };
+        // 20 keys 250ms apart span 4.75s: one sample per second plus the first key
+        let text = "a".repeat(20);
+        let report = replay(&text, &text, 250, config).generate_report();
+        assert_eq!(report.wpm_over_time.len(), 5);
+
+        // By default only every tenth character is sampled
+        let report = replay(&text, &text, 250, SessionConfig::default()).generate_report();
+        assert_eq!(report.wpm_over_time.len(), 1);
+    }
+
+    #[test]
+    fn accuracy_is_sampled_with_the_wpm() {
+        // Samples fall on keys typed at every tenth position: one wrong key is
+        // buffered at 10, two at 20, each overtyped by the right one
+        let text = "a".repeat(30);
+        let keys = format!("{}xa{}xya{}", "a".repeat(10), "a".repeat(9), "a".repeat(9));
+        let report = replay(&text, &keys, 100, SessionConfig::default()).generate_report();
+        let samples: Vec<(u64, String)> = report
+            .accuracy_over_time
+            .iter()
+            .map(|&(at, accuracy)| (at.as_millis() as u64, format!("{accuracy:.1}")))
+            .collect();
+        let expected = [
+            (1000, "100.0"),
+            (1100, "90.9"),
+            (2100, "100.0"),
+            (2200, "95.2"),
+            (2300, "90.9"),
+        ];
+        assert_eq!(samples, expected.map(|(at, a)| (at, a.to_string())));
+
+        // A clean run never dips
+        let report = replay(&text, &text, 100, SessionConfig::default()).generate_report();
+        assert!(report.accuracy_over_time.iter().all(|&(_, a)| a == 100.0));
+        assert_eq!(report.accuracy_over_time.len(), report.wpm_over_time.len());
+    }
+
+    #[test]
+    fn coverage_lists_characters_never_typed() {
+        let mut session = TypingSession::new("ab cd".to_string(), SessionConfig::default());
+        session.handle_key('a');
+        session.handle_key('b');
+        let coverage = session.generate_report().coverage;
+        assert_eq!(coverage.present, ['a', 'b', 'c', 'd']);
+        assert_eq!(coverage.missing(), ['c', 'd']);
+    }
+
+    #[test]
+    fn outlier_trimming_keeps_the_median() {
+        let latencies: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(
+            percentile_cutoff(latencies, 95.0),
+            Some(Duration::from_micros(95_050))
+        );
+        assert_eq!(percentile_cutoff(Vec::new(), 95.0), None);
+    }
+
+    #[test]
+    fn outlier_is_trimmed_from_a_short_session() {
+        let mut latencies = vec![Duration::from_millis(100); 10];
+        latencies.push(Duration::from_secs(2));
+        assert!(percentile_cutoff(latencies, 95.0).unwrap() < Duration::from_secs(2));
+
+        let config = SessionConfig {
+            trim_outliers: true,
+            ..Default::default()
+        };
+        let log: Vec<(char, Duration)> = "abcdef"
+            .chars()
+            .zip([0, 100, 200, 300, 400, 2400])
+            .map(|(key, ms)| (key, Duration::from_millis(ms)))
+            .collect();
+        let report =
+            TypingSession::from_keystroke_log("abcdef".to_string(), &log, config).generate_report();
+        // The first key has no latency; the 2s pause is left out
+        assert_eq!(report.average_latency, Duration::from_millis(80));
+    }
+
+    #[test]
+    fn overstrike_is_removed_from_man_pages() {
+        assert_eq!(strip_overstrike("b\x08bo\x08ol\x08ld _\x08x"), "bold x");
+    }
+
+    #[test]
+    fn man_pages_load_through_the_runner() {
+        let page = "N\x08NA\x08AM\x08ME\x08E\n       fake - a pretend command\n\n\
+                    D\x08DE\x08ES\x08SC\x08CR\x08RI\x08IP\x08PT\x08TI\x08IO\x08ON\x08N\n\
+                    \x20      fake does nothing at all, but it does it very well.\r\n";
+        let mut asked = None;
+        let source = TextSource::load_man_page_with("fake", &snippet_options(), |name| {
+            asked = Some(name.to_string());
+            Ok(Some(page.as_bytes().to_vec()))
+        })
+        .unwrap();
+        assert_eq!(asked.as_deref(), Some("fake"));
+
+        let (name, content) = source.get_content().unwrap();
+        assert_eq!(name, "man fake");
+        assert!(!content.contains(['\x08', '\r']));
+        assert!(content.contains("DESCRIPTION\n       fake does nothing"));
+
+        let missing = TextSource::load_man_page_with("nope", &snippet_options(), |_| Ok(None));
+        assert_eq!(
+            missing.err().map(|e| e.kind()),
+            Some(io::ErrorKind::NotFound)
+        );
+
+        let broken = TextSource::load_man_page_with("fake", &snippet_options(), |_| {
+            Err(io::Error::other("cannot run man"))
+        });
+        assert!(broken.is_err());
+    }
+
+    #[test]
+    fn bar_labels_name_whitespace_and_control_keys() {
+        assert_eq!(display_label(' '), "Space");
+        assert_eq!(display_label('\n'), "Enter");
+        assert_eq!(display_label('\x07'), "U+0007");
+        assert_eq!(display_digraph("th"), "th");
+        assert_eq!(display_digraph("e "), "e Space");
+    }
+
+    #[test]
+    fn doubling_errors_are_classified() {
+        // One 't' too many in "lettter"
+        let session = replay("letter", "lettt", 100, SessionConfig::default());
+        assert_eq!(session.errors[0].error_type, ErrorType::Doubling);
+        // Skipping the second 't' in "leter"
+        let session = replay("letter", "lete", 100, SessionConfig::default());
+        assert_eq!(session.errors[0].error_type, ErrorType::Doubling);
+        assert_eq!(session.generate_report().doubling_errors, 1);
+        // Repeating a single letter is a plain substitution
+        let session = replay("cat", "caa", 100, SessionConfig::default());
+        assert_eq!(session.errors[0].error_type, ErrorType::Substitution);
+    }
+
+    #[test]
+    fn anonymized_summary_has_no_text() {
+        let config = SessionConfig {
+            label: Some("secret label".to_string()),
+            ..Default::default()
+        };
+        let report = replay("secret words", "secret words", 100, config).generate_report();
+        let json = serde_json::to_string(&report.anonymized()).unwrap();
+        assert!(!json.contains("secret"));
+        assert!(json.contains("latency_distribution"));
+    }
+
+    #[test]
+    fn blind_mode_hides_text_beyond_the_lookahead() {
+        let config = SessionConfig {
+            blind: true,
+            ..Default::default()
+        };
+        let text = "abcdefghijklmnopqrstuvwxyz";
+        let session = replay(text, "ab", 100, config);
+        let lines = session.generate_styled_text(None);
+
+        let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(30, 1)).unwrap();
+        terminal
+            .draw(|f| f.render_widget(Paragraph::new(lines), f.area()))
+            .unwrap();
+        let row: String = (0..30)
+            .map(|x| terminal.backend().buffer()[(x, 0)].symbol())
+            .collect();
+        let visible = 2 + BLIND_LOOKAHEAD;
+        assert_eq!(row[..visible], text[..visible]);
+        assert_eq!(row[visible..], " ".repeat(30 - visible));
+    }
+
+    /// Keeps whatever the app copies
+    struct FakeClipboard(std::rc::Rc<std::cell::RefCell<Option<String>>>);
+
+    impl Clipboard for FakeClipboard {
+        fn set_text(&mut self, text: String) -> Result<(), arboard::Error> {
+            *self.0.borrow_mut() = Some(text);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn copy_puts_the_report_json_on_the_clipboard() {
+        let copied = std::rc::Rc::new(std::cell::RefCell::new(None));
+        let mut app = quote_app(UiOptions::default(), SessionConfig::default());
+        app.clipboard = Some(Box::new(FakeClipboard(copied.clone())));
+        for c in "abc".chars() {
+            app.handle_event(key(KeyCode::Char(c), event::KeyModifiers::NONE));
+        }
+        // "ab" finishes the quote, 'c' on the report copies it
+        assert_eq!(
+            app.report_message.as_deref(),
+            Some("Report copied to clipboard!")
+        );
+        let text = copied.borrow().clone().unwrap();
+        let pasted: SessionReport = serde_json::from_str(&text).unwrap();
+        let report = app.session.as_ref().unwrap().generate_report();
+        assert_eq!(pasted.target_text, "ab");
+        assert_eq!(pasted.wpm, report.wpm);
+        assert_eq!(pasted.session_duration, report.session_duration);
+        assert_eq!(pasted.keystroke_log, report.keystroke_log);
+    }
+
+    #[test]
+    fn adjacent_paragraphs_combine_into_the_band() {
+        let paragraph = |c: char, score| TextParagraph {
+            content: c.to_string().repeat(40),
+            char_count: 40,
+            score,
+        };
+        let paragraphs = [
+            paragraph('a', 1.0),
+            paragraph('é', 5.0),
+            paragraph('c', 4.0),
+            paragraph('d', 0.5),
+        ];
+
+        // Every pair fits 80..=90, the best average wins and keeps file order
+        let combined = TextSource::combine_adjacent_paragraphs(&paragraphs, 80, 90).unwrap();
+        assert_eq!(
+            combined.content,
+            format!("{}\n\n{}", "é".repeat(40), "c".repeat(40))
+        );
+        assert_eq!(combined.char_count, 82);
+        assert_eq!(combined.score, 4.5);
+
+        // 100..=130 takes three: a-é-c averages 10/3, é-c-d only 9.5/3
+        let combined = TextSource::combine_adjacent_paragraphs(&paragraphs, 100, 130).unwrap();
+        assert!(combined.content.starts_with('a') && combined.content.ends_with('c'));
+        assert_eq!(combined.char_count, 124);
+
+        // A single paragraph is no combination, and all four are too short
+        assert!(TextSource::combine_adjacent_paragraphs(&paragraphs, 30, 50).is_none());
+        assert!(TextSource::combine_adjacent_paragraphs(&paragraphs, 200, 300).is_none());
+    }
+
+    #[test]
+    fn command_line_beats_preferences_beats_defaults() {
+        let path =
+            std::env::temp_dir().join(format!("keysentry-precedence-{}", std::process::id()));
+        fs::write(
+            &path,
+            r#"{"size": "large", "top_n": 12, "hesitation_ms": 400, "live_header": true}"#,
+        )
+        .unwrap();
+        let preferences = Preferences::load_from(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        let matches = Cli::command().get_matches_from([
+            "keysentry",
+            "--top-n",
+            "5",
+            "--hesitation-ms",
+            "300",
+        ]);
+        let mut cli = Cli::from_arg_matches(&matches).unwrap();
+        preferences.apply(&mut cli, &matches);
+
+        // Given on the command line
+        assert_eq!(cli.top_n, 5);
+        assert_eq!(cli.hesitation_ms, Some(300));
+        // Only in the file
+        assert!(matches!(cli.size, ChunkSize::Large));
+        assert!(cli.live_header);
+        // In neither
+        assert_eq!(cli.layout, KeyboardLayout::Us);
+        assert_eq!(cli.idle_timeout, 0);
+        assert_eq!(cli.target_wpm, None);
+    }
+
+    #[test]
+    fn keystroke_times_start_at_the_first_key() {
+        let session = replay("abc", "abc", 100, SessionConfig::default());
+        let times: Vec<Duration> = session.keystroke_log.iter().map(|&(_, at)| at).collect();
+        assert_eq!(times, [0, 100, 200].map(Duration::from_millis).to_vec());
+    }
+
+    #[test]
+    fn difficulty_grows_with_symbols() {
+        let prose = estimate_difficulty("the quick brown fox jumps over the lazy dog");
+        let code = estimate_difficulty("fn f<'a>(x: &'a [u8]) -> Option<&'a u8> { x.get(0) }");
+        assert!((1.0..=5.0).contains(&prose));
+        assert!(code > prose);
+        assert_eq!(estimate_difficulty("   "), 1.0);
+    }
+
+    #[test]
+    fn finger_travel_is_measured() {
+        let travel = |text: &str| {
+            replay(text, text, 100, SessionConfig::default())
+                .generate_report()
+                .weakness_analysis
+                .finger_travel
+        };
+
+        // Home row keys are where the fingers already rest
+        let home = travel("asdf jkl;");
+        assert_eq!((home.left, home.right), (0.0, 0.0));
+
+        // q: the left pinky goes up from a (0.75, 2) to (0.5, 1), then down to z
+        // at (1.25, 3). p: the right pinky goes up from ; (9.75, 2) to (9.5, 1);
+        // m: the right index goes down from j (6.75, 2) to (7.25, 3)
+        let reach = travel("qpzm");
+        let left = (0.25f64.powi(2) + 1.0).sqrt() + (0.75f64.powi(2) + 4.0).sqrt();
+        let right = (0.25f64.powi(2) + 1.0).sqrt() + (0.5f64.powi(2) + 1.0).sqrt();
+        assert!((reach.left - left).abs() < 1e-9, "{}", reach.left);
+        assert!((reach.right - right).abs() < 1e-9, "{}", reach.right);
+
+        assert!((FingerTravel::to_meters(1000.0) - 19.05).abs() < 1e-9);
+    }
+
+    #[test]
+    fn instant_mode_never_buffers_wrong_keys() {
+        let config = SessionConfig {
+            instant: true,
+            ..Default::default()
+        };
+        let session = replay("ab", "axb", 100, config);
+        assert!(session.is_complete());
+        assert_eq!(session.user_input, "ab");
+        assert_eq!(session.errors.len(), 1);
+    }
+
+    #[test]
+    fn feedback_highlight_does_not_change_progress() {
+        let config = SessionConfig {
+            feedback: true,
+            ..Default::default()
+        };
+        let session = replay("ab", "ab", 100, config.clone());
+        assert!(session.is_complete());
+
+        let start = Instant::now();
+        let time = std::rc::Rc::new(std::cell::Cell::new(start));
+        let mut session = TypingSession::with_clock(
+            "ab".to_string(),
+            config,
+            Box::new(ReplayClock(time.clone())),
+        );
+        session.handle_key('a');
+        let typed_fg = |ms| {
+            time.set(start + Duration::from_millis(ms));
+            session.generate_styled_text(None)[0].spans[0].style.fg
+        };
+        assert!(FEEDBACK_DECAY == Duration::from_millis(250));
+        assert_eq!(typed_fg(0), Some(Color::White));
+        assert_eq!(typed_fg(124), Some(Color::White));
+        assert_eq!(typed_fg(125), Some(Color::LightGreen));
+        assert_eq!(typed_fg(249), Some(Color::LightGreen));
+        assert_eq!(typed_fg(250), Some(Color::Green));
+        assert_eq!(typed_fg(5_000), Some(Color::Green));
+    }
+
+    #[test]
+    fn symbol_drill_is_reproducible_with_a_seed() {
+        let a = generate_symbols(200, Some(3));
+        assert!(a.len() >= 200);
+        assert_eq!(a, generate_symbols(200, Some(3)));
+
+        for seed in 0..50 {
+            let drill = generate_symbols(200, Some(seed));
+            let mut open = Vec::new();
+            for c in drill.chars().filter(|c| !c.is_whitespace()) {
+                if let Some(&(_, close)) = SYMBOL_BRACKETS.iter().find(|(o, _)| *o == c) {
+                    open.push(close);
+                } else if SYMBOL_BRACKETS.iter().any(|&(_, close)| close == c) {
+                    assert_eq!(open.pop(), Some(c), "seed {seed}: {drill}");
+                } else {
+                    let operator_char = SYMBOL_OPERATORS.iter().any(|op| op.contains(c));
+                    assert!(operator_char, "seed {seed}: stray {c:?}");
+                }
+            }
+            assert!(open.is_empty(), "seed {seed}: unclosed in {drill}");
+        }
+    }
+
+    #[test]
+    fn error_density_bins_positions() {
+        let errors = [error_at(0), error_at(5), error_at(9)];
+        assert_eq!(error_density(&errors, 10, 2), [1, 2]);
+        assert_eq!(error_density(&errors, 0, 2), [0, 0]);
+        assert!(error_density(&errors, 10, 0).is_empty());
+    }
+
+    #[test]
+    fn ramp_reaches_the_end_at_end_speed() {
+        let ramp: WpmRamp = "30:60".parse().unwrap();
+        // 450 characters at the mean 45 WPM take two minutes
+        assert_eq!(ramp.chars_at(Duration::ZERO, 450), 0.0);
+        assert!((ramp.chars_at(Duration::from_secs(60), 450) - 187.5).abs() < 1e-9);
+        assert!((ramp.chars_at(Duration::from_secs(120), 450) - 450.0).abs() < 1e-9);
+        assert!((ramp.chars_at(Duration::from_secs(600), 450) - 450.0).abs() < 1e-9);
+        assert!("0:60".parse::<WpmRamp>().is_err());
+        assert!("fast".parse::<WpmRamp>().is_err());
+    }
+
+    #[test]
+    fn line_times_cover_each_line() {
+        let report = replay("ab\ncd", "ab\ncd", 100, SessionConfig::default()).generate_report();
+        assert_eq!(
+            report.line_times,
+            [
+                (1, Duration::from_millis(200)),
+                (2, Duration::from_millis(100))
+            ]
+        );
+    }
+
+    #[test]
+    fn missed_words_come_from_error_clusters() {
+        let session = replay(
+            "quick brown fox",
+            "qxxxuick brown fox",
+            100,
+            SessionConfig::default(),
+        );
+        let report = session.generate_report();
+        let words = missed_words(&report);
+        assert!(
+            words
+                .iter()
+                .all(|w| report.target_text.contains(w.as_str()))
+        );
+    }
+
+    #[test]
+    fn weak_words_persist_without_duplicates() {
+        let path = std::env::temp_dir().join(format!("keysentry-weak-{}", std::process::id()));
+        let _ = fs::remove_file(&path);
+        let first = replay(
+            "quick brown fox jumps",
+            "qxuick brown fox jxumps",
+            100,
+            SessionConfig::default(),
+        );
+        let second = replay(
+            "jumps over the lazy dog",
+            "jxumps over the lxazy dog",
+            100,
+            SessionConfig::default(),
+        );
+
+        assert_eq!(
+            persist_weak_words(&first.generate_report(), &path).unwrap(),
+            2
+        );
+        assert_eq!(
+            persist_weak_words(&second.generate_report(), &path).unwrap(),
+            1
+        );
+        assert_eq!(
+            persist_weak_words(&second.generate_report(), &path).unwrap(),
+            0
+        );
+        assert_eq!(load_weak_words(&path).unwrap(), ["quick", "jumps", "lazy"]);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn paste_bursts_are_flagged() {
+        let text = "a".repeat(20);
+        let pasted = replay(&text, &text, 1, SessionConfig::default());
+        assert!(pasted.generate_report().paste_suspected);
+        let typed = replay(&text, &text, 120, SessionConfig::default());
+        assert!(!typed.generate_report().paste_suspected);
+        let off = SessionConfig {
+            paste_guard: PasteGuard::Off,
+            ..Default::default()
+        };
+        assert!(
+            !replay(&text, &text, 1, off)
+                .generate_report()
+                .paste_suspected
+        );
+    }
+
+    #[test]
+    fn custom_cursor_sits_between_typed_and_remaining_text() {
+        let render = |session: &TypingSession| -> Vec<(String, Option<Color>)> {
+            session.generate_styled_text(None)[0]
+                .spans
+                .iter()
+                .map(|span| (span.content.to_string(), span.style.fg))
+                .collect()
+        };
+        let cursor = |fg: Color| ("▏".to_string(), Some(fg));
+        let config = SessionConfig {
+            cursor_char: Some('▏'),
+            ..Default::default()
+        };
+
+        let typed = replay("abc", "a", 100, config.clone());
+        let spans = render(&typed);
+        assert_eq!(spans[1], cursor(Color::Green));
+        let text: String = spans.iter().map(|(s, _)| s.as_str()).collect();
+        assert_eq!(text, "a▏bc");
+
+        let wrong = replay("abc", "ax", 100, config.clone());
+        assert!(render(&wrong).contains(&cursor(Color::Red)));
+
+        let done = replay("abc", "abc", 100, config);
+        assert_eq!(render(&done).last(), Some(&cursor(Color::Green)));
+
+        // Without a glyph only the finished text gets the default cursor
+        let plain = replay("abc", "a", 100, SessionConfig::default());
+        assert!(render(&plain).iter().all(|(s, _)| s != "|" && s != "▏"));
+        let plain = replay("abc", "abc", 100, SessionConfig::default());
+        assert_eq!(render(&plain).last().unwrap().0, "|");
+    }
+
+    #[test]
+    fn site_export_counts_error_kinds() {
+        let report = replay("abc", "axbc", 100, SessionConfig::default()).generate_report();
+        let external = report.to_external_schema();
+        assert_eq!(external.char_stats, [3, 1, 0, 0]);
+        assert!((0.0..=100.0).contains(&external.consistency));
+
+        // The timestamp is when the session was typed, not when it is exported
+        let started = report.started_at.unwrap().timestamp_millis();
+        assert_eq!(external.timestamp, started);
+        std::thread::sleep(Duration::from_millis(5));
+        assert_eq!(report.to_external_schema().timestamp, started);
+
+        let mut json = serde_json::to_value(&report).unwrap();
+        json.as_object_mut().unwrap().remove("started_at");
+        let older: SessionReport = serde_json::from_value(json).unwrap();
+        assert_eq!(older.to_external_schema().timestamp, 0);
+    }
+
+    #[test]
+    fn whitespace_only_text_has_nothing_to_type() {
+        assert!(!has_visible_chars(" \n\t"));
+        assert!(has_visible_chars(" a "));
+    }
+
+    #[test]
+    fn content_wpm_splits_code_and_comments() {
+        let config = SessionConfig {
+            split_wpm: true,
+            ..Default::default()
+        };
+        // The comment line and its newline at 200ms a key, the code at 100ms
+        let text = "// hi\nlet x";
+        let mut at = Duration::ZERO;
+        let log: Vec<(char, Duration)> = text
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                if i > 0 {
+                    at += Duration::from_millis(if i < 6 { 200 } else { 100 });
+                }
+                (c, at)
+            })
+            .collect();
+        let report =
+            TypingSession::from_keystroke_log(text.to_string(), &log, config).generate_report();
+        let split = report.content_wpm.unwrap();
+        // 6 keys in 1s and 5 keys in 0.5s
+        assert!((split.comment - 72.0).abs() < 1e-9);
+        assert!((split.code - 120.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn quiet_report_drops_the_guide_sidebars() {
+        let area = ratatui::layout::Rect::new(0, 0, 100, 40);
+        let widths = |quiet| {
+            report_columns(area, quiet)
+                .iter()
+                .map(|r| r.width)
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(widths(false), [75, 25]);
+        assert_eq!(widths(true), [100, 0]);
+
+        let screen = |quiet, view| {
+            let ui_options = UiOptions {
+                quiet,
+                ..Default::default()
+            };
+            let mut app = quote_app(ui_options, SessionConfig::default());
+            for c in "ab".chars() {
+                app.handle_event(key(KeyCode::Char(c), event::KeyModifiers::NONE));
+            }
+            app.report_view = view;
+            let mut terminal = Terminal::new(ratatui::backend::TestBackend::new(160, 60)).unwrap();
+            terminal.draw(|f| ui_report(f, &app)).unwrap();
+            terminal
+                .backend()
+                .buffer()
+                .content()
+                .iter()
+                .map(|cell| cell.symbol())
+                .collect::<String>()
+        };
+        assert!(screen(false, ReportView::Charts).contains("VISUAL ANALYSIS GUIDE"));
+        assert!(!screen(true, ReportView::Charts).contains("VISUAL ANALYSIS GUIDE"));
+        assert!(screen(false, ReportView::Analysis).contains("DETAILED INSIGHTS GUIDE"));
+        assert!(!screen(true, ReportView::Analysis).contains("DETAILED INSIGHTS GUIDE"));
+    }
+
+    #[test]
+    fn ligature_pairs_get_a_joiner_between_styles() {
+        let mut line = Line::from(vec![
+            Span::styled("-", Style::default().fg(Color::Green)),
+            Span::raw(">"),
+        ]);
+        separate_ligatures(&mut line);
+        assert_eq!(line.spans[0].content, "-\u{200C}");
+        let mut same = Line::from(vec![Span::raw("-"), Span::raw(">")]);
+        separate_ligatures(&mut same);
+        assert_eq!(same.spans[0].content, "-");
+    }
+
+    #[test]
+    fn daily_goal_needs_every_part() {
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+        assert!(!DailyGoal::default().is_met(minutes(60), 10));
+
+        let goal = DailyGoal {
+            minutes: Some(10),
+            sessions: None,
+        };
+        assert!(!goal.is_met(minutes(9), 5));
+        assert!(goal.is_met(minutes(10), 1));
+
+        let both = DailyGoal {
+            minutes: Some(10),
+            sessions: Some(2),
+        };
+        assert!(!both.is_met(minutes(20), 1));
+        assert!(both.is_met(minutes(20), 2));
+    }
+
+    #[test]
+    fn streak_counts_consecutive_goal_days() {
+        let goal = DailyGoal {
+            minutes: None,
+            sessions: Some(1),
+        };
+        let today = chrono::Local::now().date_naive();
+        let entry = |days_ago: i64| HistoryEntry {
+            timestamp: (today - chrono::Duration::days(days_ago))
+                .and_hms_opt(12, 0, 0)
+                .unwrap()
+                .and_local_timezone(chrono::Local)
+                .unwrap()
+                .with_timezone(&chrono::Utc),
+            source: "text".to_string(),
+            wpm: 50.0,
+            accuracy: 98.0,
+            duration: Duration::from_secs(60),
+        };
+        let history = History {
+            path: PathBuf::new(),
+            entries: vec![entry(3), entry(2), entry(1)],
+            read_only: false,
+        };
+        // Today isn't over yet, so yesterday's streak is still alive
+        assert_eq!(history.daily_streak(&goal, today), 3);
+        let history = History {
+            entries: vec![entry(3), entry(1), entry(0)],
+            ..history
+        };
+        assert_eq!(history.daily_streak(&goal, today), 2);
+    }
+
+    #[test]
+    fn ruler_marks_every_fifth_column() {
+        assert_eq!(column_ruler(12), "····+····1··");
+    }
+
+    #[test]
+    fn live_counters_count_finished_words() {
+        assert_eq!(completed_words(""), 0);
+        assert_eq!(completed_words("one two thr"), 2);
+        assert_eq!(completed_words("one two "), 2);
+        assert_eq!(completed_words("one\n  two\n"), 2);
+    }
+
+    #[test]
+    fn retype_recovery_thaws_on_the_right_key() {
+        let config = SessionConfig {
+            freeze_recovery: FreezeRecovery::Retype,
+            ..Default::default()
+        };
+        let keys = format!("{}a", "x".repeat(MAX_CONSECUTIVE_ERRORS));
+        let session = replay("ab", &keys, 50, config);
+        assert!(!session.is_frozen);
+        assert_eq!(session.current_position, 1);
+        assert_eq!(session.user_input, "a");
+    }
+
+    #[test]
+    fn raw_wpm_counts_every_key_press() {
+        let report = replay("ab", "ax\x08b", 100, SessionConfig::default()).generate_report();
+        assert!(report.raw_wpm > report.wpm);
+    }
+
+    #[test]
+    fn bench_log_replays_to_a_complete_session() {
+        let text: String = "the lazy dog ".chars().cycle().take(400).collect();
+        let log = bench_keystroke_log(&text);
+        // One typo plus its Backspace every 37 characters
+        assert_eq!(log.len(), 400 + 2 * (400 / 37));
+
+        let session = TypingSession::from_keystroke_log(text, &log, SessionConfig::default());
+        assert!(session.is_complete());
+        let report = session.generate_report();
+        assert_eq!(report.total_errors(), 400 / 37);
+        assert_eq!(report.total_corrections, 400 / 37);
 
-    // Weakness analysis - top row
-    let weakness_top = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(analysis_chunks[1]);
+        run_bench(2, 500, &SessionConfig::default());
+    }
 
-    // Slowest digraphs
-    let digraph_text = if report.weakness_analysis.slowest_digraphs.is_empty() {
-        "✅ No problematic letter combinations found!\nAll transitions are smooth.".to_string()
-    } else {
-        let mut text = "⚠️  SLOW LETTER COMBINATIONS:\n".to_string();
-        for (digraph, avg_ms) in report.weakness_analysis.slowest_digraphs.iter().take(6) {
-            text.push_str(&format!("• '{}': {:.0}ms\n", digraph, avg_ms));
-        }
-        text.push_str("\nFocus practice on these pairs!");
-        text
-    };
+    #[test]
+    fn backspace_removes_a_whole_grapheme() {
+        let mut session = TypingSession::new("ab".to_string(), SessionConfig::default());
+        session.handle_key('a');
+        session.handle_key('\u{301}');
+        session.handle_key('\x08');
+        assert_eq!(session.user_input, "");
+        assert_eq!(session.current_position, 0);
+    }
 
-    let digraphs = Paragraph::new(digraph_text)
-        .block(
-            Block::default()
-                .title("🔤 Letter Combinations")
-                .borders(Borders::ALL),
-        )
-        .wrap(Wrap { trim: true });
-    f.render_widget(digraphs, weakness_top[0]);
+    #[test]
+    fn delete_and_arrows_are_ignored_while_typing() {
+        let mut app = quote_app(UiOptions::default(), SessionConfig::default());
+        app.handle_event(key(KeyCode::Char('a'), event::KeyModifiers::NONE));
+        let before = {
+            let session = app.session.as_ref().unwrap();
+            (
+                session.user_input.clone(),
+                session.current_position,
+                session.keystroke_log.len(),
+            )
+        };
 
-    // Finger positioning errors
-    let finger_text = if report.weakness_analysis.finger_errors.is_empty() {
-        "✅ Perfect finger positioning!\n\nNo cross-finger errors detected.\n\n\n".to_string()
-    } else {
-        let mut text = "⚠️  FINGER POSITIONING ERRORS:\n".to_string();
-        let errors: Vec<_> = report
-            .weakness_analysis
-            .finger_errors
-            .iter()
-            .take(5)
-            .collect();
-        for (pattern, count) in &errors {
-            text.push_str(&format!("• {}: {} times\n", pattern, count));
+        for code in [
+            KeyCode::Delete,
+            KeyCode::Left,
+            KeyCode::Right,
+            KeyCode::Up,
+            KeyCode::Down,
+            KeyCode::Home,
+            KeyCode::End,
+        ] {
+            app.handle_event(key(code, event::KeyModifiers::NONE));
+            let session = app.session.as_ref().unwrap();
+            let after = (
+                session.user_input.clone(),
+                session.current_position,
+                session.keystroke_log.len(),
+            );
+            assert_eq!(after, before, "{code:?}");
+            assert!(session.errors.is_empty());
         }
-        // Add padding lines to maintain consistent height
-        for _ in errors.len()..5 {
-            text.push('\n');
+
+        app.handle_event(key(KeyCode::Char('b'), event::KeyModifiers::NONE));
+        assert!(app.state == AppState::ShowingReport);
+        assert_eq!(
+            app.session.as_ref().unwrap().generate_report().accuracy,
+            100.0
+        );
+    }
+
+    #[test]
+    fn source_meta_names_the_file_and_its_commit() {
+        // Outside a repository only the canonical path is known
+        let dir = std::env::temp_dir().join(format!("keysentry-meta-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("notes.txt");
+        fs::write(&path, "some words to type here").unwrap();
+        let meta = SourceMeta::for_path(&dir.join(".").join("notes.txt"));
+        assert_eq!(
+            meta.path,
+            fs::canonicalize(&path).unwrap().display().to_string()
+        );
+        assert_eq!(meta.commit, None);
+
+        let source = TextSource::load_from_file(&path, &snippet_options()).unwrap();
+        assert_eq!(source.source_meta().unwrap().path, meta.path);
+        fs::remove_dir_all(&dir).unwrap();
+
+        // A file that can't be resolved keeps the path it was given
+        let missing = SourceMeta::for_path(&dir.join("gone.txt"));
+        assert_eq!(missing.path, dir.join("gone.txt").display().to_string());
+        assert_eq!(missing.commit, None);
+
+        // Inside one, the checked out commit; skipped for builds outside a checkout
+        let manifest_dir = Path::new(env!("CARGO_MANIFEST_DIR"));
+        if manifest_dir.join(".git").exists() {
+            let meta = SourceMeta::for_path(&manifest_dir.join("src/main.rs"));
+            let commit = meta.commit.unwrap();
+            assert_eq!(commit.len(), 40);
+            assert!(commit.chars().all(|c| c.is_ascii_hexdigit()));
         }
-        text.push_str("Practice proper finger placement!");
-        text
-    };
 
-    let fingers = Paragraph::new(finger_text)
-        .block(
-            Block::default()
-                .title("👆 Finger Analysis")
-                .borders(Borders::ALL),
-        )
-        .wrap(Wrap { trim: true });
-    f.render_widget(fingers, weakness_top[1]);
+        let inception = TextSource::Inception(String::new(), None)
+            .source_meta()
+            .unwrap();
+        assert!(inception.path.starts_with("keysentry-"));
+        assert!(inception.path.ends_with("/src/main.rs"));
+        assert!(
+            TextSource::Quote(String::new(), Vec::new())
+                .source_meta()
+                .is_none()
+        );
+    }
 
-    // Analysis middle row
-    let weakness_mid = Layout::default()
-        .direction(Direction::Horizontal)
-        .constraints([Constraint::Percentage(50), Constraint::Percentage(50)])
-        .split(analysis_chunks[2]);
+    #[test]
+    fn frames_only_speed_up_while_something_moves() {
+        let type_char = |app: &mut App, c| {
+            app.handle_event(key(KeyCode::Char(c), event::KeyModifiers::NONE));
+        };
 
-    // Error clustering
-    let cluster_text = if report.weakness_analysis.error_clusters.is_empty() {
-        "✅ No error clustering detected!\nMistakes are well-distributed.".to_string()
-    } else {
-        let mut text = "⚠️  ERROR CLUSTERS FOUND:\n".to_string();
-        for (start, end) in &report.weakness_analysis.error_clusters {
-            text.push_str(&format!(
-                "• Positions {}-{} ({} chars)\n",
-                start,
-                end,
-                end - start + 1
-            ));
-        }
-        text.push_str("\nThese sections need extra practice!");
-        text
-    };
+        // Nothing on screen changes without input
+        let mut app = quote_app(UiOptions::default(), SessionConfig::default());
+        type_char(&mut app, 'a');
+        assert_eq!(frame_interval(&app), IDLE_FRAME_INTERVAL);
 
-    let clusters = Paragraph::new(cluster_text)
-        .block(
-            Block::default()
-                .title("🎯 Error Hotspots")
-                .borders(Borders::ALL),
-        )
-        .wrap(Wrap { trim: true });
-    f.render_widget(clusters, weakness_mid[0]);
+        let ui_options = UiOptions {
+            live_header: true,
+            ..Default::default()
+        };
+        let mut app = quote_app(ui_options, SessionConfig::default());
+        // The header waits for the first key, stops while paused and at the end
+        assert_eq!(frame_interval(&app), IDLE_FRAME_INTERVAL);
+        type_char(&mut app, 'a');
+        assert_eq!(frame_interval(&app), FRAME_INTERVAL);
+        app.session.as_mut().unwrap().pause_idle();
+        assert_eq!(frame_interval(&app), IDLE_FRAME_INTERVAL);
+        app.session.as_mut().unwrap().resume();
+        assert_eq!(frame_interval(&app), FRAME_INTERVAL);
+        type_char(&mut app, 'b');
+        assert!(app.state == AppState::ShowingReport);
+        assert_eq!(frame_interval(&app), IDLE_FRAME_INTERVAL);
+
+        for config in [
+            SessionConfig {
+                feedback: true,
+                ..Default::default()
+            },
+            SessionConfig {
+                target_wpm: Some(40),
+                ..Default::default()
+            },
+        ] {
+            let mut app = quote_app(UiOptions::default(), config);
+            type_char(&mut app, 'a');
+            assert_eq!(frame_interval(&app), FRAME_INTERVAL);
+        }
+    }
 
-    // Rhythm analysis
-    let rhythm_text = if report.weakness_analysis.rhythm_breaks.is_empty() {
-        "✅ Excellent rhythm consistency!\n\nSteady typing pace maintained.\n\n\n".to_string()
-    } else {
-        let break_count = report.weakness_analysis.rhythm_breaks.len();
-        let positions = if break_count <= 8 {
-            report
-                .weakness_analysis
-                .rhythm_breaks
-                .iter()
-                .map(|pos| pos.to_string())
-                .collect::<Vec<_>>()
-                .join(", ")
-        } else {
-            format!(
-                "{} (and {} more)",
-                report
-                    .weakness_analysis
-                    .rhythm_breaks
-                    .iter()
-                    .take(6)
-                    .map(|pos| pos.to_string())
-                    .collect::<Vec<_>>()
-                    .join(", "),
-                break_count - 6
-            )
+    #[test]
+    fn pomodoro_alternates_focus_and_breaks() {
+        let minutes = |m: u64| Duration::from_secs(m * 60);
+        let ui_options = UiOptions {
+            pomodoro: Some(minutes(25)),
+            ..Default::default()
         };
+        let mut app = quote_app(ui_options, SessionConfig::default());
+        let start = app.pomodoro.focus_start.unwrap();
+
+        // A running session is never interrupted
+        app.check_pomodoro(start + minutes(30));
+        assert!(app.state == AppState::Typing);
+        for c in "ab".chars() {
+            app.handle_event(key(KeyCode::Char(c), event::KeyModifiers::NONE));
+        }
+        assert!(app.state == AppState::ShowingReport);
+
+        // The interval is over: a break, then back to the report once it ends
+        app.check_pomodoro(start + minutes(31));
+        assert!(app.state == AppState::Break);
+        assert_eq!(
+            (app.pomodoro.intervals, app.pomodoro.focused),
+            (1, minutes(31))
+        );
+        app.check_pomodoro(start + minutes(31) + POMODORO_BREAK - Duration::from_secs(1));
+        assert!(app.state == AppState::Break);
+        app.check_pomodoro(start + minutes(31) + POMODORO_BREAK);
+        assert!(app.state == AppState::ShowingReport);
+        assert_eq!(app.pomodoro.focus_start, None);
+
+        // The next session opens a new interval, its break can be cut short
+        app.handle_event(key(KeyCode::Char('r'), event::KeyModifiers::NONE));
+        let start = app.pomodoro.focus_start.unwrap();
+        for c in "ab".chars() {
+            app.handle_event(key(KeyCode::Char(c), event::KeyModifiers::NONE));
+        }
+        app.check_pomodoro(start + minutes(24));
+        assert!(app.state == AppState::ShowingReport);
+        app.check_pomodoro(start + minutes(25));
+        assert!(app.state == AppState::Break);
+        assert_eq!(
+            (app.pomodoro.intervals, app.pomodoro.focused),
+            (2, minutes(56))
+        );
+        app.handle_event(key(KeyCode::Enter, event::KeyModifiers::NONE));
+        assert!(app.state == AppState::ShowingReport);
+        assert_eq!(app.pomodoro.break_end, None);
+    }
 
-        format!(
-            "⚠️  RHYTHM DISRUPTIONS:\n\
-                 • {} sudden slowdowns detected\n\
-                 • Positions: {}\n\n\
-                 Work on maintaining steady pace!",
-            break_count, positions
-        )
-    };
+    #[test]
+    fn flagged_blocks_match_inside_larger_passages() {
+        let block = "this block is long enough to count";
+        let passage = format!(
+            "tiny\n\n{}\nsecond line\n\nanother block that is long enough",
+            block
+        );
+        let hashes = block_hashes(&passage);
+        assert_eq!(hashes.len(), 2);
+        assert_eq!(hashes[0], content_hash(&format!("{}\nsecond line", block)));
+
+        let mut options = snippet_options();
+        options.excluded = hashes.into_iter().take(1).collect();
+        assert!(options.is_excluded(&format!("{}\nsecond line", block)));
+        assert!(!options.is_excluded("unrelated text that is long enough"));
+    }
 
-    let rhythm = Paragraph::new(rhythm_text)
-        .block(
-            Block::default()
-                .title("⏱️  Rhythm Analysis")
-                .borders(Borders::ALL),
-        )
-        .wrap(Wrap { trim: true });
-    f.render_widget(rhythm, weakness_mid[1]);
+    #[test]
+    fn repeated_blocks_are_flagged_once() {
+        let path = std::env::temp_dir().join(format!("keysentry-excluded-{}", std::process::id()));
+        let first = "the first block is long enough";
+        let second = "the second block is long enough";
+        let passage = format!("{first}\n\n{second}\n\n{first}");
+
+        let added = persist_excluded_snippet(&passage, &path).unwrap();
+        assert_eq!(added, vec![content_hash(first), content_hash(second)]);
+        assert!(
+            persist_excluded_snippet(&passage, &path)
+                .unwrap()
+                .is_empty()
+        );
+        assert_eq!(load_excluded_snippets(&path).unwrap().len(), 2);
+        fs::remove_file(&path).unwrap();
+    }
 
-    // Detailed recommendations
-    let mut recommendations = "🎯 PERSONALIZED IMPROVEMENT PLAN:\n\n".to_string();
+    #[test]
+    fn only_sources_that_skip_flagged_passages_can_be_flagged() {
+        let file = |name: &str| TextSource::File(name.to_string(), String::new(), None, None);
+        assert!(file("main.rs").honours_exclusions());
+        assert!(file("docs: serde").honours_exclusions());
+        assert!(!file("symbols drill").honours_exclusions());
+        assert!(!file("drill: report.json").honours_exclusions());
+        assert!(!file("ghost race").honours_exclusions());
+        assert!(!TextSource::Quote("ab".to_string(), Vec::new()).honours_exclusions());
+    }
 
-    if report.wpm < 30.0 {
-        recommendations
-            .push_str("🐌 SPEED: Focus on accuracy first, then gradually increase pace\n");
-    } else if report.wpm < 50.0 {
-        recommendations.push_str("🚶 SPEED: Good foundation! Work on consistent 40+ WPM\n");
-    } else {
-        recommendations
-            .push_str("🏃 SPEED: Excellent! Maintain this pace while improving accuracy\n");
+    #[test]
+    fn chars_override_the_size_preset() {
+        let range: CharRange = "200:400".parse().unwrap();
+        assert_eq!(range, CharRange { min: 200, max: 400 });
+        assert!("400:200".parse::<CharRange>().is_err());
+        assert!("0:10".parse::<CharRange>().is_err());
+        assert!("10".parse::<CharRange>().is_err());
+
+        let mut options = snippet_options();
+        options.chars = Some(range);
+        assert_eq!(options.char_range(), (200, 400));
+        assert_eq!(options.line_range(), (5, 10));
     }
 
-    if report.accuracy < 90.0 {
-        recommendations.push_str("❌ ACCURACY: Slow down and focus on correct keystrokes\n");
-    } else if report.accuracy < 97.0 {
-        recommendations.push_str("✓ ACCURACY: Good! Aim for 97%+ accuracy\n");
-    } else {
-        recommendations.push_str("🎯 ACCURACY: Excellent precision! Keep it up!\n");
+    #[test]
+    fn registry_versions_compare_numerically() {
+        assert!(version_key("1.0.10") > version_key("1.0.9"));
+        assert!(version_key("1.10.0") > version_key("1.9.9"));
+        assert!(version_key("2.0.0") > version_key("2.0.0-rc.1"));
+        assert!(version_key("2.0.0-rc.1") > version_key("1.99.0"));
     }
 
-    if !report.weakness_analysis.slowest_digraphs.is_empty() {
-        recommendations.push_str("🔤 PRACTICE: Drill slow letter combinations separately\n");
+    #[test]
+    fn doc_examples_are_extracted_from_rust_fences() {
+        let source = "\
+/// Example:
+/// ```
+/// let x = 1;
+/// # hidden();
+/// ## attr
+/// ```
+/// ```text
+/// not rust
+/// ```
+//! ```rust,no_run
+//! run();
+fn f() {}
+";
+        assert_eq!(doc_code_blocks(source), ["let x = 1;\n# attr"]);
     }
 
-    if !report.weakness_analysis.finger_errors.is_empty() {
-        recommendations.push_str("👆 FINGERS: Review proper finger positioning\n");
+    #[test]
+    fn sentences_split_on_terminators() {
+        let text = "Hi there. How are you? Fine!";
+        assert_eq!(sentence_spans(text), [(0, 9), (10, 22), (23, 28)]);
+        // A trailing fragment without a terminator is a sentence too
+        assert_eq!(sentence_spans("One. two  "), [(0, 4), (5, 8)]);
     }
 
-    if report.weakness_analysis.rhythm_breaks.len() > 3 {
-        recommendations.push_str("⏱️  RHYTHM: Practice with metronome for consistency\n");
+    #[test]
+    fn abbreviations_and_initials_do_not_end_sentences() {
+        let text = "See e.g. J. R. R. Tolkien. Then U.S. stuff.";
+        assert_eq!(sentence_spans(text), [(0, 26), (27, 43)]);
+        // A one-letter word that isn't an initial still ends the sentence
+        assert_eq!(sentence_spans("So do I. Me too."), [(0, 8), (9, 16)]);
+        assert_eq!(sentence_spans("Take x. Then y."), [(0, 7), (8, 15)]);
     }
 
-    recommendations.push_str(
-        "\n💡 NEXT STEPS:\n\
-                              1. Practice identified weak areas daily\n\
-                              2. Use typing games for problem keys\n\
-                              3. Maintain proper posture and hand position\n\
-                              4. Take breaks to avoid fatigue",
-    );
+    #[test]
+    fn half_typed_sentences_count_only_typed_positions() {
+        let text = "Go. abcdefghij.";
+        let full = replay(text, text, 100, SessionConfig::default()).generate_report();
+        let half = replay(text, "Go. abcde", 100, SessionConfig::default()).generate_report();
+        assert_eq!(half.sentence_stats.len(), 2);
+        let (full_wpm, half_wpm) = (full.sentence_stats[1].wpm, half.sentence_stats[1].wpm);
+        // Same pace either way, rather than the whole sentence over half the time
+        assert!(
+            (full_wpm - half_wpm).abs() < 1.0,
+            "{} vs {}",
+            full_wpm,
+            half_wpm
+        );
+    }
 
-    let rec_widget = Paragraph::new(recommendations)
-        .block(
-            Block::default()
-                .title("📋 Action Plan")
-                .borders(Borders::ALL),
-        )
-        .wrap(Wrap { trim: true })
-        .style(Style::default().fg(Color::Yellow));
-    f.render_widget(rec_widget, analysis_chunks[3]);
+    #[test]
+    fn sudden_death_ends_on_the_first_error() {
+        let config = SessionConfig {
+            sudden_death: true,
+            ..Default::default()
+        };
+        let session = replay("abcd", "abxd", 100, config);
+        assert!(session.is_complete());
+        assert_eq!(session.generate_report().error_free_chars, Some(2));
+    }
 
-    // Educational sidebar
-    let education_text = "DETAILED INSIGHTS GUIDE\n\n\
-        🔍 UNDERSTANDING THE DATA:\n\
-        • Performance Metrics: Core stats vs targets\n\
-        • Letter Combinations: Transition speed analysis\n\
-        • Finger Analysis: Hand positioning accuracy\n\
-        • Error Hotspots: Problem text sections\n\
-        • Rhythm Analysis: Pace consistency check\n\n\
-        📈 SKILL DEVELOPMENT:\n\
-        • Focus on one weakness at a time\n\
-        • Use targeted practice exercises\n\
-        • Track progress over multiple sessions\n\
-        • Build muscle memory gradually\n\n\
-        🏆 IMPROVEMENT STRATEGY:\n\
-        • Accuracy before speed\n\
-        • Consistent practice beats marathon sessions\n\
-        • Learn from each mistake\n\
-        • Celebrate small improvements\n\n\
-        ← Switch to 'Visual Analysis'\n\
-        for charts and graphs";
+    #[test]
+    fn key_stats_from_older_reports_load_in_microseconds() {
+        let old = r#"{"key":"a","count":2,"total_latency":{"secs":0,"nanos":300000000},
+            "error_count":0,"latencies":[100,200],"positions":[0,1]}"#;
+        let stat: KeyStat = serde_json::from_str(old).unwrap();
+        assert_eq!(stat.latencies_us, [100_000, 200_000]);
+
+        let report = replay("ab", "ab", 100, SessionConfig::default()).generate_report();
+        let json = serde_json::to_string(&report).unwrap();
+        let loaded: SessionReport = serde_json::from_str(&json).unwrap();
+        assert_eq!(
+            loaded.key_stats[&'b'].latencies_us,
+            report.key_stats[&'b'].latencies_us
+        );
+    }
 
-    let education = Paragraph::new(education_text)
-        .wrap(Wrap { trim: true })
-        .style(Style::default().fg(Color::DarkGray))
-        .block(Block::default().padding(Padding::uniform(2)));
-    f.render_widget(education, main_chunks[1]);
-}
+    #[test]
+    fn per_key_accuracy_counts_errors() {
+        let stat = KeyStat {
+            key: 'a',
+            count: 4,
+            total_latency: Duration::ZERO,
+            error_count: 1,
+            latencies_us: Vec::new(),
+            positions: Vec::new(),
+            error_flags: Vec::new(),
+        };
+        assert_eq!(stat.accuracy(), Some(75.0));
+        assert_eq!(KeyStat { count: 0, ..stat }.accuracy(), None);
+    }
 
-fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let cli = Cli::parse();
+    #[test]
+    fn verbose_errors_carry_their_context() {
+        let session = replay("hello world", "hello wx", 100, SessionConfig::default());
+        let error = &session.errors[0];
+        assert_eq!(error.context_before, "llo w");
+        assert_eq!(error.context_after, "rld");
+    }
 
-    // Determine the text source based on CLI arguments
-    let text_source = if cli.inception {
-        TextSource::load_inception(cli.size)?
-    } else if let Some(file_path) = cli.file {
-        TextSource::load_from_file(&file_path, cli.size)?
-    } else {
-        // Error: user must specify either --file or --inception
-        eprintln!("Error: You must specify either --file <path> or --inception");
-        eprintln!("Run with --help for usage information");
-        std::process::exit(1);
-    };
+    #[test]
+    fn hardest_sentence_is_the_slowest() {
+        let text = "Ab. Cd.";
+        let log: Vec<(char, Duration)> = [
+            (0, 'A'),
+            (100, 'b'),
+            (200, '.'),
+            (300, ' '),
+            (1300, 'C'),
+            (2300, 'd'),
+            (3300, '.'),
+        ]
+        .iter()
+        .map(|&(ms, key)| (key, Duration::from_millis(ms)))
+        .collect();
+        let report =
+            TypingSession::from_keystroke_log(text.to_string(), &log, SessionConfig::default())
+                .generate_report();
+        assert_eq!(report.sentence_stats.len(), 2);
+        assert_eq!(report.hardest_sentence().map(|s| s.start), Some(4));
+    }
 
-    enable_raw_mode()?;
-    let mut stdout = io::stdout();
-    execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
-    let backend = CrosstermBackend::new(stdout);
-    let mut terminal = Terminal::new(backend)?;
+    #[test]
+    fn calibration_scales_the_median_latency() {
+        let calibrate = |step_ms| {
+            let text = "a".repeat(CALIBRATION_MIN_KEYS + 1);
+            let report = replay(&text, &text, step_ms, SessionConfig::default()).generate_report();
+            (Calibration::from_report(&report).unwrap(), report.wpm)
+        };
 
-    let mut app = App::new(text_source)?;
+        // 137ms * 2.5 = 342ms, rounded up to 350
+        let (calibration, wpm) = calibrate(137);
+        assert_eq!(calibration.hesitation_ms, 350);
+        assert_eq!(calibration.target_wpm, (wpm * 1.1).round() as u32);
+        assert_eq!(calibrate(20).0.hesitation_ms, 250);
+        assert_eq!(calibrate(1000).0.hesitation_ms, 1500);
 
-    loop {
-        terminal.draw(|f| match app.state {
-            AppState::Typing => ui_typing(f, &app),
-            AppState::ShowingReport => ui_report(f, &app),
-        })?;
+        let text = "a".repeat(CALIBRATION_MIN_KEYS);
+        let report = replay(&text, &text, 100, SessionConfig::default()).generate_report();
+        assert_eq!(Calibration::from_report(&report), None);
+    }
 
-        if event::poll(Duration::from_millis(50))? {
-            app.handle_event(event::read()?)?;
-        }
+    #[test]
+    fn broken_preferences_fail_to_load() {
+        let path = std::env::temp_dir().join(format!("keysentry-prefs-{}", std::process::id()));
+        fs::write(&path, "{ not json").unwrap();
+        let error = Preferences::load_from(&path).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        assert!(error.to_string().contains(&path.display().to_string()));
+        fs::remove_file(&path).unwrap();
+    }
 
-        if app.should_quit {
-            break;
-        }
+    #[test]
+    fn piped_stdin_is_the_source_only_without_a_source_flag() {
+        let cli = Cli::parse_from(["keysentry"]);
+        assert!(matches!(
+            SourceChoice::from_cli(&cli, true),
+            SourceChoice::Browse
+        ));
+        assert!(matches!(
+            SourceChoice::from_cli(&cli, false),
+            SourceChoice::Stdin
+        ));
+
+        let cli = Cli::parse_from(["keysentry", "--quotes"]);
+        assert!(matches!(
+            SourceChoice::from_cli(&cli, false),
+            SourceChoice::Quotes
+        ));
+        let cli = Cli::parse_from(["keysentry", "--file", "notes.txt"]);
+        assert!(matches!(
+            SourceChoice::from_cli(&cli, false),
+            SourceChoice::File(_)
+        ));
     }
 
-    disable_raw_mode()?;
-    execute!(
-        terminal.backend_mut(),
-        LeaveAlternateScreen,
-        DisableMouseCapture
-    )?;
-    terminal.show_cursor()?;
+    #[test]
+    fn until_wpm_waits_for_the_pace_to_hold() {
+        // Cumulative WPM samples each second, at 60 WPM apart from a slow
+        // patch at 20 WPM between 20s and 30s
+        let mut words = 0.0;
+        let series: Vec<(Duration, f64)> = (1..=80u64)
+            .map(|second| {
+                words += if (21..=30).contains(&second) {
+                    1.0 / 3.0
+                } else {
+                    1.0
+                };
+                (Duration::from_secs(second), words * 60.0 / second as f64)
+            })
+            .collect();
+        let reached = |series: &[(Duration, f64)]| {
+            sustained_wpm_since(series, 50.0, UNTIL_WPM_SMOOTHING, UNTIL_WPM_HOLD)
+        };
 
-    Ok(())
+        // Held from 10s (the first full window) would need until 40s; the
+        // slow patch resets it until its window has mostly passed
+        assert_eq!(reached(&series), Some(Duration::from_secs(38)));
+        assert_eq!(reached(&series[..60]), None);
+        assert_eq!(reached(&series[..20]), None);
+    }
+
+    #[test]
+    fn until_wpm_is_left_out_of_zen_and_the_warmup() {
+        assert!(Cli::try_parse_from(["keysentry", "--zen", "--until-wpm", "40"]).is_err());
+
+        let ui_options = UiOptions {
+            warmup: true,
+            ..Default::default()
+        };
+        let config = SessionConfig {
+            until_wpm: Some(40),
+            ..Default::default()
+        };
+        let app = quote_app(ui_options, config);
+        assert!(app.warming_up);
+        let session = app.session.as_ref().unwrap();
+        assert!(!session.wants_more_text());
+    }
+
+    #[test]
+    fn drill_passages_repeat_shuffled_rounds() {
+        let words = ["alpha".to_string(), "beta".to_string()];
+        let passage = drill_passage(&words, 40, Some(1));
+        assert!(passage.chars().count() >= 40);
+        assert!(
+            passage
+                .split_whitespace()
+                .all(|w| w == "alpha" || w == "beta")
+        );
+        assert_eq!(passage, drill_passage(&words, 40, Some(1)));
+    }
 }